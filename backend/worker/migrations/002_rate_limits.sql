@@ -0,0 +1,16 @@
+ALTER TABLE users ADD COLUMN image_limit_per_minute INTEGER;
+ALTER TABLE users ADD COLUMN image_limit_per_day INTEGER;
+ALTER TABLE users ADD COLUMN video_limit_per_minute INTEGER;
+ALTER TABLE users ADD COLUMN video_limit_per_day INTEGER;
+ALTER TABLE users ADD COLUMN status_limit_per_minute INTEGER;
+
+CREATE TABLE rate_limit_counters (
+    user_id INTEGER NOT NULL,
+    bucket TEXT NOT NULL,
+    window_start INTEGER NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, bucket, window_start),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_rate_limit_counters_window ON rate_limit_counters(window_start);
@@ -0,0 +1,19 @@
+CREATE TABLE generations (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    parameters TEXT NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL,
+    error_code TEXT,
+    operation_name TEXT,
+    latency_ms INTEGER,
+    output_bytes INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_generations_user_created ON generations(user_id, created_at);
+CREATE INDEX idx_generations_operation ON generations(operation_name);
@@ -0,0 +1,13 @@
+CREATE TABLE assets (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    generation_id TEXT,
+    content_type TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (generation_id) REFERENCES generations(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_assets_user ON assets(user_id);
+CREATE INDEX idx_assets_generation ON assets(generation_id);
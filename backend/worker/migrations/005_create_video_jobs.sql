@@ -0,0 +1,20 @@
+CREATE TABLE video_jobs (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    operation_name TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    parameters TEXT NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL DEFAULT 'pending',
+    error_code TEXT,
+    generation_id TEXT,
+    asset_id TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (generation_id) REFERENCES generations(id) ON DELETE SET NULL,
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_video_jobs_user ON video_jobs(user_id, created_at);
+CREATE INDEX idx_video_jobs_status ON video_jobs(status);
@@ -0,0 +1,3 @@
+ALTER TABLE video_jobs ADD COLUMN origin TEXT;
+
+CREATE INDEX idx_video_jobs_pending ON video_jobs(status, updated_at);
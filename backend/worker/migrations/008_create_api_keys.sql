@@ -0,0 +1,19 @@
+CREATE TABLE api_keys (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT UNIQUE NOT NULL,
+    revoked INTEGER NOT NULL DEFAULT 0,
+    revoked_at DATETIME,
+    last_used_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_api_keys_user ON api_keys(user_id, created_at);
+
+-- Existing plaintext keys in users.api_key keep working. SQLite cannot hash
+-- them here, so the worker moves each one into api_keys the first time it
+-- is presented, and the scheduled handler sweeps up the rest. users.api_key
+-- is left holding a SHA-256 hash, which is never accepted as a key.
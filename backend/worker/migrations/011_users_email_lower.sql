@@ -0,0 +1,3 @@
+-- Emails are now stored lowercased and looked up with lower(email) so that
+-- accounts registered before normalization still match.
+CREATE INDEX idx_users_email_lower ON users(lower(email));
@@ -0,0 +1,24 @@
+-- Failed logins, counted separately per account (normalized email) and per
+-- client IP. Times are unix seconds so the backoff can be computed in code.
+CREATE TABLE login_failures (
+    scope TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failed_at INTEGER NOT NULL,
+    locked_until INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (scope, subject)
+);
+
+CREATE TABLE security_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event TEXT NOT NULL,
+    user_id INTEGER,
+    email TEXT,
+    ip TEXT,
+    details TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_security_events_user ON security_events(user_id, created_at);
+CREATE INDEX idx_security_events_email ON security_events(email, created_at);
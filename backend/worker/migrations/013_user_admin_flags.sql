@@ -0,0 +1,6 @@
+ALTER TABLE users ADD COLUMN is_admin INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN disabled_at DATETIME;
+ALTER TABLE users ADD COLUMN disabled_reason TEXT;
+
+-- There is no route that grants admin. Promote the first operator by hand:
+--   wrangler d1 execute geminipocket --command "UPDATE users SET is_admin = 1 WHERE lower(email) = 'you@example.com'"
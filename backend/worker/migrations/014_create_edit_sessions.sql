@@ -0,0 +1,27 @@
+CREATE TABLE edit_sessions (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    -- JSON array of asset ids given at creation; they open the first turn.
+    image_asset_ids TEXT NOT NULL DEFAULT '[]',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_edit_sessions_user ON edit_sessions(user_id, created_at);
+
+CREATE TABLE edit_session_turns (
+    session_id TEXT NOT NULL,
+    turn INTEGER NOT NULL,
+    prompt TEXT NOT NULL,
+    input_asset_ids TEXT NOT NULL DEFAULT '[]',
+    output_asset_id TEXT,
+    text TEXT,
+    generation_id TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (session_id, turn),
+    FOREIGN KEY (session_id) REFERENCES edit_sessions(id) ON DELETE CASCADE,
+    FOREIGN KEY (output_asset_id) REFERENCES assets(id) ON DELETE SET NULL,
+    FOREIGN KEY (generation_id) REFERENCES generations(id) ON DELETE SET NULL
+);
@@ -0,0 +1,5 @@
+-- A turn is claimed as 'pending' before the model is called, so a second
+-- turn sent meanwhile is refused instead of racing for the same number.
+ALTER TABLE edit_session_turns ADD COLUMN status TEXT NOT NULL DEFAULT 'done';
+
+CREATE INDEX idx_edit_sessions_updated ON edit_sessions(updated_at);
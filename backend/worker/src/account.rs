@@ -0,0 +1,180 @@
+use serde::Deserialize;
+use serde_json::json;
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+use crate::login_guard::{self, LoginGuard};
+use crate::validation::{self, FieldError};
+use crate::{assets, rate_limit, sessions, usage};
+use crate::{authorize, hash_password, parse_json, verify_password, AuthUser};
+
+/// How far back the usage summary in `GET /me` looks.
+const USAGE_DAYS: u32 = 30;
+
+#[derive(Deserialize)]
+struct UpdateAccountRequest {
+    current_password: String,
+    email: Option<String>,
+    new_password: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DeleteAccountRequest {
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct AccountRow {
+    email: String,
+    password_hash: String,
+    created_at: String,
+    updated_at: Option<String>,
+}
+
+async fn load(env: &Env, user_id: i32) -> ApiResult<AccountRow> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT email, password_hash, created_at, updated_at FROM users WHERE id = ?");
+    let query = statement.bind_refs(&[D1Type::Integer(user_id)])?;
+    query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Account not found"))
+}
+
+/// Checks the current password before a change to the account. Wrong
+/// guesses count towards the same lockout as `/login`, so a stolen session
+/// cannot be used to brute-force the password.
+async fn reverify(req: &Request, env: &Env, user: &AuthUser, account: &AccountRow, password: &str) -> ApiResult<()> {
+    let email = validation::normalize_email(&user.email);
+    let guard = LoginGuard::check(env, &email, login_guard::client_ip(req)).await?;
+    if !verify_password(password, &account.password_hash)? {
+        let error = ApiError::new(403, "invalid_password", "Current password is incorrect");
+        return Err(guard.failed(env, error).await);
+    }
+    guard.succeeded(env).await;
+    Ok(())
+}
+
+/// `GET /me` — the caller's account, the limits that apply to it and a
+/// summary of recent usage.
+pub async fn handle_get(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let account = load(&ctx.env, user.id).await?;
+    let totals = usage::summary(&ctx.env, user.id, USAGE_DAYS).await?;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "user": {
+            "id": user.id,
+            "email": account.email,
+            "created_at": account.created_at,
+            "updated_at": account.updated_at,
+            "plan": rate_limit::plan(&user),
+        },
+        "usage": {
+            "days": USAGE_DAYS,
+            "totals": totals,
+        },
+    }))?)
+}
+
+/// `PATCH /me` — changes the email, the password or both. Needs the current
+/// password. A new password ends every session; API keys keep working.
+pub async fn handle_update(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: UpdateAccountRequest = parse_json(&mut req).await?;
+    if body.email.is_none() && body.new_password.is_none() {
+        return Err(ApiError::bad_request("Nothing to change: send email and/or new_password"));
+    }
+
+    let account = load(&ctx.env, user.id).await?;
+    let email = body.email.as_deref().map(validation::normalize_email);
+    let mut errors: Vec<FieldError> = email.as_deref().and_then(validation::check_email).into_iter().collect();
+    if let Some(password) = &body.new_password {
+        let email = email.as_deref().unwrap_or(&account.email);
+        errors.extend(validation::check_password(password, Some(&validation::normalize_email(email))));
+    }
+    if !errors.is_empty() {
+        return Err(validation::into_api_error(errors));
+    }
+
+    reverify(&req, &ctx.env, &user, &account, &body.current_password).await?;
+
+    let db = ctx.env.d1("DB")?;
+    let client_ip = login_guard::client_ip(&req);
+    let email_changed = email
+        .as_deref()
+        .is_some_and(|email| email != validation::normalize_email(&account.email));
+
+    if let Some(email) = email.as_deref().filter(|_| email_changed) {
+        let statement = db.prepare("SELECT 1 AS taken FROM users WHERE lower(email) = ? AND id != ?");
+        let query = statement.bind_refs(&[D1Type::Text(email), D1Type::Integer(user.id)])?;
+        if query.first::<i32>(Some("taken")).await?.is_some() {
+            return Err(ApiError::conflict("email_taken", "Another account already uses that email"));
+        }
+
+        let statement = db.prepare("UPDATE users SET email = ?, updated_at = datetime('now') WHERE id = ?");
+        statement.bind_refs(&[D1Type::Text(email), D1Type::Integer(user.id)])?.run().await?;
+        login_guard::record_event(
+            &ctx.env,
+            "email_changed",
+            Some(email),
+            client_ip.as_deref(),
+            json!({ "previous_email": account.email }),
+        )
+        .await;
+    }
+
+    let password_changed = body.new_password.is_some();
+    if let Some(password) = &body.new_password {
+        let password_hash = hash_password(password)?;
+        let statement = db.prepare("UPDATE users SET password_hash = ?, updated_at = datetime('now') WHERE id = ?");
+        statement.bind_refs(&[D1Type::Text(&password_hash), D1Type::Integer(user.id)])?.run().await?;
+        sessions::revoke_all(&ctx.env, user.id).await?;
+
+        let email = email.as_deref().unwrap_or(&account.email);
+        login_guard::record_event(&ctx.env, "password_changed", Some(email), client_ip.as_deref(), json!({})).await;
+    }
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "email": email.unwrap_or(account.email),
+        "email_changed": email_changed,
+        "password_changed": password_changed,
+        "sessions_revoked": password_changed,
+    }))?)
+}
+
+/// `DELETE /me` — deletes the account after checking the password. Keys,
+/// sessions, history, jobs and stored assets go with it. `security_events`
+/// keeps its rows with the user id cleared.
+pub async fn handle_delete(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: DeleteAccountRequest = parse_json(&mut req).await?;
+    let account = load(&ctx.env, user.id).await?;
+    reverify(&req, &ctx.env, &user, &account, &body.password).await?;
+
+    assets::delete_all(&ctx.env, user.id)
+        .await
+        .map_err(|e| ApiError::internal_logged(format!("Failed to delete stored assets: {}", e)))?;
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare("DELETE FROM users WHERE id = ?");
+    statement.bind_refs(&[D1Type::Integer(user.id)])?.run().await?;
+
+    let email = validation::normalize_email(&account.email);
+    login_guard::record_event(
+        &ctx.env,
+        "account_deleted",
+        Some(&email),
+        login_guard::client_ip(&req).as_deref(),
+        json!({ "user_id": user.id }),
+    )
+    .await;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "deleted": true,
+    }))?)
+}
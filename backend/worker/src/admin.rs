@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+use crate::history::escape_like;
+use crate::login_guard;
+use crate::{api_keys, rate_limit, sessions, usage, validation};
+use crate::{authorize, bool_from_int, parse_json, AuthUser, AUTH_USER_COLUMNS};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+/// How far back the usage totals in the user detail look.
+const USAGE_DAYS: u32 = 30;
+
+const MAX_REASON_LEN: usize = 500;
+
+/// A user as operators see it.
+#[derive(Deserialize, Serialize)]
+struct AdminUser {
+    id: i32,
+    email: String,
+    #[serde(deserialize_with = "bool_from_int")]
+    is_admin: bool,
+    disabled_at: Option<String>,
+    disabled_reason: Option<String>,
+    created_at: String,
+    active_keys: u32,
+}
+
+const ADMIN_USER_COLUMNS: &str = "id, email, is_admin, disabled_at, disabled_reason, created_at, \
+    (SELECT COUNT(*) FROM api_keys k WHERE k.user_id = users.id AND k.revoked = 0) AS active_keys";
+
+#[derive(Deserialize, Default)]
+struct DisableRequest {
+    reason: Option<String>,
+}
+
+/// Like [`authorize`], but only lets admins through.
+async fn authorize_admin(req: &Request, env: &Env) -> ApiResult<AuthUser> {
+    let user = authorize(req, env).await?;
+    if !user.is_admin {
+        return Err(ApiError::forbidden("Admin access required"));
+    }
+    Ok(user)
+}
+
+fn user_id(ctx: &RouteContext<Context>) -> ApiResult<i32> {
+    ctx.param("id")
+        .and_then(|id| id.parse::<i32>().ok())
+        .ok_or_else(|| ApiError::bad_request("Invalid user id"))
+}
+
+async fn find(env: &Env, id: i32) -> ApiResult<AdminUser> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare(format!("SELECT {} FROM users WHERE id = ?", ADMIN_USER_COLUMNS));
+    let query = statement.bind_refs(&[D1Type::Integer(id)])?;
+    query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::not_found("User not found"))
+}
+
+/// Operator actions go to `security_events` under the affected account,
+/// with the acting admin in the details.
+async fn audit(env: &Env, event: &str, target: &AdminUser, admin: &AuthUser, details: serde_json::Value) {
+    let mut details = details;
+    details["admin_id"] = json!(admin.id);
+    login_guard::record_event(env, event, Some(&validation::normalize_email(&target.email)), None, details).await;
+}
+
+/// `GET /admin/users` — all users, newest first.
+///
+/// Query parameters: `q` (a case-insensitive substring of the email),
+/// `disabled` (`true` or `false`), `limit` and `cursor` (the `next_cursor`
+/// of the previous page).
+pub async fn handle_list_users(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    authorize_admin(&req, &ctx.env).await?;
+    let url = req.url()?;
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+
+    let limit = match param("limit") {
+        Some(value) => value
+            .parse::<u32>()
+            .ok()
+            .filter(|limit| (1..=MAX_PAGE_SIZE).contains(limit))
+            .ok_or_else(|| ApiError::bad_request(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)))?,
+        None => DEFAULT_PAGE_SIZE,
+    };
+
+    let mut sql = format!("SELECT {} FROM users WHERE 1 = 1", ADMIN_USER_COLUMNS);
+    let mut texts: Vec<String> = Vec::new();
+    let mut cursor = None;
+
+    if let Some(q) = param("q").filter(|q| !q.trim().is_empty()) {
+        sql.push_str(" AND lower(email) LIKE ? ESCAPE '\\'");
+        texts.push(format!("%{}%", escape_like(&q.trim().to_lowercase())));
+    }
+    match param("disabled").as_deref() {
+        Some("true") => sql.push_str(" AND disabled_at IS NOT NULL"),
+        Some("false") => sql.push_str(" AND disabled_at IS NULL"),
+        Some(_) => return Err(ApiError::bad_request("disabled must be true or false")),
+        None => {}
+    }
+    if let Some(value) = param("cursor") {
+        cursor = Some(value.parse::<i32>().map_err(|_| ApiError::bad_request("Invalid cursor"))?);
+        sql.push_str(" AND id < ?");
+    }
+    sql.push_str(" ORDER BY id DESC LIMIT ?");
+
+    let mut binds: Vec<D1Type> = texts.iter().map(|text| D1Type::Text(text)).collect();
+    if let Some(cursor) = cursor {
+        binds.push(D1Type::Integer(cursor));
+    }
+    binds.push(D1Type::Integer(limit as i32 + 1));
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(&sql);
+    let query = statement.bind_refs(&binds)?;
+    let mut users: Vec<AdminUser> = query.all().await?.results()?;
+
+    let next_cursor = if users.len() > limit as usize {
+        users.truncate(limit as usize);
+        users.last().map(|last| last.id.to_string())
+    } else {
+        None
+    };
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "users": users,
+        "next_cursor": next_cursor,
+    }))?)
+}
+
+/// `GET /admin/users/{id}` — one user with their limits, keys and recent
+/// usage totals.
+pub async fn handle_get_user(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    authorize_admin(&req, &ctx.env).await?;
+    let id = user_id(&ctx)?;
+    let user = find(&ctx.env, id).await?;
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(format!("SELECT {} FROM users u WHERE u.id = ?", AUTH_USER_COLUMNS));
+    let query = statement.bind_refs(&[D1Type::Integer(id)])?;
+    let limits: Option<AuthUser> = query.first(None).await?;
+    let plan = limits.as_ref().map(rate_limit::plan);
+
+    let keys = api_keys::list(&ctx.env, id).await?;
+    let totals = usage::summary(&ctx.env, id, USAGE_DAYS).await?;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "user": user,
+        "plan": plan,
+        "keys": keys,
+        "usage": {
+            "days": USAGE_DAYS,
+            "totals": totals,
+        },
+    }))?)
+}
+
+/// `POST /admin/users/{id}/disable` — blocks the user's keys and sessions
+/// and refuses their logins until re-enabled. Takes an optional `reason`.
+pub async fn handle_disable_user(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let admin = authorize_admin(&req, &ctx.env).await?;
+    let id = user_id(&ctx)?;
+    if id == admin.id {
+        return Err(ApiError::bad_request("You cannot disable your own account"));
+    }
+    let body: DisableRequest = parse_json(&mut req).await.unwrap_or_default();
+    let reason = body.reason.map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty());
+    if reason.as_ref().is_some_and(|reason| reason.chars().count() > MAX_REASON_LEN) {
+        return Err(ApiError::bad_request(format!("reason must be at most {} characters", MAX_REASON_LEN)));
+    }
+    find(&ctx.env, id).await?;
+
+    // Disabling again only updates the reason; the original time is kept.
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(
+        "UPDATE users SET disabled_at = COALESCE(disabled_at, datetime('now')), disabled_reason = ?, \
+         updated_at = datetime('now') WHERE id = ?",
+    );
+    let query = statement.bind_refs(&[
+        reason.as_deref().map(D1Type::Text).unwrap_or(D1Type::Null),
+        D1Type::Integer(id),
+    ])?;
+    query.run().await?;
+
+    // Sessions are rejected while disabled anyway; ending them means they
+    // stay ended after the user is enabled again.
+    sessions::revoke_all(&ctx.env, id).await?;
+
+    let user = find(&ctx.env, id).await?;
+    audit(&ctx.env, "account_disabled", &user, &admin, json!({ "reason": reason })).await;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "user": user,
+    }))?)
+}
+
+/// `POST /admin/users/{id}/enable` — lifts a disable. Keys that were not
+/// revoked work again.
+pub async fn handle_enable_user(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let admin = authorize_admin(&req, &ctx.env).await?;
+    let id = user_id(&ctx)?;
+    find(&ctx.env, id).await?;
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(
+        "UPDATE users SET disabled_at = NULL, disabled_reason = NULL, updated_at = datetime('now') WHERE id = ?",
+    );
+    statement.bind_refs(&[D1Type::Integer(id)])?.run().await?;
+
+    let user = find(&ctx.env, id).await?;
+    audit(&ctx.env, "account_enabled", &user, &admin, json!({})).await;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "user": user,
+    }))?)
+}
+
+/// `POST /admin/users/{id}/reset_quota` — clears the user's rate limit
+/// counters, so every window starts from zero.
+pub async fn handle_reset_quota(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let admin = authorize_admin(&req, &ctx.env).await?;
+    let id = user_id(&ctx)?;
+    let user = find(&ctx.env, id).await?;
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare("DELETE FROM rate_limit_counters WHERE user_id = ?");
+    let result = statement.bind_refs(&[D1Type::Integer(id)])?.run().await?;
+    let cleared = result.meta()?.and_then(|meta| meta.changes).unwrap_or(0);
+
+    audit(&ctx.env, "quota_reset", &user, &admin, json!({ "cleared": cleared })).await;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "user_id": id,
+        "cleared": cleared,
+    }))?)
+}
+
+/// `DELETE /admin/users/{id}/keys` revokes every key of the user;
+/// `DELETE /admin/users/{id}/keys/{key_id}` revokes one.
+pub async fn handle_revoke_keys(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let admin = authorize_admin(&req, &ctx.env).await?;
+    let id = user_id(&ctx)?;
+    let user = find(&ctx.env, id).await?;
+    let key_id = ctx.param("key_id").map(String::as_str);
+
+    let revoked = api_keys::revoke(&ctx.env, id, key_id).await?;
+    if key_id.is_some() && revoked == 0 {
+        return Err(ApiError::not_found("API key not found"));
+    }
+
+    audit(&ctx.env, "keys_revoked", &user, &admin, json!({ "key_id": key_id, "revoked": revoked })).await;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "user_id": id,
+        "revoked": revoked,
+    }))?)
+}
+
+/// `GET /admin/users/{id}/usage?days=N` — the user's `/usage` report.
+pub async fn handle_user_usage(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    authorize_admin(&req, &ctx.env).await?;
+    let id = user_id(&ctx)?;
+    let days = usage::requested_days(&req)?;
+    find(&ctx.env, id).await?;
+
+    let mut report = usage::report(&ctx.env, id, days).await?;
+    report["user_id"] = json!(id);
+    Ok(Response::from_json(&report)?)
+}
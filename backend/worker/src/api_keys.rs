@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+use crate::{authorize, parse_json, AuthUser, AUTH_USER_COLUMNS};
+
+/// Characters of the plaintext key kept for display, e.g. `gp_1a2b3c4d`.
+const PREFIX_LEN: usize = 11;
+
+const MAX_NAME_LEN: usize = 100;
+
+/// How many legacy plaintext keys one scheduled run hashes.
+const LEGACY_BATCH: i32 = 100;
+
+/// A key as listed to its owner. The plaintext is only ever returned once,
+/// when the key is created.
+#[derive(Debug, Serialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked: bool,
+}
+
+/// `api_keys` as D1 returns it; SQLite has no boolean type.
+#[derive(Deserialize)]
+struct ApiKeyRow {
+    id: String,
+    name: String,
+    prefix: String,
+    created_at: String,
+    last_used_at: Option<String>,
+    revoked: i32,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            prefix: row.prefix,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+            revoked: row.revoked != 0,
+        }
+    }
+}
+
+pub fn generate() -> String {
+    format!("gp_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Keys, refresh tokens and reset tokens are random values of at least 128
+/// bits, so an unsalted SHA-256 is enough to make a leaked table useless
+/// without slowing down every request.
+pub fn hash(api_key: &str) -> String {
+    Sha256::digest(api_key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn prefix(api_key: &str) -> &str {
+    &api_key[..PREFIX_LEN.min(api_key.len())]
+}
+
+pub fn is_well_formed(api_key: &str) -> bool {
+    api_key.starts_with("gp_") && api_key.len() == 35
+}
+
+fn validate_name(name: &str) -> ApiResult<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ApiError::bad_request("Key name must not be empty"));
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(ApiError::bad_request(format!("Key name must be at most {} characters", MAX_NAME_LEN)));
+    }
+    Ok(name)
+}
+
+/// Stores the hash of `api_key` under `name`.
+async fn insert(env: &Env, user_id: i32, name: &str, api_key: &str) -> Result<ApiKey> {
+    let key = ApiKey {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        prefix: prefix(api_key).to_string(),
+        created_at: String::new(),
+        last_used_at: None,
+        revoked: false,
+    };
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "INSERT INTO api_keys (id, user_id, name, prefix, key_hash, created_at) \
+         VALUES (?, ?, ?, ?, ?, datetime('now')) RETURNING created_at",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&key.id),
+        D1Type::Integer(user_id),
+        D1Type::Text(&key.name),
+        D1Type::Text(&key.prefix),
+        D1Type::Text(&hash(api_key)),
+    ])?;
+    let created_at = query.first::<String>(Some("created_at")).await?.unwrap_or_default();
+
+    Ok(ApiKey { created_at, ..key })
+}
+
+/// Issues a new key for `user_id`. Returns the stored record and the
+/// plaintext, which the caller must hand back now or lose.
+pub async fn create(env: &Env, user_id: i32, name: &str) -> ApiResult<(ApiKey, String)> {
+    let name = validate_name(name)?;
+    let api_key = generate();
+    let key = insert(env, user_id, name, &api_key).await?;
+    Ok((key, api_key))
+}
+
+/// Issues a key named `name` and revokes the other active keys of
+/// `user_id` with that name. Only done when the caller asks for it.
+pub async fn replace(env: &Env, user_id: i32, name: &str) -> ApiResult<(ApiKey, String)> {
+    let (key, api_key) = create(env, user_id, name).await?;
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "UPDATE api_keys SET revoked = 1, revoked_at = datetime('now') \
+         WHERE user_id = ? AND name = ? AND id != ? AND revoked = 0",
+    );
+    let query = statement.bind_refs(&[D1Type::Integer(user_id), D1Type::Text(&key.name), D1Type::Text(&key.id)])?;
+    query.run().await?;
+    Ok((key, api_key))
+}
+
+async fn find_owner(env: &Env, key_hash: &str) -> Result<Option<AuthUser>> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare(format!(
+        "SELECT {} \
+         FROM api_keys k JOIN users u ON u.id = k.user_id WHERE k.key_hash = ? AND k.revoked = 0",
+        AUTH_USER_COLUMNS
+    ));
+    let query = statement.bind_refs(&[D1Type::Text(key_hash)])?;
+    query.first(None).await
+}
+
+/// Resolves a presented key to its owner. Revoked keys resolve to nothing.
+pub async fn authenticate(env: &Env, api_key: &str) -> Result<Option<AuthUser>> {
+    if !is_well_formed(api_key) {
+        return Ok(None);
+    }
+    let key_hash = hash(api_key);
+
+    let owner = match find_owner(env, &key_hash).await? {
+        Some(owner) => Some(owner),
+        None if migrate_legacy(env, api_key).await? => find_owner(env, &key_hash).await?,
+        None => None,
+    };
+
+    if owner.is_some() {
+        touch(env, &key_hash).await;
+    }
+    Ok(owner)
+}
+
+/// Records when a key was last used, at most once a minute per key so a
+/// busy key does not turn every request into a write.
+async fn touch(env: &Env, key_hash: &str) {
+    let result = async {
+        let db = env.d1("DB")?;
+        let statement = db.prepare(
+            "UPDATE api_keys SET last_used_at = datetime('now') \
+             WHERE key_hash = ? AND (last_used_at IS NULL OR last_used_at < datetime('now', '-60 seconds'))",
+        );
+        statement.bind_refs(&[D1Type::Text(key_hash)])?.run().await?;
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to record API key use: {}", e);
+    }
+}
+
+/// Moves a pre-`api_keys` plaintext key out of `users.api_key`. The column
+/// keeps its NOT NULL UNIQUE constraint, so it is overwritten with the hash,
+/// which can never be presented as a key. Returns whether `api_key` was one.
+async fn migrate_legacy(env: &Env, api_key: &str) -> Result<bool> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT id FROM users WHERE api_key = ?");
+    let query = statement.bind_refs(&[D1Type::Text(api_key)])?;
+    let Some(user_id) = query.first::<i32>(Some("id")).await? else {
+        return Ok(false);
+    };
+
+    migrate_user(env, user_id, api_key).await?;
+    Ok(true)
+}
+
+async fn migrate_user(env: &Env, user_id: i32, api_key: &str) -> Result<()> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("UPDATE users SET api_key = ? WHERE id = ? AND api_key = ?");
+    let query = statement.bind_refs(&[D1Type::Text(&hash(api_key)), D1Type::Integer(user_id), D1Type::Text(api_key)])?;
+    let changed = query.run().await?.meta()?.and_then(|meta| meta.changes).unwrap_or(0) > 0;
+
+    // Only the caller that won the update inserts, so concurrent requests
+    // with the same legacy key cannot create it twice.
+    if changed {
+        insert(env, user_id, "Default", api_key).await?;
+    }
+    Ok(())
+}
+
+/// Hashes legacy keys nobody has presented yet. Called from the scheduled
+/// handler until `users.api_key` holds no plaintext.
+pub async fn migrate_legacy_keys(env: &Env) {
+    let result = async {
+        let db = env.d1("DB")?;
+        let statement = db.prepare("SELECT id, api_key FROM users WHERE substr(api_key, 1, 3) = 'gp_' LIMIT ?");
+        let query = statement.bind_refs(&[D1Type::Integer(LEGACY_BATCH)])?;
+
+        #[derive(Deserialize)]
+        struct Legacy {
+            id: i32,
+            api_key: String,
+        }
+
+        for legacy in query.all().await?.results::<Legacy>()? {
+            if let Err(e) = migrate_user(env, legacy.id, &legacy.api_key).await {
+                console_log!("Failed to migrate API key for user {}: {}", legacy.id, e);
+            }
+        }
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to load legacy API keys: {}", e);
+    }
+}
+
+/// Every key of `user_id`, newest first, revoked ones included.
+pub async fn list(env: &Env, user_id: i32) -> Result<Vec<ApiKey>> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "SELECT id, name, prefix, created_at, last_used_at, revoked FROM api_keys \
+         WHERE user_id = ? ORDER BY created_at DESC, id",
+    );
+    let query = statement.bind_refs(&[D1Type::Integer(user_id)])?;
+    Ok(query
+        .all()
+        .await?
+        .results::<ApiKeyRow>()?
+        .into_iter()
+        .map(ApiKey::from)
+        .collect())
+}
+
+/// Revokes one active key of `user_id`, or all of them when `id` is `None`.
+/// Returns how many were revoked.
+pub async fn revoke(env: &Env, user_id: i32, id: Option<&str>) -> Result<u32> {
+    let db = env.d1("DB")?;
+    let result = match id {
+        Some(id) => {
+            let statement = db.prepare("UPDATE api_keys SET revoked = 1, revoked_at = datetime('now') WHERE id = ? AND user_id = ? AND revoked = 0");
+            statement.bind_refs(&[D1Type::Text(id), D1Type::Integer(user_id)])?.run().await?
+        }
+        None => {
+            let statement = db.prepare("UPDATE api_keys SET revoked = 1, revoked_at = datetime('now') WHERE user_id = ? AND revoked = 0");
+            statement.bind_refs(&[D1Type::Integer(user_id)])?.run().await?
+        }
+    };
+    Ok(result.meta()?.and_then(|meta| meta.changes).unwrap_or(0) as u32)
+}
+
+#[derive(Deserialize)]
+struct CreateKeyRequest {
+    name: String,
+}
+
+/// `POST /keys` — issues an additional named key.
+pub async fn handle_create(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: CreateKeyRequest = parse_json(&mut req).await?;
+    let (key, api_key) = create(&ctx.env, user.id, &body.name).await?;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "key": key,
+        "api_key": api_key,
+    }))?
+    .with_status(201))
+}
+
+/// `GET /keys` — the caller's keys, newest first, without their secrets.
+pub async fn handle_list(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+
+    let keys = list(&ctx.env, user.id).await?;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "keys": keys,
+    }))?)
+}
+
+/// `DELETE /keys/{id}` — revokes one of the caller's keys. The row is kept
+/// so the key still shows up, marked revoked, in `GET /keys`.
+pub async fn handle_revoke(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let id = ctx.param("id").ok_or_else(|| ApiError::bad_request("Missing key id"))?;
+
+    if revoke(&ctx.env, user.id, Some(id)).await? == 0 {
+        return Err(ApiError::not_found("API key not found"));
+    }
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "id": id,
+        "revoked": true,
+    }))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_is_hex_sha256() {
+        assert_eq!(hash("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn test_generate_and_prefix() {
+        let api_key = generate();
+        assert!(is_well_formed(&api_key));
+        assert_eq!(prefix(&api_key).len(), PREFIX_LEN);
+        assert!(api_key.starts_with(prefix(&api_key)));
+    }
+}
@@ -0,0 +1,357 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use serde::{Deserialize, Serialize};
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+use crate::{authorize, sessions};
+
+/// R2 binding that holds generated images and videos.
+const BUCKET: &str = "ASSETS";
+
+/// How long the signed URLs in API responses work.
+pub const URL_TTL_SECONDS: u64 = 60 * 60;
+
+/// How long the signed URLs in webhook payloads work. Outlasts the whole
+/// retry schedule, so a late delivery still carries a usable link.
+pub const WEBHOOK_URL_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// A stored output as returned to clients in place of inline base64.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Asset {
+    pub id: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+impl Asset {
+    /// Signed URL of `GET /assets/{id}` on the origin that served `req`.
+    pub fn url(&self, req: &Request, env: &Env) -> Result<String> {
+        Ok(asset_url(env, &req.url()?, &self.id, URL_TTL_SECONDS))
+    }
+}
+
+/// The key asset links are signed with, derived from `SESSION_SECRET` so
+/// a link signature can never be passed off as a session token or back.
+fn url_key(secret: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(b"asset-url-v1");
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Whether this deployment can sign asset links. Reported by `/health`.
+pub fn signing_configured(env: &Env) -> bool {
+    sessions::secret(env).is_ok()
+}
+
+/// `HMAC-SHA256("{id}.{exp}")` under the asset link key.
+fn mac(key: &[u8], id: &str, exp: u64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", id, exp).as_bytes());
+    mac
+}
+
+fn signature(key: &[u8], id: &str, exp: u64) -> String {
+    URL_SAFE_NO_PAD.encode(mac(key, id, exp).finalize().into_bytes())
+}
+
+fn check_signature(key: &[u8], id: &str, exp: u64, sig: &str, now: u64) -> ApiResult<()> {
+    let invalid = || ApiError::new(403, "invalid_asset_signature", "This asset link is not valid");
+    let sig = URL_SAFE_NO_PAD.decode(sig).map_err(|_| invalid())?;
+    mac(key, id, exp).verify_slice(&sig).map_err(|_| invalid())?;
+    if exp <= now {
+        return Err(ApiError::new(403, "asset_url_expired", "This asset link has expired; fetch a new one or send an Authorization header"));
+    }
+    Ok(())
+}
+
+/// Absolute URL of `GET /assets/{id}` on the origin of `request_url`. With
+/// `SESSION_SECRET` set it carries `exp` and `sig`, so `<img>` and `<video>`
+/// tags and webhook receivers can fetch it without an `Authorization`
+/// header for `ttl` seconds. Without the secret it needs the header, which
+/// is logged every time and reported by `/health`.
+pub fn asset_url(env: &Env, request_url: &Url, id: &str, ttl: u64) -> String {
+    let url = format!("{}/assets/{}", request_url.origin().ascii_serialization(), id);
+    match sessions::secret(env) {
+        Ok(secret) => {
+            let exp = Date::now().as_millis() / 1000 + ttl;
+            format!("{}?exp={}&sig={}", url, exp, signature(&url_key(&secret), id, exp))
+        }
+        Err(_) => {
+            console_error!("SESSION_SECRET is not set; asset {} gets an unsigned URL that needs an Authorization header", id);
+            url
+        }
+    }
+}
+
+/// Objects are namespaced by owner so a bucket listing maps onto users.
+fn object_key(user_id: i32, id: &str) -> String {
+    format!("{}/{}", user_id, id)
+}
+
+/// Writes `bytes` to R2 and records the asset, linked to its ledger row when
+/// there is one.
+pub async fn store(env: &Env, user_id: i32, generation_id: Option<&str>, content_type: &str, bytes: Vec<u8>) -> ApiResult<Asset> {
+    let asset = Asset {
+        id: uuid::Uuid::new_v4().to_string(),
+        content_type: content_type.to_string(),
+        size: bytes.len() as u64,
+    };
+
+    let bucket = env
+        .bucket(BUCKET)
+        .map_err(|e| ApiError::internal_logged(format!("Asset storage unavailable: {}", e)))?;
+    bucket
+        .put(object_key(user_id, &asset.id), bytes)
+        .http_metadata(HttpMetadata {
+            content_type: Some(asset.content_type.clone()),
+            ..Default::default()
+        })
+        .execute()
+        .await
+        .map_err(|e| ApiError::internal_logged(format!("Failed to store asset: {}", e)))?;
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "INSERT INTO assets (id, user_id, generation_id, content_type, size, created_at) \
+         VALUES (?, ?, ?, ?, ?, datetime('now'))",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&asset.id),
+        D1Type::Integer(user_id),
+        generation_id.map(D1Type::Text).unwrap_or(D1Type::Null),
+        D1Type::Text(&asset.content_type),
+        D1Type::Real(asset.size as f64),
+    ])?;
+    query.run().await?;
+
+    Ok(asset)
+}
+
+/// Looks an asset up for its owner.
+pub async fn find(env: &Env, user_id: i32, id: &str) -> ApiResult<Asset> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT id, content_type, size FROM assets WHERE id = ? AND user_id = ?");
+    let query = statement.bind_refs(&[D1Type::Text(id), D1Type::Integer(user_id)])?;
+    query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Asset not found"))
+}
+
+/// Looks an asset up by id alone, for requests that carry a signature
+/// instead of credentials. Returns the owner with it.
+async fn find_any(env: &Env, id: &str) -> ApiResult<(i32, Asset)> {
+    #[derive(Deserialize)]
+    struct OwnedAsset {
+        user_id: i32,
+        #[serde(flatten)]
+        asset: Asset,
+    }
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT user_id, id, content_type, size FROM assets WHERE id = ?");
+    let query = statement.bind_refs(&[D1Type::Text(id)])?;
+    let found: OwnedAsset = query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Asset not found"))?;
+    Ok((found.user_id, found.asset))
+}
+
+/// Reads a whole asset back, for callers that asked for inline data.
+pub async fn read(env: &Env, user_id: i32, asset: &Asset) -> ApiResult<Vec<u8>> {
+    let object = env
+        .bucket(BUCKET)?
+        .get(object_key(user_id, &asset.id))
+        .execute()
+        .await?
+        .ok_or_else(|| ApiError::not_found("Asset not found"))?;
+    let body = object
+        .body()
+        .ok_or_else(|| ApiError::internal("Stored asset has no body"))?;
+    Ok(body.bytes().await?)
+}
+
+/// Removes every object `user_id` owns from R2. The `assets` rows go with
+/// the user through `ON DELETE CASCADE`.
+pub async fn delete_all(env: &Env, user_id: i32) -> Result<()> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT id FROM assets WHERE user_id = ?");
+    let query = statement.bind_refs(&[D1Type::Integer(user_id)])?;
+
+    #[derive(Deserialize)]
+    struct AssetId {
+        id: String,
+    }
+
+    let keys: Vec<String> = query
+        .all()
+        .await?
+        .results::<AssetId>()?
+        .into_iter()
+        .map(|asset| object_key(user_id, &asset.id))
+        .collect();
+
+    // R2 deletes at most 1000 keys per call.
+    let bucket = env.bucket(BUCKET)?;
+    for chunk in keys.chunks(1000) {
+        bucket.delete_multiple(chunk.to_vec()).await?;
+    }
+    Ok(())
+}
+
+/// Deletes some of `user_id`'s assets, from R2 and from `assets`.
+pub async fn delete(env: &Env, user_id: i32, ids: &[String]) -> Result<()> {
+    let keys: Vec<String> = ids.iter().map(|id| object_key(user_id, id)).collect();
+    let bucket = env.bucket(BUCKET)?;
+    for chunk in keys.chunks(1000) {
+        bucket.delete_multiple(chunk.to_vec()).await?;
+    }
+
+    let db = env.d1("DB")?;
+    for id in ids {
+        let statement = db.prepare("DELETE FROM assets WHERE id = ? AND user_id = ?");
+        statement.bind_refs(&[D1Type::Text(id), D1Type::Integer(user_id)])?.run().await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+/// Parses a single `Range: bytes=...` header against an object of `size`
+/// bytes. Multi-range and malformed headers fall back to the full body, as
+/// RFC 9110 allows servers to ignore ranges they do not support.
+fn parse_range(header: Option<&str>, size: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    let (start, end) = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+        (Some(start), Some(end)) if start <= end => (start, end.min(size.saturating_sub(1))),
+        (Some(start), None) if end.is_empty() => (start, size.saturating_sub(1)),
+        (None, Some(suffix)) if start.is_empty() && suffix > 0 => (size.saturating_sub(suffix), size.saturating_sub(1)),
+        (None, Some(0)) if start.is_empty() => return ByteRange::Unsatisfiable,
+        _ => return ByteRange::Full,
+    };
+
+    if size == 0 || start >= size {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial { start, end }
+    }
+}
+
+/// `GET /assets/{id}` — streams a stored output to its owner, honouring
+/// single byte ranges so video players can seek. Takes either an
+/// `Authorization` header or the `exp` and `sig` of a signed URL.
+pub async fn handle_get(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let id = ctx.param("id").ok_or_else(|| ApiError::bad_request("Missing asset id"))?;
+    let url = req.url()?;
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+
+    let signed = param("exp").zip(param("sig"));
+    let (owner, asset) = match signed {
+        Some((exp, sig)) if req.headers().get("Authorization")?.is_none() => {
+            let secret = sessions::secret(&ctx.env)?;
+            let exp = exp.parse::<u64>().map_err(|_| ApiError::new(403, "invalid_asset_signature", "This asset link is not valid"))?;
+            check_signature(&url_key(&secret), id, exp, &sig, Date::now().as_millis() / 1000)?;
+            find_any(&ctx.env, id).await?
+        }
+        _ => {
+            let user = authorize(&req, &ctx.env).await?;
+            (user.id, find(&ctx.env, user.id, id).await?)
+        }
+    };
+
+    let range = parse_range(req.headers().get("Range")?.as_deref(), asset.size);
+    let bucket = ctx.env.bucket(BUCKET)?;
+    let get = bucket.get(object_key(owner, &asset.id));
+    let (get, status, length) = match range {
+        ByteRange::Full => (get, 200, asset.size),
+        ByteRange::Partial { start, end } => (
+            get.range(Range::OffsetWithLength { offset: start, length: end - start + 1 }),
+            206,
+            end - start + 1,
+        ),
+        ByteRange::Unsatisfiable => {
+            return Err(ApiError::new(416, "range_not_satisfiable", "Requested range is outside the asset")
+                .with_header("Content-Range", format!("bytes */{}", asset.size)));
+        }
+    };
+
+    let object = get
+        .execute()
+        .await?
+        .ok_or_else(|| ApiError::not_found("Asset not found"))?;
+    let body = object
+        .body()
+        .ok_or_else(|| ApiError::internal("Stored asset has no body"))?;
+
+    let response = Response::from_body(body.response_body()?)?.with_status(status);
+    let headers = response.headers();
+    headers.set("Content-Type", &asset.content_type)?;
+    headers.set("Content-Length", &length.to_string())?;
+    headers.set("Accept-Ranges", "bytes")?;
+    headers.set("ETag", &object.http_etag())?;
+    headers.set("Cache-Control", "private, max-age=31536000, immutable")?;
+    if let ByteRange::Partial { start, end } = range {
+        headers.set("Content-Range", &format!("bytes {}-{}/{}", start, end, asset.size))?;
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_urls_check_id_and_expiry() {
+        let key = url_key("secret");
+        let sig = signature(&key, "asset-1", 1_000);
+        assert!(check_signature(&key, "asset-1", 1_000, &sig, 999).is_ok());
+
+        assert_eq!(check_signature(&key, "asset-1", 1_000, &sig, 1_000).unwrap_err().code, "asset_url_expired");
+        assert_eq!(check_signature(&key, "asset-2", 1_000, &sig, 999).unwrap_err().code, "invalid_asset_signature");
+        assert_eq!(check_signature(&key, "asset-1", 2_000, &sig, 999).unwrap_err().code, "invalid_asset_signature");
+        assert_eq!(check_signature(&url_key("other"), "asset-1", 1_000, &sig, 999).unwrap_err().code, "invalid_asset_signature");
+        assert_eq!(check_signature(&key, "asset-1", 1_000, "not base64!", 999).unwrap_err().code, "invalid_asset_signature");
+
+        // Links are not signed with the session secret itself.
+        let raw = signature(b"secret", "asset-1", 1_000);
+        assert_eq!(check_signature(&key, "asset-1", 1_000, &raw, 999).unwrap_err().code, "invalid_asset_signature");
+    }
+
+    #[test]
+    fn test_parse_range_forms() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), ByteRange::Partial { start: 0, end: 9 });
+        assert_eq!(parse_range(Some("bytes=90-"), 100), ByteRange::Partial { start: 90, end: 99 });
+        assert_eq!(parse_range(Some("bytes=-10"), 100), ByteRange::Partial { start: 90, end: 99 });
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), ByteRange::Partial { start: 50, end: 99 });
+        assert_eq!(parse_range(Some("bytes=-500"), 100), ByteRange::Partial { start: 0, end: 99 });
+    }
+
+    #[test]
+    fn test_parse_range_rejects_out_of_bounds_and_ignores_garbage() {
+        assert_eq!(parse_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-2"), 100), ByteRange::Full);
+    }
+}
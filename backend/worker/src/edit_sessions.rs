@@ -0,0 +1,525 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::assets::{self, Asset};
+use crate::error::{ApiError, ApiResult};
+use crate::rate_limit::{self, LimitKind};
+use crate::upstream::{ChatRole, ChatTurn, GeminiClient, InlineData};
+use crate::usage::{self, base64_decoded_len, Generation, GenerationKind};
+use crate::{authorize, extract_images_from_response, input_images, models, store_images, uploads, AuthUser};
+use crate::{EditImage, ImageResponse, ImageSettings, EDIT_FORM};
+
+/// Turns one session can hold. Every turn resends the whole history, so
+/// long sessions also run into the input size limit.
+const MAX_TURNS: u32 = 20;
+
+/// A claimed turn whose worker never finished it stops blocking the
+/// session after this long.
+const TURN_CLAIM_MINUTES: u32 = 5;
+
+/// Sessions nobody has used for this long are deleted by the scheduled
+/// handler, with the images uploaded to them. Results stay in history.
+const SESSION_TTL_DAYS: u32 = 7;
+
+/// How many sessions one scheduled run deletes.
+const EXPIRE_BATCH: i32 = 20;
+
+#[derive(Deserialize)]
+struct CreateSessionRequest {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    images: Vec<EditImage>,
+}
+
+#[derive(Deserialize)]
+struct TurnRequest {
+    prompt: String,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    images: Vec<EditImage>,
+    #[serde(default)]
+    inline: bool,
+    #[serde(flatten)]
+    settings: ImageSettings,
+}
+
+#[derive(Serialize)]
+struct TurnResponse {
+    session_id: String,
+    turn: u32,
+    #[serde(flatten)]
+    result: ImageResponse,
+}
+
+/// An edit session as stored. Asset id lists are JSON arrays.
+#[derive(Deserialize)]
+struct Session {
+    id: String,
+    model: String,
+    image_asset_ids: String,
+}
+
+#[derive(Deserialize)]
+struct StoredTurn {
+    prompt: String,
+    input_asset_ids: String,
+    output_asset_id: Option<String>,
+    text: Option<String>,
+}
+
+/// A turn with its images read back, ready to resend.
+struct LoadedTurn {
+    prompt: String,
+    inputs: Vec<InlineData>,
+    outputs: Vec<InlineData>,
+    text: Option<String>,
+}
+
+fn asset_ids(list: &str) -> Vec<String> {
+    serde_json::from_str(list).unwrap_or_default()
+}
+
+/// The `contents` Gemini gets: each earlier turn as a user message and the
+/// model's reply, then the new message.
+fn conversation<'a>(history: &'a [LoadedTurn], next: &'a LoadedTurn) -> Vec<ChatTurn<'a>> {
+    let mut turns = Vec::with_capacity(history.len() * 2 + 1);
+    for turn in history.iter().chain([next]) {
+        turns.push(ChatTurn {
+            role: ChatRole::User,
+            images: &turn.inputs,
+            text: Some(&turn.prompt),
+        });
+        if !turn.outputs.is_empty() || turn.text.is_some() {
+            turns.push(ChatTurn {
+                role: ChatRole::Model,
+                images: &turn.outputs,
+                text: turn.text.as_deref(),
+            });
+        }
+    }
+    turns
+}
+
+fn session_id(ctx: &RouteContext<Context>) -> ApiResult<String> {
+    ctx.param("id")
+        .cloned()
+        .ok_or_else(|| ApiError::bad_request("Missing session id"))
+}
+
+/// Looks a session up for its owner.
+async fn find(env: &Env, user_id: i32, id: &str) -> ApiResult<Session> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT id, model, image_asset_ids FROM edit_sessions WHERE id = ? AND user_id = ?");
+    let query = statement.bind_refs(&[D1Type::Text(id), D1Type::Integer(user_id)])?;
+    query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Session not found"))
+}
+
+/// The session's finished turns, in order.
+async fn turns(env: &Env, session_id: &str) -> Result<Vec<StoredTurn>> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "SELECT prompt, input_asset_ids, output_asset_id, text FROM edit_session_turns \
+         WHERE session_id = ? AND status = 'done' ORDER BY turn",
+    );
+    let query = statement.bind_refs(&[D1Type::Text(session_id)])?;
+    query.all().await?.results()
+}
+
+async fn find_assets(env: &Env, user_id: i32, ids: &[String]) -> ApiResult<Vec<Asset>> {
+    let mut found = Vec::with_capacity(ids.len());
+    for id in ids {
+        found.push(assets::find(env, user_id, id).await?);
+    }
+    Ok(found)
+}
+
+async fn read_assets(env: &Env, user_id: i32, found: &[Asset]) -> ApiResult<Vec<InlineData>> {
+    let mut images = Vec::with_capacity(found.len());
+    for asset in found {
+        let bytes = assets::read(env, user_id, asset).await?;
+        images.push(InlineData {
+            mime_type: asset.content_type.clone(),
+            data: general_purpose::STANDARD.encode(bytes),
+        });
+    }
+    Ok(images)
+}
+
+/// Keeps uploaded images as assets, so later turns can send them again.
+/// They are not linked to a generation, which would list them among its
+/// results, and go when the session expires.
+async fn store_inputs(env: &Env, user_id: i32, images: &[InlineData]) -> ApiResult<Vec<String>> {
+    let mut ids = Vec::with_capacity(images.len());
+    for image in images {
+        let bytes = general_purpose::STANDARD
+            .decode(&image.data)
+            .map_err(|_| ApiError::bad_request("Invalid image data"))?;
+        ids.push(assets::store(env, user_id, None, &image.mime_type, bytes).await?.id);
+    }
+    Ok(ids)
+}
+
+#[derive(Deserialize)]
+struct TurnCounts {
+    turns: u32,
+    pending: u32,
+}
+
+/// Reserves the next turn number with a pending row. The primary key lets
+/// only one of two turns sent at once have it; the other gets 409, before
+/// it is charged or reaches the model.
+async fn claim_turn(env: &Env, session_id: &str, prompt: &str) -> ApiResult<u32> {
+    let db = env.d1("DB")?;
+    let abandoned = format!("-{} minutes", TURN_CLAIM_MINUTES);
+    let statement = db.prepare(
+        "DELETE FROM edit_session_turns WHERE session_id = ? AND status = 'pending' AND created_at <= datetime('now', ?)",
+    );
+    statement.bind_refs(&[D1Type::Text(session_id), D1Type::Text(&abandoned)])?.run().await?;
+
+    let statement = db.prepare(
+        "SELECT COUNT(*) AS turns, COALESCE(SUM(status = 'pending'), 0) AS pending \
+         FROM edit_session_turns WHERE session_id = ?",
+    );
+    let query = statement.bind_refs(&[D1Type::Text(session_id)])?;
+    let counts: TurnCounts = query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::internal("Failed to count turns"))?;
+
+    let in_progress = || ApiError::conflict("turn_in_progress", "The previous turn of this session has not finished yet");
+    if counts.pending > 0 {
+        return Err(in_progress());
+    }
+    if counts.turns >= MAX_TURNS {
+        return Err(ApiError::conflict(
+            "session_full",
+            format!("This session has reached {} turns. Start a new one from its last image.", MAX_TURNS),
+        ));
+    }
+
+    let turn = counts.turns + 1;
+    let statement = db.prepare(
+        "INSERT OR IGNORE INTO edit_session_turns (session_id, turn, prompt, status, created_at) \
+         VALUES (?, ?, ?, 'pending', datetime('now'))",
+    );
+    let query = statement.bind_refs(&[D1Type::Text(session_id), D1Type::Integer(turn as i32), D1Type::Text(prompt)])?;
+    let claimed = query.run().await?.meta()?.and_then(|meta| meta.changes).unwrap_or(0);
+    if claimed == 0 {
+        return Err(in_progress());
+    }
+    Ok(turn)
+}
+
+/// Gives back a turn that failed, so the session can carry on.
+async fn release_turn(env: &Env, session_id: &str, turn: u32) {
+    let result = async {
+        let db = env.d1("DB")?;
+        let statement = db.prepare("DELETE FROM edit_session_turns WHERE session_id = ? AND turn = ? AND status = 'pending'");
+        statement.bind_refs(&[D1Type::Text(session_id), D1Type::Integer(turn as i32)])?.run().await?;
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to release turn {} of session {}: {}", turn, session_id, e);
+    }
+}
+
+/// `POST /sessions` — starts a multi-turn edit session. Takes an optional
+/// `model` and the images to start from, as `/edit` does; both may be left
+/// out to start from a text prompt.
+pub async fn handle_create(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: CreateSessionRequest = uploads::read(&mut req, &EDIT_FORM).await?;
+    let inputs = input_images(body.image.as_deref(), &body.images)?;
+    let model = models::resolve(&ctx.env, GenerationKind::Edit, body.model.as_deref())?;
+    // Starting images are stored like an upload to /edit, and count as one.
+    let limit = rate_limit::enforce(&ctx.env, &user, LimitKind::Image).await?;
+
+    let image_asset_ids = store_inputs(&ctx.env, user.id, &inputs).await?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let ids = json!(image_asset_ids).to_string();
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(
+        "INSERT INTO edit_sessions (id, user_id, model, image_asset_ids, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, datetime('now'), datetime('now'))",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&id),
+        D1Type::Integer(user.id),
+        D1Type::Text(&model),
+        D1Type::Text(&ids),
+    ])?;
+    query.run().await?;
+
+    let response = Response::from_json(&json!({
+        "success": true,
+        "session_id": id,
+        "model": model,
+        "image_asset_ids": image_asset_ids,
+        "max_turns": MAX_TURNS,
+    }))?
+    .with_status(201);
+    limit.apply(response.headers())?;
+    Ok(response)
+}
+
+/// `POST /sessions/{id}/turns` — sends the next instruction, with the
+/// session's earlier prompts, images and replies as context. Takes the
+/// `/edit` fields except `model`; images are optional. Answers like
+/// `/edit`, plus the session id and turn number.
+pub async fn handle_turn(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let id = session_id(&ctx)?;
+    let body: TurnRequest = uploads::read(&mut req, &EDIT_FORM).await?;
+    let inputs = input_images(body.image.as_deref(), &body.images)?;
+    let settings = body.settings.validate()?;
+
+    let session = find(&ctx.env, user.id, &id).await?;
+    let turn = claim_turn(&ctx.env, &session.id, &body.prompt).await?;
+    let result = run_turn(&req, &ctx.env, &user, &session, turn, body.prompt, inputs, settings, body.inline).await;
+    if result.is_err() {
+        release_turn(&ctx.env, &session.id, turn).await;
+    }
+    result
+}
+
+/// Everything after the turn is claimed. An error gives the turn back.
+#[allow(clippy::too_many_arguments)]
+async fn run_turn(
+    req: &Request,
+    env: &Env,
+    user: &AuthUser,
+    session: &Session,
+    turn: u32,
+    prompt: String,
+    inputs: Vec<InlineData>,
+    settings: ImageSettings,
+    inline: bool,
+) -> ApiResult<Response> {
+    let stored = turns(env, &session.id).await?;
+
+    // The images the session was created with open its first turn.
+    let carried_ids = if stored.is_empty() { asset_ids(&session.image_asset_ids) } else { Vec::new() };
+    let carried = find_assets(env, user.id, &carried_ids).await?;
+
+    // Sizes are known before anything is read back.
+    let input_bytes: u64 = inputs.iter().map(|image| base64_decoded_len(&image.data)).sum();
+    let mut total = input_bytes + carried.iter().map(|asset| asset.size).sum::<u64>();
+    let mut found = Vec::with_capacity(stored.len());
+    for turn in &stored {
+        let turn_inputs = find_assets(env, user.id, &asset_ids(&turn.input_asset_ids)).await?;
+        let turn_outputs = find_assets(env, user.id, turn.output_asset_id.as_slice()).await?;
+        total += turn_inputs.iter().chain(&turn_outputs).map(|asset| asset.size).sum::<u64>();
+        found.push((turn_inputs, turn_outputs));
+    }
+    if total > uploads::MAX_INPUT_BYTES {
+        return Err(ApiError::payload_too_large(format!(
+            "This session's images total {} bytes; the limit is {} bytes. Start a new session from its last image.",
+            total,
+            uploads::MAX_INPUT_BYTES
+        ))
+        .with_details(json!({ "size": total, "limit": uploads::MAX_INPUT_BYTES })));
+    }
+    let limit = rate_limit::enforce(env, user, LimitKind::Image).await?;
+
+    let mut history = Vec::with_capacity(stored.len());
+    for (turn, (turn_inputs, turn_outputs)) in stored.into_iter().zip(found) {
+        history.push(LoadedTurn {
+            prompt: turn.prompt,
+            inputs: read_assets(env, user.id, &turn_inputs).await?,
+            outputs: read_assets(env, user.id, &turn_outputs).await?,
+            text: turn.text,
+        });
+    }
+    let mut next_inputs = read_assets(env, user.id, &carried).await?;
+    next_inputs.extend(inputs.iter().cloned());
+    let next = LoadedTurn {
+        prompt,
+        inputs: next_inputs,
+        outputs: Vec::new(),
+        text: None,
+    };
+
+    let gemini = GeminiClient::from_env(env)?;
+    let started = Date::now().as_millis();
+    let result = match gemini.converse(&session.model, &conversation(&history, &next), &settings.options(1)).await {
+        Ok(gemini_response) => extract_images_from_response(&gemini_response).map(|mut output| {
+            output.images.truncate(1);
+            output
+        }),
+        Err(e) => Err(e.into()),
+    };
+
+    let (status, error_code) = usage::outcome(&result);
+    let mut parameters = json!({
+        "session_id": session.id,
+        "turn": turn,
+        "input_bytes": input_bytes,
+        "context_bytes": total,
+    });
+    settings.record(&mut parameters);
+    let generation_id = usage::record(env, &Generation {
+        user_id: user.id,
+        kind: GenerationKind::Edit,
+        model: &session.model,
+        prompt: &next.prompt,
+        parameters,
+        status,
+        error_code,
+        operation_name: None,
+        latency_ms: Date::now().as_millis() - started,
+        output_bytes: result.as_ref().ok().map(|output| base64_decoded_len(&output.images[0].data)),
+    }).await;
+    let output = result?;
+
+    let mut input_asset_ids = carried_ids;
+    input_asset_ids.extend(store_inputs(env, user.id, &inputs).await?);
+    let images = store_images(req, env, user.id, generation_id.as_deref(), output.images, inline).await?;
+
+    let db = env.d1("DB")?;
+    let input_ids = json!(input_asset_ids).to_string();
+    let statement = db.prepare(
+        "UPDATE edit_session_turns SET input_asset_ids = ?, output_asset_id = ?, text = ?, generation_id = ?, status = 'done' \
+         WHERE session_id = ? AND turn = ?",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&input_ids),
+        D1Type::Text(&images[0].asset_id),
+        output.text.as_deref().map(D1Type::Text).unwrap_or(D1Type::Null),
+        generation_id.as_deref().map(D1Type::Text).unwrap_or(D1Type::Null),
+        D1Type::Text(&session.id),
+        D1Type::Integer(turn as i32),
+    ])?;
+    query.run().await?;
+    let statement = db.prepare("UPDATE edit_sessions SET updated_at = datetime('now') WHERE id = ?");
+    statement.bind_refs(&[D1Type::Text(&session.id)])?.run().await?;
+
+    let response = Response::from_json(&TurnResponse {
+        session_id: session.id.clone(),
+        turn,
+        result: ImageResponse::new(images, output.text),
+    })?;
+    limit.apply(response.headers())?;
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+struct ExpiredSession {
+    id: String,
+    user_id: i32,
+    image_asset_ids: String,
+}
+
+/// The images uploaded to a session: the ones it started from and those
+/// sent with each turn. Results are not among them.
+async fn uploaded_asset_ids(env: &Env, session: &ExpiredSession) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct TurnInputs {
+        input_asset_ids: String,
+    }
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT input_asset_ids FROM edit_session_turns WHERE session_id = ?");
+    let query = statement.bind_refs(&[D1Type::Text(&session.id)])?;
+    let turns: Vec<TurnInputs> = query.all().await?.results()?;
+
+    // The starting images are also listed as the first turn's inputs.
+    let mut ids = asset_ids(&session.image_asset_ids);
+    for turn in turns {
+        for id in asset_ids(&turn.input_asset_ids) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Deletes sessions unused for `SESSION_TTL_DAYS`, with their turns and
+/// uploaded images. Called from the scheduled handler.
+pub async fn expire(env: &Env) {
+    let result = async {
+        let db = env.d1("DB")?;
+        let cutoff = format!("-{} days", SESSION_TTL_DAYS);
+        let statement = db.prepare(
+            "SELECT id, user_id, image_asset_ids FROM edit_sessions WHERE updated_at <= datetime('now', ?) \
+             ORDER BY updated_at LIMIT ?",
+        );
+        let query = statement.bind_refs(&[D1Type::Text(&cutoff), D1Type::Integer(EXPIRE_BATCH)])?;
+        let sessions: Vec<ExpiredSession> = query.all().await?.results()?;
+
+        for session in &sessions {
+            let ids = uploaded_asset_ids(env, session).await?;
+            assets::delete(env, session.user_id, &ids).await?;
+            // Turns go with the session through `ON DELETE CASCADE`.
+            let statement = db.prepare("DELETE FROM edit_sessions WHERE id = ?");
+            statement.bind_refs(&[D1Type::Text(&session.id)])?.run().await?;
+            console_log!("Expired edit session {}", session.id);
+        }
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to expire edit sessions: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(data: &str) -> InlineData {
+        InlineData {
+            mime_type: "image/png".to_string(),
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_conversation_alternates_roles() {
+        let history = vec![LoadedTurn {
+            prompt: "make the sky purple".to_string(),
+            inputs: vec![image("photo")],
+            outputs: vec![image("purple")],
+            text: Some("Done".to_string()),
+        }];
+        let next = LoadedTurn {
+            prompt: "now add a moon".to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            text: None,
+        };
+
+        let turns = conversation(&history, &next);
+        let summary: Vec<(ChatRole, usize, Option<&str>)> = turns.iter().map(|turn| (turn.role, turn.images.len(), turn.text)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ChatRole::User, 1, Some("make the sky purple")),
+                (ChatRole::Model, 1, Some("Done")),
+                (ChatRole::User, 0, Some("now add a moon")),
+            ]
+        );
+        assert_eq!(turns[1].images[0].data, "purple");
+    }
+
+    #[test]
+    fn test_asset_ids_tolerate_bad_json() {
+        assert_eq!(asset_ids(r#"["a","b"]"#), vec!["a", "b"]);
+        assert!(asset_ids("[]").is_empty());
+        assert!(asset_ids("not json").is_empty());
+    }
+}
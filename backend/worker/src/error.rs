@@ -0,0 +1,187 @@
+use serde_json::{json, Value};
+use std::fmt;
+use worker::{console_error, Response};
+
+use crate::upstream::UpstreamError;
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// `Retry-After` for an upstream 429. Gemini does not say how long its
+/// quota lasts; its errors say to wait a few minutes.
+const UPSTREAM_RETRY_AFTER_SECONDS: u64 = 60;
+
+/// Error returned by every route. Serializes to
+/// `{"success":false,"error":{"code","message","retryable"}}` with a real
+/// HTTP status so proxies and clients can tell failures apart.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub code: &'static str,
+    pub message: String,
+    pub retryable: bool,
+    pub details: Option<Value>,
+    pub headers: Vec<(&'static str, String)>,
+}
+
+impl ApiError {
+    pub fn new(status: u16, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            retryable: status == 429 || status == 502 || status == 503,
+            details: None,
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(400, "invalid_request", message)
+    }
+
+    pub fn unauthorized(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(401, code, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(403, "forbidden", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(404, "not_found", message)
+    }
+
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(409, code, message)
+    }
+
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        Self::new(413, "payload_too_large", message)
+    }
+
+    pub fn rate_limited(message: impl Into<String>, retry_after: u64) -> Self {
+        Self::new(429, "rate_limited", message).with_header("Retry-After", retry_after.to_string())
+    }
+
+    pub fn upstream(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(502, code, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(500, "internal_error", message)
+    }
+
+    /// Logs `detail` and returns a 500 that does not repeat it, so SQL
+    /// errors, binding names and storage failures stay out of responses.
+    pub fn internal_logged(detail: impl fmt::Display) -> Self {
+        console_error!("Internal error: {}", detail);
+        Self::internal("Internal error")
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut error = json!({
+            "code": self.code,
+            "message": self.message,
+            "retryable": self.retryable,
+        });
+        if let Some(details) = &self.details {
+            error["details"] = details.clone();
+        }
+
+        json!({
+            "success": false,
+            "error": error,
+        })
+    }
+
+    pub fn into_response(self) -> worker::Result<Response> {
+        let response = Response::from_json(&self.to_json())?.with_status(self.status);
+        for (name, value) in &self.headers {
+            response.headers().set(name, value)?;
+        }
+        Ok(response)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.code, self.status, self.message)
+    }
+}
+
+impl From<worker::Error> for ApiError {
+    fn from(e: worker::Error) -> Self {
+        ApiError::internal_logged(e)
+    }
+}
+
+impl From<UpstreamError> for ApiError {
+    fn from(e: UpstreamError) -> Self {
+        let message = e.to_string();
+        match e {
+            UpstreamError::NotConfigured => ApiError::internal(message),
+            UpstreamError::RateLimited => ApiError::new(429, "upstream_rate_limited", message)
+                .with_header("Retry-After", UPSTREAM_RETRY_AFTER_SECONDS.to_string()),
+            UpstreamError::InvalidRequest(_) => ApiError::bad_request(message),
+            UpstreamError::AccessDenied => ApiError::upstream("upstream_access_denied", message).with_retryable(false),
+            UpstreamError::ServerError | UpstreamError::Transport(_) => ApiError::upstream("upstream_error", message),
+            // The upstream body can echo our request or name internal
+            // resources, so it is only logged.
+            UpstreamError::Api { code, .. } | UpstreamError::Http { status: code, .. } => {
+                console_error!("Upstream error: {}", message);
+                ApiError::upstream("upstream_error", format!("Upstream request failed with status {}", code)).with_retryable(code >= 500)
+            }
+            UpstreamError::Parse(_) | UpstreamError::MissingData(_) => {
+                ApiError::upstream("upstream_invalid_response", message).with_retryable(false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_envelope_escapes_message() {
+        let error = ApiError::bad_request("bad \"quoted\" input");
+        let body = error.to_json().to_string();
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(parsed["success"], false);
+        assert_eq!(parsed["error"]["code"], "invalid_request");
+        assert_eq!(parsed["error"]["message"], "bad \"quoted\" input");
+        assert_eq!(parsed["error"]["retryable"], false);
+    }
+
+    #[test]
+    fn test_upstream_errors_map_to_gateway_statuses() {
+        let error: ApiError = UpstreamError::RateLimited.into();
+        assert_eq!(error.status, 429);
+        assert!(error.retryable);
+        assert!(error.headers.contains(&("Retry-After", "60".to_string())));
+
+        let error: ApiError = UpstreamError::ServerError.into();
+        assert_eq!(error.status, 502);
+        assert!(error.retryable);
+
+        let error: ApiError = UpstreamError::InvalidRequest("nope".into()).into();
+        assert_eq!(error.status, 400);
+        assert!(!error.retryable);
+    }
+}
@@ -0,0 +1,265 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::assets::{asset_url, Asset, URL_TTL_SECONDS};
+use crate::authorize;
+use crate::error::{ApiError, ApiResult};
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+const KINDS: [&str; 4] = ["generate", "edit", "generate_video", "edit_video"];
+
+#[derive(Deserialize, Serialize)]
+struct HistoryItem {
+    id: String,
+    kind: String,
+    model: String,
+    prompt: String,
+    status: String,
+    error_code: Option<String>,
+    latency_ms: Option<u64>,
+    output_bytes: Option<u64>,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct HistoryRow {
+    #[serde(flatten)]
+    item: HistoryItem,
+    parameters: String,
+    job_id: Option<String>,
+}
+
+/// Cursors are opaque to clients: the `(created_at, id)` of the last item
+/// on the previous page, which is the sort key of the listing.
+fn encode_cursor(created_at: &str, id: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(format!("{}|{}", created_at, id))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(String, String)> {
+    let bytes = general_purpose::URL_SAFE_NO_PAD.decode(cursor).ok()?;
+    let text = String::from_utf8(bytes).ok()?;
+    let (created_at, id) = text.split_once('|')?;
+    Some((created_at.to_string(), id.to_string()))
+}
+
+/// Accepts `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS` (with `T` allowed as the
+/// separator) and returns it in the format stored by `datetime('now')`.
+fn normalize_date(value: &str) -> Option<String> {
+    let value = value.replacen('T', " ", 1);
+    let shape_ok = |s: &str, pattern: &str| {
+        s.len() == pattern.len()
+            && s.chars().zip(pattern.chars()).all(|(c, p)| match p {
+                'd' => c.is_ascii_digit(),
+                _ => c == p,
+            })
+    };
+
+    if shape_ok(&value, "dddd-dd-dd") {
+        Some(format!("{} 00:00:00", value))
+    } else if shape_ok(&value, "dddd-dd-dd dd:dd:dd") {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// The exclusive upper bound for `to`, as a `normalize_date` value and the
+/// SQLite modifier to apply to it. A bare date covers that whole day, so
+/// the bound is the next midnight; a timestamp is used as given.
+fn to_bound(value: &str) -> Option<(String, &'static str)> {
+    let date_only = !value.contains(['T', ' ']);
+    let modifier = if date_only { "+1 day" } else { "+0 seconds" };
+    normalize_date(value).map(|bound| (bound, modifier))
+}
+
+pub fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Where the result of a generation can be fetched: its first stored asset,
+/// or the status endpoint for a video that has not been collected yet.
+fn download_url(env: &Env, request_url: &Url, row: &HistoryRow, assets: &[Asset]) -> Option<String> {
+    if let Some(asset) = assets.first() {
+        return Some(asset_url(env, request_url, &asset.id, URL_TTL_SECONDS));
+    }
+    match (row.item.status.as_str(), row.job_id.as_deref()) {
+        ("succeeded" | "pending", Some(job_id)) => Some(format!(
+            "{}/video_status/{}",
+            request_url.origin().ascii_serialization(),
+            job_id
+        )),
+        _ => None,
+    }
+}
+
+/// `GET /history` — the caller's generations, newest first.
+///
+/// Query parameters: `limit`, `cursor`, `kind`, `from`, `to` and `q` (a
+/// case-insensitive substring of the prompt).
+pub async fn handle_list(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let url = req.url()?;
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+
+    let limit = match param("limit") {
+        Some(value) => value
+            .parse::<u32>()
+            .ok()
+            .filter(|limit| (1..=MAX_PAGE_SIZE).contains(limit))
+            .ok_or_else(|| ApiError::bad_request(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)))?,
+        None => DEFAULT_PAGE_SIZE,
+    };
+
+    let mut sql = String::from(
+        "SELECT id, kind, model, prompt, status, error_code, latency_ms, output_bytes, created_at \
+         FROM generations WHERE user_id = ?",
+    );
+    let mut values: Vec<String> = Vec::new();
+
+    if let Some(kind) = param("kind") {
+        if !KINDS.contains(&kind.as_str()) {
+            return Err(ApiError::bad_request(format!("kind must be one of: {}", KINDS.join(", "))));
+        }
+        sql.push_str(" AND kind = ?");
+        values.push(kind);
+    }
+    if let Some(from) = param("from") {
+        let from = normalize_date(&from).ok_or_else(|| ApiError::bad_request("from must be a date (YYYY-MM-DD)"))?;
+        sql.push_str(" AND created_at >= ?");
+        values.push(from);
+    }
+    if let Some(to) = param("to") {
+        let (to, modifier) = to_bound(&to).ok_or_else(|| ApiError::bad_request("to must be a date (YYYY-MM-DD)"))?;
+        sql.push_str(" AND created_at < datetime(?, ?)");
+        values.push(to);
+        values.push(modifier.to_string());
+    }
+    if let Some(q) = param("q").filter(|q| !q.trim().is_empty()) {
+        sql.push_str(" AND prompt LIKE ? ESCAPE '\\'");
+        values.push(format!("%{}%", escape_like(q.trim())));
+    }
+    if let Some(cursor) = param("cursor") {
+        let (created_at, id) = decode_cursor(&cursor).ok_or_else(|| ApiError::bad_request("Invalid cursor"))?;
+        sql.push_str(" AND (created_at < ? OR (created_at = ? AND id < ?))");
+        values.push(created_at.clone());
+        values.push(created_at);
+        values.push(id);
+    }
+    sql.push_str(" ORDER BY created_at DESC, id DESC LIMIT ?");
+
+    let mut binds = vec![D1Type::Integer(user.id)];
+    binds.extend(values.iter().map(|value| D1Type::Text(value)));
+    binds.push(D1Type::Integer(limit as i32 + 1));
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(&sql);
+    let query = statement.bind_refs(&binds)?;
+    let mut items: Vec<HistoryItem> = query.all().await?.results()?;
+
+    let next_cursor = if items.len() > limit as usize {
+        items.truncate(limit as usize);
+        items.last().map(|last| encode_cursor(&last.created_at, &last.id))
+    } else {
+        None
+    };
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "items": items,
+        "next_cursor": next_cursor,
+    }))?)
+}
+
+/// Every output of a generation, in the order the model returned them.
+async fn generation_assets(env: &Env, user_id: i32, generation_id: &str) -> Result<Vec<Asset>> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "SELECT id, content_type, size FROM assets WHERE generation_id = ? AND user_id = ? ORDER BY created_at, rowid",
+    );
+    let query = statement.bind_refs(&[D1Type::Text(generation_id), D1Type::Integer(user_id)])?;
+    query.all().await?.results()
+}
+
+/// `GET /history/{id}` — one generation with its parameters and outputs.
+pub async fn handle_get(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let id = ctx.param("id").ok_or_else(|| ApiError::bad_request("Missing generation id"))?;
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(
+        "SELECT id, kind, model, prompt, status, error_code, latency_ms, output_bytes, created_at, parameters, \
+         (SELECT j.id FROM video_jobs j WHERE j.generation_id = generations.id) AS job_id \
+         FROM generations WHERE id = ? AND user_id = ?",
+    );
+    let query = statement.bind_refs(&[D1Type::Text(id), D1Type::Integer(user.id)])?;
+    let row: HistoryRow = query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Generation not found"))?;
+
+    let parameters: Value = serde_json::from_str(&row.parameters).unwrap_or(Value::Null);
+    let url = req.url()?;
+    let assets = generation_assets(&ctx.env, user.id, &row.item.id).await?;
+    let download_url = download_url(&ctx.env, &url, &row, &assets);
+    let assets: Vec<Value> = assets
+        .iter()
+        .map(|asset| {
+            json!({
+                "asset_id": asset.id,
+                "asset_url": asset_url(&ctx.env, &url, &asset.id, URL_TTL_SECONDS),
+                "mime_type": asset.content_type,
+                "size": asset.size,
+            })
+        })
+        .collect();
+
+    let mut generation = serde_json::to_value(&row.item).map_err(ApiError::internal_logged)?;
+    generation["parameters"] = parameters;
+    generation["download_url"] = json!(download_url);
+    generation["assets"] = json!(assets);
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "generation": generation,
+    }))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = encode_cursor("2024-05-01 12:00:00", "abc-123");
+        assert_eq!(
+            decode_cursor(&cursor),
+            Some(("2024-05-01 12:00:00".to_string(), "abc-123".to_string()))
+        );
+        assert_eq!(decode_cursor("not a cursor!"), None);
+    }
+
+    #[test]
+    fn test_normalize_date() {
+        assert_eq!(normalize_date("2024-05-01").as_deref(), Some("2024-05-01 00:00:00"));
+        assert_eq!(normalize_date("2024-05-01T08:30:00").as_deref(), Some("2024-05-01 08:30:00"));
+        assert_eq!(normalize_date("05/01/2024"), None);
+        assert_eq!(normalize_date("2024-05-01'; DROP"), None);
+    }
+
+    #[test]
+    fn test_to_bound_includes_a_bare_date() {
+        assert_eq!(to_bound("2024-05-01"), Some(("2024-05-01 00:00:00".to_string(), "+1 day")));
+        assert_eq!(to_bound("2024-05-01T08:30:00"), Some(("2024-05-01 08:30:00".to_string(), "+0 seconds")));
+        assert_eq!(to_bound("2024-05-01 08:30:00"), Some(("2024-05-01 08:30:00".to_string(), "+0 seconds")));
+        assert_eq!(to_bound("tomorrow"), None);
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("100%_done\\"), "100\\%\\_done\\\\");
+    }
+}
@@ -0,0 +1,205 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::json;
+
+use crate::error::ApiError;
+
+/// Formats Gemini accepts as image input.
+pub const IMAGE_INPUT_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/webp", "image/gif"];
+
+/// Formats Veo accepts as the first frame of a video.
+pub const VIDEO_INPUT_TYPES: [&str; 3] = ["image/png", "image/jpeg", "image/webp"];
+
+/// What the first bytes of an upload say it is.
+#[derive(Debug, PartialEq)]
+enum Sniffed {
+    /// A complete PNG, JPEG, WebP or GIF.
+    Image(&'static str),
+    /// Starts like one of those but is cut short or malformed.
+    Corrupt(&'static str),
+    /// A recognizable format we do not forward, e.g. TIFF.
+    Other(&'static str),
+    Unknown,
+}
+
+/// GIF ends with a fixed trailer; some encoders pad after it.
+fn ends_with(bytes: &[u8], trailer: &[u8]) -> bool {
+    let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |last| last + 1);
+    bytes[..end].ends_with(trailer)
+}
+
+/// Where `marker` first occurs at or after `from`. PNG and JPEG end
+/// markers are searched for rather than expected last, because motion
+/// photos and some editors append data after them.
+fn find(bytes: &[u8], from: usize, marker: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(marker.len())
+        .position(|window| window == marker)
+        .map(|at| from + at)
+}
+
+/// A PNG opens with a 13-byte `IHDR` chunk and is complete once an `IEND`
+/// chunk, CRC included, follows it.
+fn png_complete(bytes: &[u8]) -> bool {
+    let header = bytes.get(8..16) == Some(b"\0\0\0\x0dIHDR");
+    header && find(bytes, 33, b"IEND").is_some_and(|at| at + 8 <= bytes.len())
+}
+
+/// A JPEG opens with SOI and a marker segment whose length fits the file,
+/// and is complete once an EOI marker follows that segment.
+fn jpeg_complete(bytes: &[u8]) -> bool {
+    let Some(&[0xff, marker, high, low]) = bytes.get(2..6) else {
+        return false;
+    };
+    let length = u16::from_be_bytes([high, low]) as usize;
+    let segment_end = 4 + length;
+    (0xc0..=0xfe).contains(&marker) && length >= 2 && segment_end <= bytes.len() && find(bytes, segment_end, b"\xff\xd9").is_some()
+}
+
+fn sniff(bytes: &[u8]) -> Sniffed {
+    let starts = |prefix: &[u8]| bytes.starts_with(prefix);
+
+    if starts(b"\x89PNG\r\n\x1a\n") {
+        return if png_complete(bytes) { Sniffed::Image("image/png") } else { Sniffed::Corrupt("image/png") };
+    }
+    if starts(b"\xff\xd8\xff") {
+        return if jpeg_complete(bytes) { Sniffed::Image("image/jpeg") } else { Sniffed::Corrupt("image/jpeg") };
+    }
+    if starts(b"GIF87a") || starts(b"GIF89a") {
+        let complete = bytes.len() >= 14 && ends_with(bytes, b";");
+        return if complete { Sniffed::Image("image/gif") } else { Sniffed::Corrupt("image/gif") };
+    }
+    if starts(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        // The RIFF header gives the size of everything after its first 8 bytes.
+        let size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+        let chunk = bytes.get(12..16);
+        let complete = size + 8 <= bytes.len() && matches!(chunk, Some(b"VP8 " | b"VP8L" | b"VP8X"));
+        return if complete { Sniffed::Image("image/webp") } else { Sniffed::Corrupt("image/webp") };
+    }
+
+    if starts(b"II*\0") || starts(b"MM\0*") {
+        return Sniffed::Other("image/tiff");
+    }
+    if starts(b"BM") {
+        return Sniffed::Other("image/bmp");
+    }
+    if bytes.get(4..8) == Some(b"ftyp") {
+        match bytes.get(8..12) {
+            Some(b"heic" | b"heix" | b"mif1" | b"msf1") => return Sniffed::Other("image/heic"),
+            Some(b"avif" | b"avis") => return Sniffed::Other("image/avif"),
+            _ => {}
+        }
+    }
+    Sniffed::Unknown
+}
+
+/// Decodes a base64 upload and returns its real MIME type, which must be
+/// one of `accepted`. The type the client declared is not trusted; `label`
+/// names the upload in errors, e.g. "Image 2".
+pub fn detect(label: &str, data: &str, accepted: &[&str]) -> Result<&'static str, ApiError> {
+    let bytes = general_purpose::STANDARD
+        .decode(data)
+        .map_err(|_| ApiError::new(400, "invalid_image", format!("{} is not valid base64", label)))?;
+
+    let unsupported = |found: Option<&str>| {
+        let message = match found {
+            Some(found) => format!("{} is {}, which is not supported here; use {}", label, found, accepted.join(", ")),
+            None => format!("{} is not a recognized image; use {}", label, accepted.join(", ")),
+        };
+        ApiError::new(415, "unsupported_image_type", message).with_details(json!({
+            "detected": found,
+            "supported": accepted,
+        }))
+    };
+
+    match sniff(&bytes) {
+        Sniffed::Image(mime_type) if accepted.contains(&mime_type) => Ok(mime_type),
+        Sniffed::Image(mime_type) | Sniffed::Other(mime_type) => Err(unsupported(Some(mime_type))),
+        Sniffed::Corrupt(mime_type) => Err(ApiError::new(
+            400,
+            "invalid_image",
+            format!("{} looks like {} but is truncated or corrupt", label, mime_type),
+        )
+        .with_details(json!({ "detected": mime_type }))),
+        Sniffed::Unknown => Err(unsupported(None)),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The smallest complete file of each format, base64-encoded.
+    pub const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+    pub const GIF: &str = "R0lGODlhAQABAAAAACwAAAAAAQABAAACAUwAOw==";
+    pub const WEBP: &str = "UklGRhoAAABXRUJQVlA4TA0AAAAvAAAAEAcQERGIiP4HAA==";
+    pub const JPEG: &str = "/9j/4AAQSkZJRgABAQAAAQABAAD/2wBDAP//////////////////////////////////////////////////////////////////////////////////////wAALCAABAAEBAREA/8QAFAABAAAAAAAAAAAAAAAAAAAAA//EABQQAQAAAAAAAAAAAAAAAAAAAAD/2gAIAQEAAD8AN//Z";
+
+    fn bytes(data: &str) -> Vec<u8> {
+        general_purpose::STANDARD.decode(data).unwrap()
+    }
+
+    #[test]
+    fn test_sniff_recognizes_formats() {
+        assert_eq!(sniff(&bytes(PNG)), Sniffed::Image("image/png"));
+        assert_eq!(sniff(&bytes(JPEG)), Sniffed::Image("image/jpeg"));
+        assert_eq!(sniff(&bytes(GIF)), Sniffed::Image("image/gif"));
+        assert_eq!(sniff(&bytes(WEBP)), Sniffed::Image("image/webp"));
+        assert_eq!(sniff(b"II*\0rest"), Sniffed::Other("image/tiff"));
+        assert_eq!(sniff(b"hello"), Sniffed::Unknown);
+        assert_eq!(sniff(b""), Sniffed::Unknown);
+
+        // Padding after the trailer is fine.
+        let mut padded = bytes(JPEG);
+        padded.extend([0, 0]);
+        assert_eq!(sniff(&padded), Sniffed::Image("image/jpeg"));
+    }
+
+    #[test]
+    fn test_sniff_accepts_data_after_the_end_marker() {
+        // Motion photos append an MP4 after the image.
+        let trailer = b"\0\0\0\x18ftypmp42 appended video";
+
+        let mut jpeg = bytes(JPEG);
+        jpeg.extend(trailer);
+        assert_eq!(sniff(&jpeg), Sniffed::Image("image/jpeg"));
+
+        let mut png = bytes(PNG);
+        png.extend(trailer);
+        assert_eq!(sniff(&png), Sniffed::Image("image/png"));
+    }
+
+    #[test]
+    fn test_sniff_rejects_broken_headers() {
+        let mut jpeg = bytes(JPEG);
+        jpeg[4..6].copy_from_slice(&[0xff, 0xff]);
+        assert_eq!(sniff(&jpeg), Sniffed::Corrupt("image/jpeg"));
+
+        let mut png = bytes(PNG);
+        png[12..16].copy_from_slice(b"IDAT");
+        assert_eq!(sniff(&png), Sniffed::Corrupt("image/png"));
+    }
+
+    #[test]
+    fn test_sniff_detects_truncation() {
+        for data in [PNG, JPEG, GIF, WEBP] {
+            let full = bytes(data);
+            assert!(matches!(sniff(&full[..full.len() - 3]), Sniffed::Corrupt(_)), "{}", data);
+        }
+    }
+
+    #[test]
+    fn test_detect_errors() {
+        assert_eq!(detect("Image 1", JPEG, &IMAGE_INPUT_TYPES).unwrap(), "image/jpeg");
+
+        let error = detect("Image 1", "not base64!", &IMAGE_INPUT_TYPES).unwrap_err();
+        assert_eq!((error.status, error.code), (400, "invalid_image"));
+
+        let error = detect("Image 1", GIF, &VIDEO_INPUT_TYPES).unwrap_err();
+        assert_eq!((error.status, error.code), (415, "unsupported_image_type"));
+        assert_eq!(error.details.unwrap()["detected"], "image/gif");
+
+        let error = detect("Image 2", "aGVsbG8=", &IMAGE_INPUT_TYPES).unwrap_err();
+        assert!(error.message.starts_with("Image 2 is not a recognized image"));
+    }
+}
@@ -1,161 +1,281 @@
+#![recursion_limit = "512"]
+
+pub mod account;
+pub mod admin;
+pub mod api_keys;
+pub mod assets;
+pub mod edit_sessions;
+pub mod error;
+pub mod history;
+pub mod image_format;
+pub mod login_guard;
+pub mod mail;
+pub mod models;
 pub mod openapi;
+pub mod password_reset;
+pub mod rate_limit;
+pub mod sessions;
 pub mod swagger_ui;
+pub mod uploads;
+pub mod upstream;
+pub mod usage;
+pub mod validation;
+pub mod video_jobs;
+pub mod webhooks;
 
 use worker::*;
 use worker::d1::D1Type;
 use serde::{Deserialize, Serialize};
-use serde_json::{self, json};
 use base64::{Engine as _, engine::general_purpose};
 
 
+use crate::assets::Asset;
+use crate::error::{ApiError, ApiResult};
+use crate::login_guard::LoginGuard;
 use crate::openapi::openapi_spec;
+use crate::rate_limit::LimitKind;
 use crate::swagger_ui::swagger_ui_html;
+use crate::usage::{base64_decoded_len, Generation, GenerationKind, GenerationStatus};
+use crate::video_jobs::{NewVideoJob, VideoJob};
+use crate::upstream::{GeminiClient, UpstreamError, GeminiPart, GeminiResponse, ImageOptions, InlineData, SafetyRating, GenerateVideoResponse, VideoFile, VideoGenerationResponse, VideoOptions, VideoSample, VideoStatusResponse};
 
 #[derive(Deserialize)]
 struct GenerateRequest {
     prompt: String,
+    /// One of `GET /models`; the kind's default when omitted.
+    #[serde(default)]
+    model: Option<String>,
+    /// Also return the image as base64, as responses did before assets.
+    #[serde(default)]
+    inline: bool,
+    /// How many images to ask for, 1 to [`MAX_IMAGE_COUNT`].
+    #[serde(default)]
+    count: Option<u32>,
+    #[serde(flatten)]
+    settings: ImageSettings,
 }
 
+/// Generation settings accepted by `/generate` and `/edit`, forwarded as
+/// Gemini's `generationConfig`. Unset fields keep the model's defaults.
+#[derive(Debug, Deserialize, Default)]
+struct ImageSettings {
+    #[serde(default)]
+    aspect_ratio: Option<String>,
+    #[serde(default)]
+    seed: Option<i64>,
+    #[serde(default)]
+    temperature: Option<f64>,
+    /// `TEXT` and/or `IMAGE`, in any case.
+    #[serde(default)]
+    response_modalities: Option<Vec<String>>,
+}
+
+impl ImageSettings {
+    /// Uppercases the modalities and checks every field.
+    fn validate(mut self) -> ApiResult<Self> {
+        if let Some(modalities) = &mut self.response_modalities {
+            for modality in modalities.iter_mut() {
+                *modality = modality.trim().to_uppercase();
+            }
+        }
+        let errors = validation::check_image_options(
+            self.aspect_ratio.as_deref(),
+            self.seed,
+            self.temperature,
+            self.response_modalities.as_deref(),
+        );
+        if !errors.is_empty() {
+            return Err(validation::into_api_error(errors));
+        }
+        Ok(self)
+    }
+
+    fn options(&self, candidate_count: u32) -> ImageOptions<'_> {
+        ImageOptions {
+            candidate_count,
+            aspect_ratio: self.aspect_ratio.as_deref(),
+            seed: self.seed,
+            temperature: self.temperature,
+            response_modalities: self.response_modalities.as_deref(),
+        }
+    }
+
+    /// Adds the settings that were given to a ledger `parameters` object.
+    fn record(&self, parameters: &mut serde_json::Value) {
+        let settings = [
+            ("aspect_ratio", serde_json::json!(self.aspect_ratio)),
+            ("seed", serde_json::json!(self.seed)),
+            ("temperature", serde_json::json!(self.temperature)),
+            ("response_modalities", serde_json::json!(self.response_modalities)),
+        ];
+        for (key, value) in settings {
+            if !value.is_null() {
+                parameters[key] = value;
+            }
+        }
+    }
+}
+
+/// Upper bound for `count` on `/generate`. Each image counts against the
+/// image rate limit.
+const MAX_IMAGE_COUNT: u32 = 4;
+
+/// `/edit` takes either one `image` or an ordered list of `images`, which
+/// the prompt can refer to as image 1, 2, … A `mime_type` sent alongside is
+/// accepted but not trusted: the type is read from the data.
 #[derive(Deserialize)]
 struct EditRequest {
-    image: String,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    images: Vec<EditImage>,
     prompt: String,
     #[serde(default)]
-    #[allow(dead_code)]
-    mime_type: String,
+    model: Option<String>,
+    #[serde(default)]
+    inline: bool,
+    #[serde(flatten)]
+    settings: ImageSettings,
 }
 
+#[derive(Deserialize)]
+struct EditImage {
+    /// Base64-encoded image.
+    data: String,
+}
+
+/// Upper bound on input images for one `/edit`.
+const MAX_EDIT_IMAGES: usize = 3;
+
+/// `/edit` as `multipart/form-data`: the images as `image` file parts, in
+/// order, and the other fields as text parts.
+const EDIT_FORM: uploads::Form = uploads::Form {
+    text: &["prompt", "model", "aspect_ratio"],
+    scalars: &["inline", "seed", "temperature"],
+    lists: &["response_modalities"],
+    max_files: MAX_EDIT_IMAGES,
+};
+
 #[derive(Deserialize)]
 struct GenerateVideoRequest {
     prompt: String,
     #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
     negative_prompt: Option<String>,
     #[serde(default)]
     aspect_ratio: Option<String>,
     #[serde(default)]
     resolution: Option<String>,
+    /// Receives a signed POST when the job succeeds or fails.
+    #[serde(default)]
+    callback_url: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct EditVideoRequest {
     prompt: String,
     image: String,
-    mime_type: String,
+    #[serde(default)]
+    model: Option<String>,
     #[serde(default)]
     negative_prompt: Option<String>,
     #[serde(default)]
     aspect_ratio: Option<String>,
     #[serde(default)]
     resolution: Option<String>,
+    /// Receives a signed POST when the job succeeds or fails.
+    #[serde(default)]
+    callback_url: Option<String>,
 }
 
+/// `/edit_video` as `multipart/form-data`: the first frame as an `image`
+/// file part and the other fields as text parts.
+const EDIT_VIDEO_FORM: uploads::Form = uploads::Form {
+    text: &["prompt", "model", "negative_prompt", "aspect_ratio", "resolution", "callback_url"],
+    scalars: &[],
+    lists: &[],
+    max_files: 1,
+};
+
+/// The top-level fields describe the first image; `images` lists all of
+/// them, in the order the model returned them.
 #[derive(Serialize)]
-struct VideoOperationResponse {
+struct ImageResponse {
     success: bool,
-    operation_name: Option<String>,
-    error: Option<String>,
-}
-
-#[derive(Deserialize)]
-struct VideoStatusResponse {
-    done: Option<bool>,
-    response: Option<VideoGenerationResponse>,
-    #[allow(dead_code)]
-    name: Option<String>,
-}
-
-#[derive(Deserialize)]
-struct VideoGenerationResponse {
-    #[serde(rename = "generateVideoResponse")]
-    generate_video_response: GenerateVideoResponse,
-}
-
-#[derive(Deserialize)]
-struct GenerateVideoResponse {
-    #[serde(rename = "generatedSamples")]
-    generated_samples: Vec<VideoSample>,
-}
-
-#[derive(Deserialize)]
-struct VideoSample {
-    video: VideoFile,
-}
-
-#[derive(Deserialize)]
-struct VideoFile {
-    uri: String,
-}
-
-#[derive(Deserialize)]
-struct GeminiError {
-    error: GeminiErrorDetails,
-}
-
-#[derive(Deserialize)]
-struct GeminiErrorDetails {
-    code: u16,
-    message: String,
-    #[allow(dead_code)]
-    status: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct GeminiResponse {
-    candidates: Vec<GeminiCandidate>,
-}
-
-#[derive(Debug, Deserialize)]
-struct GeminiCandidate {
-    content: Option<GeminiContent>,
-    #[serde(rename = "finishReason")]
-    #[allow(dead_code)]
-    finish_reason: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct GeminiContent {
-    parts: Vec<GeminiPart>,
+    asset_id: String,
+    asset_url: String,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    images: Vec<StoredImage>,
+    /// The model's text parts, when it sent any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum GeminiPart {
-    Text {
-        #[allow(dead_code)]
-        text: String,
-    },
-    Image {
-        #[serde(rename = "inlineData")]
-        inline_data: InlineData,
-    },
+impl ImageResponse {
+    /// `stored` must not be empty.
+    fn new(stored: Vec<StoredImage>, text: Option<String>) -> Self {
+        let first = stored[0].clone();
+        Self {
+            success: true,
+            asset_id: first.asset_id,
+            asset_url: first.asset_url,
+            mime_type: first.mime_type,
+            image: first.image,
+            images: stored,
+            text,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct InlineData {
-    #[serde(rename = "mimeType")]
-    #[allow(dead_code)]
+#[derive(Clone, Serialize)]
+struct StoredImage {
+    asset_id: String,
+    asset_url: String,
     mime_type: String,
-    data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
 }
 
+#[derive(Serialize)]
+struct VideoOperationResponse {
+    success: bool,
+    job_id: String,
+}
 
 #[derive(Deserialize)]
 struct RegisterRequest {
     email: String,
     password: String,
+    /// Start a session instead of issuing a first API key, as `/login`
+    /// does with the same flag.
+    #[serde(default)]
+    session: bool,
 }
 
 #[derive(Deserialize)]
 struct LoginRequest {
     email: String,
     password: String,
+    /// Name for the key this login issues, as shown by `GET /keys`.
+    #[serde(default)]
+    key_name: Option<String>,
+    /// Issue a short-lived session token and refresh token instead of an
+    /// API key, so browsers never hold a permanent credential.
+    #[serde(default)]
+    session: bool,
+    /// Revoke the caller's other active keys named `key_name`.
+    #[serde(default)]
+    replace: bool,
 }
 
 #[derive(Serialize)]
 struct AuthResponse {
     success: bool,
-    api_key: Option<String>,
-    error: Option<String>,
+    api_key: String,
+    key_id: String,
 }
 
 #[derive(Serialize)]
@@ -167,6 +287,31 @@ struct User {
     created_at: String,
 }
 
+/// The caller resolved from the `Authorization` header, including any
+/// per-user rate limit overrides (NULL means use the default).
+#[derive(Deserialize)]
+pub struct AuthUser {
+    pub id: i32,
+    pub email: String,
+    pub image_limit_per_minute: Option<u32>,
+    pub image_limit_per_day: Option<u32>,
+    pub video_limit_per_minute: Option<u32>,
+    pub video_limit_per_day: Option<u32>,
+    pub status_limit_per_minute: Option<u32>,
+    #[serde(deserialize_with = "bool_from_int")]
+    pub is_admin: bool,
+    pub disabled_at: Option<String>,
+}
+
+/// The `users` columns, aliased `u`, that make up an [`AuthUser`].
+pub const AUTH_USER_COLUMNS: &str = "u.id, u.email, u.image_limit_per_minute, u.image_limit_per_day, \
+    u.video_limit_per_minute, u.video_limit_per_day, u.status_limit_per_minute, u.is_admin, u.disabled_at";
+
+/// SQLite has no boolean type; flags are stored as 0 or 1.
+fn bool_from_int<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<bool, D::Error> {
+    Ok(i64::deserialize(deserializer)? != 0)
+}
+
 fn is_test_mode(req: &Request, env: &Env) -> bool {
     if env.var("TEST_MODE")
         .map(|v| v.to_string() == "true")
@@ -194,286 +339,152 @@ fn is_test_mode(req: &Request, env: &Env) -> bool {
 fn cors_headers() -> Headers {
     let headers = Headers::new();
     headers.set("Access-Control-Allow-Origin", "*").unwrap();
-    headers.set("Access-Control-Allow-Methods", "GET, POST, OPTIONS").unwrap();
-    headers.set("Access-Control-Allow-Headers", "Content-Type, Authorization, X-Test-Mode").unwrap();
+    headers.set("Access-Control-Allow-Methods", "GET, POST, PATCH, DELETE, OPTIONS").unwrap();
+    headers.set("Access-Control-Allow-Headers", "Content-Type, Authorization, Range, X-Test-Mode").unwrap();
+    headers.set("Access-Control-Expose-Headers", "Retry-After, X-RateLimit-Limit, X-RateLimit-Remaining, X-RateLimit-Reset, Accept-Ranges, Content-Range, Content-Length").unwrap();
     headers
 }
 
-async fn call_gemini_generate(prompt: &str, api_key: &str) -> Result<GeminiResponse> {
-    let url = "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash-preview:generateContent";
+/// What the model returned for a `/generate` or `/edit` call.
+#[derive(Debug)]
+struct ModelOutput {
+    images: Vec<InlineData>,
+    /// The text parts, one per line. Models use them to describe the image,
+    /// or to explain why there is none.
+    text: Option<String>,
+}
 
-    let headers = Headers::new();
-    headers.set("Content-Type", "application/json").unwrap();
-    headers.set("x-goog-api-key", &api_key.strip_prefix("gp_").unwrap_or(api_key)).unwrap();
-
-    let request_body = json!({
-        "contents": [{
-            "parts": [{
-                "text": prompt
-            }]
-        }]
-    });
+/// Gemini's safety filters stopped the prompt or the output. Not retryable:
+/// the same request is blocked again.
+fn content_blocked(stage: &str, reason: &str, safety_ratings: &[SafetyRating]) -> ApiError {
+    ApiError::new(
+        422,
+        "content_blocked",
+        format!("The {} was blocked by the model's safety filters ({})", stage, reason),
+    )
+    .with_details(serde_json::json!({
+        "blocked": stage,
+        "reason": reason,
+        "safety_ratings": safety_ratings,
+    }))
+}
 
-    let json_body = serde_json::to_string(&request_body)?;
-
-    let request = Request::new_with_init(
-        url,
-        &RequestInit::new()
-            .with_method(Method::Post)
-            .with_headers(headers)
-            .with_body(Some(json_body.into())),
-    )?;
-
-    let mut response = Fetch::Request(request).send().await?;
-    let text = response.text().await?;
-
-    if response.status_code() < 200 || response.status_code() >= 300 {
-        if let Ok(error_response) = serde_json::from_str::<GeminiError>(&text) {
-            let error_msg = match error_response.error.code {
-                429 => "Rate limit exceeded. You've reached your API quota. Please wait a few minutes before trying again.".to_string(),
-                403 => "Access denied. Please check your API key and permissions.".to_string(),
-                400 => format!("Invalid request: {}", error_response.error.message),
-                500 => "Server error. Please try again later.".to_string(),
-                _ => format!("API Error ({}): {}", error_response.error.code, error_response.error.message),
-            };
-            return Err(worker::Error::RustError(error_msg));
-        } else {
-            return Err(worker::Error::RustError(format!("API request failed with status {}: {}", response.status_code(), text)));
+/// Every inline image and text part in the response, across candidates.
+fn extract_images_from_response(response: &GeminiResponse) -> ApiResult<ModelOutput> {
+    if let Some(feedback) = &response.prompt_feedback {
+        if let Some(reason) = &feedback.block_reason {
+            return Err(content_blocked("prompt", reason, &feedback.safety_ratings));
         }
     }
-
-    let gemini_response: GeminiResponse = serde_json::from_str(&text)
-        .map_err(|e| worker::Error::RustError(format!("Failed to parse Gemini response: {}", e)))?;
-
-    Ok(gemini_response)
-}
-
-fn extract_image_from_response(response: &GeminiResponse) -> Result<String> {
     if response.candidates.is_empty() {
-        return Err(worker::Error::RustError("No candidates in Gemini response".into()));
+        return Err(ApiError::upstream("no_image", "No candidates in Gemini response").with_retryable(false));
     }
 
-    for candidate in &response.candidates {
-        if let Some(content) = &candidate.content {
-            for part in &content.parts {
-                if let GeminiPart::Image { inline_data } = part {
-
-                    return Ok(inline_data.data.clone());
-                }
-            }
+    let mut images = Vec::new();
+    let mut texts = Vec::new();
+    let parts = response
+        .candidates
+        .iter()
+        .filter_map(|candidate| candidate.content.as_ref())
+        .flat_map(|content| &content.parts);
+    for part in parts {
+        match part {
+            GeminiPart::Image { inline_data } => images.push(inline_data.clone()),
+            GeminiPart::Text { text } if !text.trim().is_empty() => texts.push(text.trim()),
+            GeminiPart::Text { .. } => {}
         }
     }
-
-
-    Err(worker::Error::RustError("No image data found in response".into()))
-}
-
-async fn call_veo_edit(image_data: &str, mime_type: &str, prompt: &str, negative_prompt: Option<&str>, aspect_ratio: Option<&str>, resolution: Option<&str>, api_key: &str) -> Result<String> {
-    let url = "https://generativelanguage.googleapis.com/v1beta/models/veo-3.0-fast-generate-001:predictLongRunning";
-
-    let mut instances = serde_json::json!({
-        "prompt": prompt,
-        "image": {
-            "bytesBase64Encoded": image_data,
-            "mimeType": mime_type
+    let text = (!texts.is_empty()).then(|| texts.join("\n"));
+
+    if images.is_empty() {
+        // Blocked candidates only matter when nothing else got through.
+        if let Some((candidate, reason)) = response
+            .candidates
+            .iter()
+            .find_map(|candidate| candidate.block_reason().map(|reason| (candidate, reason)))
+        {
+            return Err(content_blocked("response", reason, &candidate.safety_ratings));
         }
-    });
 
-    if let Some(neg_prompt) = negative_prompt {
-        instances["negativePrompt"] = serde_json::json!(neg_prompt);
-    }
-
-    let mut parameters = serde_json::json!({});
-    if let Some(ar) = aspect_ratio {
-        parameters["aspectRatio"] = serde_json::json!(ar);
-    }
-    if let Some(res) = resolution {
-        parameters["resolution"] = serde_json::json!(res);
-    }
-
-    let json_body = serde_json::json!({
-        "instances": [instances],
-        "parameters": parameters
-    });
-
-    let headers = Headers::new();
-    headers.set("Content-Type", "application/json").unwrap();
-    headers.set("x-goog-api-key", api_key).unwrap();
-
-    let request = Request::new_with_init(
-        url,
-        &RequestInit::new()
-            .with_method(Method::Post)
-            .with_headers(headers)
-            .with_body(Some(json_body.to_string().into())),
-    )?;
-
-    let mut response = Fetch::Request(request).send().await?;
-    let text = response.text().await?;
-
-    // Check if the response is an error
-    if response.status_code() < 200 || response.status_code() >= 300 {
-        // Try to parse as Gemini error response
-        if let Ok(error_response) = serde_json::from_str::<GeminiError>(&text) {
-            let error_msg = match error_response.error.code {
-                429 => "Rate limit exceeded. You've reached your API quota. Please wait a few minutes before trying again.".to_string(),
-                403 => "Access denied. Please check your API key and permissions.".to_string(),
-                400 => format!("Invalid request: {}", error_response.error.message),
-                500 => "Server error. Please try again later.".to_string(),
-                _ => format!("API Error ({}): {}", error_response.error.code, error_response.error.message),
-            };
-            return Err(worker::Error::RustError(error_msg));
-        } else {
-            return Err(worker::Error::RustError(format!("API request failed with status {}: {}", response.status_code(), text)));
-        }
+        let finish_reason = response
+            .candidates
+            .iter()
+            .find_map(|candidate| candidate.finish_reason.as_deref())
+            .filter(|reason| *reason != "STOP");
+        let message = match (&text, finish_reason) {
+            (Some(text), _) => format!("The model returned no image: {}", text),
+            (None, Some(reason)) => format!("No image data found in response (finish reason {})", reason),
+            (None, None) => "No image data found in response".to_string(),
+        };
+        return Err(ApiError::upstream("no_image", message)
+            .with_retryable(false)
+            .with_details(serde_json::json!({ "text": text, "finish_reason": finish_reason })));
     }
-
-    let operation_response: serde_json::Value = serde_json::from_str(&text)
-        .map_err(|e| worker::Error::RustError(format!("Failed to parse Veo response: {}", e)))?;
-
-    let operation_name = operation_response["name"]
-        .as_str()
-        .ok_or_else(|| worker::Error::RustError("No operation name in response".into()))?;
-
-    Ok(operation_name.to_string())
+    Ok(ModelOutput { images, text })
 }
 
-async fn call_gemini_edit(image_data: &str, prompt: &str, api_key: &str) -> Result<GeminiResponse> {
-    let url = "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash-image-preview:generateContent";
-
-    let headers = Headers::new();
-    headers.set("Content-Type", "application/json").unwrap();
-    headers.set("x-goog-api-key", &api_key.strip_prefix("gp_").unwrap_or(api_key)).unwrap();
-
-    let request_body = json!({
-        "contents": [{
-            "parts": [
-                {
-                    "inline_data": {
-                        "mime_type": "image/png",
-                        "data": image_data
-                    }
-                },
-                {
-                    "text": prompt
-                }
-            ]
-        }]
-    });
-
-    let json_body = serde_json::to_string(&request_body)?;
-
-    let request = Request::new_with_init(
-        url,
-        &RequestInit::new()
-            .with_method(Method::Post)
-            .with_headers(headers)
-            .with_body(Some(json_body.into())),
-    )?;
-
-    let mut response = Fetch::Request(request).send().await?;
-    let text = response.text().await?;
-
-    if response.status_code() < 200 || response.status_code() >= 300 {
-        if let Ok(error_response) = serde_json::from_str::<GeminiError>(&text) {
-            let error_msg = match error_response.error.code {
-                429 => "Rate limit exceeded. You've reached your API quota. Please wait a few minutes before trying again.".to_string(),
-                403 => "Access denied. Please check your API key and permissions.".to_string(),
-                400 => format!("Invalid request: {}", error_response.error.message),
-                500 => "Server error. Please try again later.".to_string(),
-                _ => format!("API Error ({}): {}", error_response.error.code, error_response.error.message),
-            };
-            return Err(worker::Error::RustError(error_msg));
-        } else {
-            return Err(worker::Error::RustError(format!("API request failed with status {}: {}", response.status_code(), text)));
-        }
+/// The input images of an `/edit`, in order, checked against the count and
+/// total size limits and labelled with the format their bytes show.
+fn edit_inputs(body: &EditRequest) -> ApiResult<Vec<InlineData>> {
+    let images = input_images(body.image.as_deref(), &body.images)?;
+    if images.is_empty() {
+        return Err(ApiError::bad_request("image or images is required"));
     }
-
-    let gemini_response: GeminiResponse = serde_json::from_str(&text)
-        .map_err(|e| worker::Error::RustError(format!("Failed to parse Gemini response: {}", e)))?;
-
-    Ok(gemini_response)
+    Ok(images)
 }
 
-async fn call_veo_generate(prompt: &str, negative_prompt: Option<&str>, aspect_ratio: Option<&str>, resolution: Option<&str>, api_key: &str) -> Result<String> {
-    let url = "https://generativelanguage.googleapis.com/v1beta/models/veo-3.0-fast-generate-001:predictLongRunning";
-
-    let mut instances = serde_json::json!({
-        "prompt": prompt
-    });
-
-    if let Some(neg_prompt) = negative_prompt {
-        instances["negativePrompt"] = serde_json::json!(neg_prompt);
+/// Input images sent as either one `image` or a list of `images`; none at
+/// all is fine here.
+fn input_images(image: Option<&str>, images: &[EditImage]) -> ApiResult<Vec<InlineData>> {
+    let inputs: Vec<&str> = match (image, images.is_empty()) {
+        (Some(_), false) => return Err(ApiError::bad_request("Send either image or images, not both")),
+        (Some(image), true) => vec![image],
+        (None, _) => images.iter().map(|image| image.data.as_str()).collect(),
+    };
+    if inputs.len() > MAX_EDIT_IMAGES {
+        return Err(ApiError::bad_request(format!("At most {} images can be edited together", MAX_EDIT_IMAGES)));
     }
 
-    let mut parameters = serde_json::json!({});
-    if let Some(ar) = aspect_ratio {
-        parameters["aspectRatio"] = serde_json::json!(ar);
-    }
-    if let Some(res) = resolution {
-        parameters["resolution"] = serde_json::json!(res);
-    }
+    // Checked before anything is decoded.
+    uploads::check_input_size(inputs.iter().map(|data| base64_decoded_len(data)).sum())?;
 
-    let json_body = serde_json::json!({
-        "instances": [instances],
-        "parameters": parameters
-    });
-
-    let headers = Headers::new();
-    headers.set("Content-Type", "application/json").unwrap();
-    headers.set("x-goog-api-key", api_key).unwrap();
-
-    let request = Request::new_with_init(
-        url,
-        &RequestInit::new()
-            .with_method(Method::Post)
-            .with_headers(headers)
-            .with_body(Some(json_body.to_string().into())),
-    )?;
-
-    let mut response = Fetch::Request(request).send().await?;
-    let text = response.text().await?;
-
-    if response.status_code() < 200 || response.status_code() >= 300 {
-        if let Ok(error_response) = serde_json::from_str::<GeminiError>(&text) {
-            let error_msg = match error_response.error.code {
-                429 => "Rate limit exceeded. You've reached your API quota. Please wait a few minutes before trying again.".to_string(),
-                403 => "Access denied. Please check your API key and permissions.".to_string(),
-                400 => format!("Invalid request: {}", error_response.error.message),
-                500 => "Server error. Please try again later.".to_string(),
-                _ => format!("API Error ({}): {}", error_response.error.code, error_response.error.message),
-            };
-            return Err(worker::Error::RustError(error_msg));
-        } else {
-            return Err(worker::Error::RustError(format!("API request failed with status {}: {}", response.status_code(), text)));
+    let mut images = Vec::with_capacity(inputs.len());
+    for (index, data) in inputs.into_iter().enumerate() {
+        let label = format!("Image {}", index + 1);
+        if data.is_empty() {
+            return Err(ApiError::bad_request(format!("{} is empty", label)));
         }
+        let mime_type = image_format::detect(&label, data, &image_format::IMAGE_INPUT_TYPES)?;
+        images.push(InlineData {
+            mime_type: mime_type.to_string(),
+            data: data.to_string(),
+        });
     }
+    Ok(images)
+}
 
-    let operation_response: serde_json::Value = serde_json::from_str(&text)
-        .map_err(|e| worker::Error::RustError(format!("Failed to parse Veo response: {}", e)))?;
-
-    let operation_name = operation_response["name"]
-        .as_str()
-        .ok_or_else(|| worker::Error::RustError("No operation name in response".into()))?;
-
-
-    Ok(operation_name.to_string())
+fn image_count(requested: Option<u32>) -> ApiResult<u32> {
+    match requested {
+        None => Ok(1),
+        Some(count) if (1..=MAX_IMAGE_COUNT).contains(&count) => Ok(count),
+        Some(_) => Err(ApiError::bad_request(format!("count must be between 1 and {}", MAX_IMAGE_COUNT))),
+    }
 }
 
-async fn mock_veo_generate(prompt: &str, negative_prompt: Option<&str>, aspect_ratio: Option<&str>, resolution: Option<&str>) -> Result<String> {
+async fn mock_veo_generate(prompt: &str, options: &VideoOptions<'_>) -> Result<String> {
     console_log!("Starting Veo video generation with prompt: {}", prompt);
-    if let Some(neg) = negative_prompt {
+    if let Some(neg) = options.negative_prompt {
         console_log!("Negative prompt: {}", neg);
     }
-    if let Some(ar) = aspect_ratio {
+    if let Some(ar) = options.aspect_ratio {
         console_log!("Aspect ratio: {}", ar);
     }
-    if let Some(res) = resolution {
+    if let Some(res) = options.resolution {
         console_log!("Resolution: {}", res);
     }
 
     let operation_id = format!("test_{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
-    let operation_name = format!("models/veo-3.0-fast-generate-001/operations/{}", operation_id);
+    let operation_name = format!("models/{}/operations/{}", options.model, operation_id);
 
     console_log!("Mock Veo API request body created");
     console_log!("Mock operation name: {}", operation_name);
@@ -481,22 +492,22 @@ async fn mock_veo_generate(prompt: &str, negative_prompt: Option<&str>, aspect_r
     Ok(operation_name)
 }
 
-async fn mock_veo_edit(image_data: &str, mime_type: &str, prompt: &str, negative_prompt: Option<&str>, aspect_ratio: Option<&str>, resolution: Option<&str>) -> Result<String> {
+async fn mock_veo_edit(image_data: &str, mime_type: &str, prompt: &str, options: &VideoOptions<'_>) -> Result<String> {
     console_log!("Starting Veo video edit with prompt: {}", prompt);
     console_log!("Image data length: {}", image_data.len());
     console_log!("MIME type: {}", mime_type);
-    if let Some(neg) = negative_prompt {
+    if let Some(neg) = options.negative_prompt {
         console_log!("Negative prompt: {}", neg);
     }
-    if let Some(ar) = aspect_ratio {
+    if let Some(ar) = options.aspect_ratio {
         console_log!("Aspect ratio: {}", ar);
     }
-    if let Some(res) = resolution {
+    if let Some(res) = options.resolution {
         console_log!("Resolution: {}", res);
     }
 
     let operation_id = format!("test_edit_{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
-    let operation_name = format!("models/veo-3.0-fast-generate-001/operations/{}", operation_id);
+    let operation_name = format!("models/{}/operations/{}", options.model, operation_id);
 
     console_log!("Mock Veo edit API request body created");
     console_log!("Mock operation name: {}", operation_name);
@@ -504,32 +515,6 @@ async fn mock_veo_edit(image_data: &str, mime_type: &str, prompt: &str, negative
     Ok(operation_name)
 }
 
-async fn poll_video_operation(operation_name: &str, api_key: &str) -> Result<VideoStatusResponse> {
-    let url = format!("https://generativelanguage.googleapis.com/v1beta/{}", operation_name);
-
-    let headers = Headers::new();
-    headers.set("x-goog-api-key", api_key).unwrap();
-
-    let request = Request::new_with_init(
-        &url,
-        &RequestInit::new()
-            .with_method(Method::Get)
-            .with_headers(headers),
-    )?;
-
-    let mut response = Fetch::Request(request).send().await?;
-    let text = response.text().await?;
-
-    let status_response: VideoStatusResponse = serde_json::from_str(&text)
-        .map_err(|e| worker::Error::RustError(format!("Failed to parse operation status: {}", e)))?;
-
-    if status_response.done.unwrap_or(false) {
-    } else {
-    }
-
-    Ok(status_response)
-}
-
 async fn mock_poll_video_operation(operation_name: &str) -> Result<VideoStatusResponse> {
     console_log!("=== MOCK VIDEO STATUS ENDPOINT CALLED ===");
 
@@ -547,25 +532,32 @@ async fn mock_poll_video_operation(operation_name: &str) -> Result<VideoStatusRe
             },
         }),
         name: Some(operation_name.to_string()),
+        error: None,
     };
 
     Ok(mock_response)
 }
 
 fn extract_video_uri(response: &VideoStatusResponse) -> Result<String> {
-    if let Some(video_response) = &response.response {
-        if !video_response.generate_video_response.generated_samples.is_empty() {
-            let video_uri = &video_response.generate_video_response.generated_samples[0].video.uri;
-            return Ok(video_uri.clone());
-        } else {
-            return Err(worker::Error::RustError("No video samples found".into()));
-        }
-    } else {
-        return Err(worker::Error::RustError("No response field in video status".into()));
-    }
+    let video_response = response
+        .response
+        .as_ref()
+        .ok_or_else(|| worker::Error::RustError("No response field in video status".into()))?;
+
+    video_response
+        .generate_video_response
+        .generated_samples
+        .first()
+        .map(|sample| sample.video.uri.clone())
+        .ok_or_else(|| worker::Error::RustError("No video samples found".into()))
 }
 
-async fn mock_download_video(video_uri: &str) -> Result<String> {
+/// Operations started in test mode are mocked end to end, whoever polls them.
+fn is_mock_operation(operation_name: &str) -> bool {
+    operation_name.contains("/operations/test_")
+}
+
+async fn mock_download_video(video_uri: &str) -> Result<Vec<u8>> {
     console_log!("Mock downloading video from: {}", video_uri);
 
     let fake_mp4_data = vec![
@@ -574,18 +566,11 @@ async fn mock_download_video(video_uri: &str) -> Result<String> {
         0x66, 0x72, 0x65, 0x65, 0x00, 0x00, 0x00, 0x00, 0x6D, 0x64, 0x61, 0x74, 0x00, 0x00, 0x00, 0x00,
     ];
 
-    let video_base64 = general_purpose::STANDARD.encode(&fake_mp4_data);
-    let data_url = format!("data:video/mp4;base64,{}", video_base64);
-
-    console_log!("Mock video downloaded and encoded successfully");
-    Ok(data_url)
+    console_log!("Mock video downloaded successfully");
+    Ok(fake_mp4_data)
 }
 
 
-fn generate_api_key() -> String {
-    format!("gp_{}", uuid::Uuid::new_v4().to_string().replace("-", ""))
-}
-
 fn verify_password(password: &str, hash: &str) -> Result<bool> {
     bcrypt::verify(password, hash)
         .map_err(|e| worker::Error::RustError(format!("Password verification failed: {}", e)))
@@ -600,9 +585,35 @@ fn hash_password(password: &str) -> Result<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_auth_user_reads_sqlite_flags() {
+        let row = serde_json::json!({
+            "id": 7,
+            "email": "ops@example.com",
+            "image_limit_per_minute": null,
+            "image_limit_per_day": null,
+            "video_limit_per_minute": null,
+            "video_limit_per_day": null,
+            "status_limit_per_minute": 60,
+            "is_admin": 1,
+            "disabled_at": null,
+        });
+        let user: AuthUser = serde_json::from_value(row.clone()).unwrap();
+        assert!(user.is_admin);
+        assert!(user.disabled_at.is_none());
+        assert_eq!(user.status_limit_per_minute, Some(60));
+
+        let mut row = row;
+        row["is_admin"] = serde_json::json!(0);
+        row["disabled_at"] = serde_json::json!("2024-05-01 12:00:00");
+        let user: AuthUser = serde_json::from_value(row).unwrap();
+        assert!(!user.is_admin);
+        assert!(user.disabled_at.is_some());
+    }
+
     #[test]
     fn test_generate_api_key() {
-        let api_key = generate_api_key();
+        let api_key = api_keys::generate();
         assert!(api_key.starts_with("gp_"));
         assert_eq!(api_key.len(), 35); // "gp_" + 32 characters (UUID without hyphens)
     }
@@ -626,82 +637,735 @@ mod tests {
         assert_eq!(valid_key.len(), 35);
         assert!(!invalid_key.starts_with("gp_") || invalid_key.len() != 35);
     }
-}
 
-async fn validate_api_key(env: &Env, api_key: &str) -> Result<bool> {
-    if !api_key.starts_with("gp_") || api_key.len() != 35 {
-        return Ok(false);
+    #[test]
+    fn test_is_mock_operation() {
+        assert!(is_mock_operation("models/veo-3.0-fast-generate-001/operations/test_edit_0123abcd"));
+        assert!(!is_mock_operation("models/veo-3.0-fast-generate-001/operations/5k2x9q7w"));
     }
 
-    let db = env.d1("DB")?;
-    let statement = db.prepare("SELECT COUNT(*) as count FROM users WHERE api_key = ?");
-    let query = statement.bind_refs(&[D1Type::Text(api_key)])?;
+    #[test]
+    fn test_extract_images_collects_every_candidate() {
+        let response: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [
+                {"content": {"parts": [
+                    {"text": "Here you go"},
+                    {"inlineData": {"mimeType": "image/png", "data": "AAAA"}}
+                ]}},
+                {"content": {"parts": [{"inlineData": {"mimeType": "image/jpeg", "data": "BBBB"}}]}},
+                {"finishReason": "SAFETY"}
+            ]
+        }))
+        .unwrap();
+        let output = extract_images_from_response(&response).unwrap();
+        let data: Vec<&str> = output.images.iter().map(|image| image.data.as_str()).collect();
+        assert_eq!(data, vec!["AAAA", "BBBB"]);
+        assert_eq!(output.text.as_deref(), Some("Here you go"));
+
+        let refused: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{"content": {"parts": [{"text": "I can't draw that. "}, {"text": "Try a landscape?"}]}}]
+        }))
+        .unwrap();
+        let error = extract_images_from_response(&refused).unwrap_err();
+        assert_eq!(error.code, "no_image");
+        assert!(error.message.contains("I can't draw that."));
+        assert_eq!(error.details.unwrap()["text"], "I can't draw that.\nTry a landscape?");
+    }
 
-    #[derive(serde::Deserialize)]
-    struct CountResult {
-        count: i32,
+    #[test]
+    fn test_blocked_prompt_and_response_are_content_blocked() {
+        let prompt: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "promptFeedback": {
+                "blockReason": "SAFETY",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true},
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE"}
+                ]
+            }
+        }))
+        .unwrap();
+        let error = extract_images_from_response(&prompt).unwrap_err();
+        assert_eq!((error.status, error.code, error.retryable), (422, "content_blocked", false));
+        let details = error.details.unwrap();
+        assert_eq!(details["blocked"], "prompt");
+        assert_eq!(details["reason"], "SAFETY");
+        assert_eq!(details["safety_ratings"][0]["blocked"], true);
+        assert!(details["safety_ratings"][1].get("blocked").is_none());
+
+        let response: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{"finishReason": "IMAGE_SAFETY", "safetyRatings": []}]
+        }))
+        .unwrap();
+        let error = extract_images_from_response(&response).unwrap_err();
+        assert_eq!(error.code, "content_blocked");
+        assert_eq!(error.details.unwrap()["blocked"], "response");
+
+        let truncated: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{"finishReason": "MAX_TOKENS"}]
+        }))
+        .unwrap();
+        let error = extract_images_from_response(&truncated).unwrap_err();
+        assert_eq!(error.code, "no_image");
+        assert_eq!(error.details.unwrap()["finish_reason"], "MAX_TOKENS");
+    }
+
+    #[test]
+    fn test_image_settings_parse_validate_and_record() {
+        let body: GenerateRequest = serde_json::from_value(serde_json::json!({
+            "prompt": "a lighthouse",
+            "count": 2,
+            "aspect_ratio": "16:9",
+            "seed": 7,
+            "response_modalities": ["image", " Text "]
+        }))
+        .unwrap();
+        assert_eq!(body.count, Some(2));
+        let settings = body.settings.validate().unwrap();
+        assert_eq!(settings.response_modalities.as_deref().unwrap(), ["IMAGE", "TEXT"]);
+
+        let mut parameters = serde_json::json!({ "count": 2 });
+        settings.record(&mut parameters);
+        assert_eq!(
+            parameters,
+            serde_json::json!({
+                "count": 2,
+                "aspect_ratio": "16:9",
+                "seed": 7,
+                "response_modalities": ["IMAGE", "TEXT"]
+            })
+        );
+
+        let invalid = ImageSettings {
+            temperature: Some(3.0),
+            ..Default::default()
+        };
+        assert_eq!(invalid.validate().unwrap_err().code, "validation_failed");
+    }
+
+    fn edit_request(body: serde_json::Value) -> EditRequest {
+        serde_json::from_value(body).unwrap()
     }
 
-    let result: Option<CountResult> = query.first(None).await?;
-    Ok(result.map(|r| r.count > 0).unwrap_or(false))
+    #[test]
+    fn test_edit_inputs_keep_order_and_types() {
+        use crate::image_format::tests::{JPEG, PNG, WEBP};
+
+        // The declared type is ignored in favour of the data.
+        let single = edit_inputs(&edit_request(serde_json::json!({ "prompt": "p", "image": JPEG, "mime_type": "image/png" }))).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].mime_type, "image/jpeg");
+
+        let several = edit_inputs(&edit_request(serde_json::json!({
+            "prompt": "put the person from image 1 into the scene of image 2",
+            "images": [
+                {"data": WEBP, "mime_type": "image/jpeg"},
+                {"data": PNG}
+            ]
+        })))
+        .unwrap();
+        let inputs: Vec<(&str, &str)> = several.iter().map(|image| (image.data.as_str(), image.mime_type.as_str())).collect();
+        assert_eq!(inputs, vec![(WEBP, "image/webp"), (PNG, "image/png")]);
+    }
+
+    #[test]
+    fn test_edit_inputs_limits() {
+        let both = edit_request(serde_json::json!({ "prompt": "p", "image": "AAAA", "images": [{"data": "BBBB"}] }));
+        assert_eq!(edit_inputs(&both).unwrap_err().status, 400);
+        let neither = edit_request(serde_json::json!({ "prompt": "p" }));
+        assert_eq!(edit_inputs(&neither).unwrap_err().status, 400);
+
+        let too_many = edit_request(serde_json::json!({
+            "prompt": "p",
+            "images": vec![serde_json::json!({"data": "AAAA"}); MAX_EDIT_IMAGES + 1]
+        }));
+        assert_eq!(edit_inputs(&too_many).unwrap_err().status, 400);
+
+        let tiff = edit_request(serde_json::json!({ "prompt": "p", "images": [{"data": "SUkqAA=="}] }));
+        let error = edit_inputs(&tiff).unwrap_err();
+        assert_eq!(error.status, 415);
+        assert!(error.message.contains("image/tiff"));
+
+        // Two images that fit on their own but not together.
+        let half = "A".repeat((uploads::MAX_INPUT_BYTES / 3 * 4 / 2 + 4) as usize);
+        let large = edit_request(serde_json::json!({
+            "prompt": "p",
+            "images": [{"data": half}, {"data": half}]
+        }));
+        let error = edit_inputs(&large).unwrap_err();
+        assert_eq!((error.status, error.code), (413, "payload_too_large"));
+    }
+
+    #[test]
+    fn test_image_count_is_bounded() {
+        assert_eq!(image_count(None).unwrap(), 1);
+        assert_eq!(image_count(Some(MAX_IMAGE_COUNT)).unwrap(), MAX_IMAGE_COUNT);
+        assert!(image_count(Some(0)).is_err());
+        assert!(image_count(Some(MAX_IMAGE_COUNT + 1)).is_err());
+    }
+}
+
+/// Looks the key up by its hash in `api_keys`; revoked keys are rejected.
+async fn validate_api_key(env: &Env, api_key: &str) -> Result<Option<AuthUser>> {
+    api_keys::authenticate(env, api_key).await
+}
+
+async fn authorize(req: &Request, env: &Env) -> ApiResult<AuthUser> {
+    let auth_header = req
+        .headers()
+        .get("Authorization")?
+        .ok_or_else(|| ApiError::unauthorized("missing_api_key", "Missing API key"))?;
+
+    let token = auth_header.trim_start_matches("Bearer ");
+    let user = if token.starts_with(sessions::TOKEN_PREFIX) {
+        sessions::authenticate(env, token).await?
+    } else {
+        validate_api_key(env, token)
+            .await?
+            .ok_or_else(|| ApiError::unauthorized("invalid_api_key", "Invalid API key"))?
+    };
+
+    if user.disabled_at.is_some() {
+        return Err(account_disabled());
+    }
+    Ok(user)
 }
 
-async fn create_user(env: &Env, email: &str, password: &str) -> Result<String> {
+fn account_disabled() -> ApiError {
+    ApiError::new(403, "account_disabled", "This account has been disabled. Contact support if you think this is a mistake.")
+}
+
+async fn parse_json<T: serde::de::DeserializeOwned>(req: &mut Request) -> ApiResult<T> {
+    req.json::<T>()
+        .await
+        .map_err(|e| ApiError::bad_request(format!("Invalid request body: {}", e)))
+}
+
+/// Creates the account and returns its id.
+async fn create_user(env: &Env, email: &str, password: &str) -> ApiResult<i32> {
     let db = env.d1("DB")?;
-    let api_key = generate_api_key();
     let password_hash = hash_password(password)?;
 
-    let check_statement = db.prepare("SELECT 1 FROM users WHERE email = ?");
+    let check_statement = db.prepare("SELECT 1 FROM users WHERE lower(email) = ?");
     let check_query = check_statement.bind_refs(&[D1Type::Text(email)])?;
-    let existing_user: Option<i32> = check_query.first(None).await?;
+    let existing_user: Option<serde_json::Value> = check_query.first(None).await?;
 
     if existing_user.is_some() {
-        return Err(worker::Error::RustError("User already exists".into()));
+        return Err(ApiError::conflict("email_taken", "User already exists"));
     }
 
+    // `users.api_key` is NOT NULL UNIQUE from before keys had their own
+    // table; a fresh random hash satisfies it without being a usable key.
+    let placeholder = api_keys::hash(&api_keys::generate());
     let insert_statement = db.prepare(
-        "INSERT INTO users (email, password_hash, api_key, created_at) VALUES (?, ?, ?, datetime('now'))"
+        "INSERT INTO users (email, password_hash, api_key, created_at) VALUES (?, ?, ?, datetime('now')) RETURNING id"
     );
     let insert_query = insert_statement.bind_refs(&[
         D1Type::Text(email),
         D1Type::Text(&password_hash),
-        D1Type::Text(&api_key),
+        D1Type::Text(&placeholder),
     ])?;
 
-    insert_query.run().await?;
-    Ok(api_key)
+    let user_id = insert_query
+        .first::<i32>(Some("id"))
+        .await?
+        .ok_or_else(|| ApiError::internal("Failed to create user"))?;
+    Ok(user_id)
 }
 
-async fn authenticate_user(env: &Env, email: &str, password: &str) -> Result<String> {
+/// Checks the password and returns the user id.
+async fn authenticate_user(env: &Env, email: &str, password: &str) -> ApiResult<i32> {
     let db = env.d1("DB")?;
-    let statement = db.prepare("SELECT api_key, password_hash FROM users WHERE email = ?");
+    let statement = db.prepare("SELECT id, password_hash, disabled_at FROM users WHERE lower(email) = ?");
     let query = statement.bind_refs(&[D1Type::Text(email)])?;
 
     #[derive(serde::Deserialize)]
     struct UserCredentials {
-        api_key: String,
+        id: i32,
         password_hash: String,
+        disabled_at: Option<String>,
     }
 
     let result: Option<UserCredentials> = query.first(None).await?;
 
     match result {
-        Some(credentials) => {
-            if verify_password(password, &credentials.password_hash)? {
-                Ok(credentials.api_key)
-            } else {
-                Err(worker::Error::RustError("Invalid credentials".into()))
+        // Checked after the password so the answer does not reveal that a
+        // disabled account exists to someone who does not know it.
+        Some(credentials) if verify_password(password, &credentials.password_hash)? => match credentials.disabled_at {
+            Some(_) => Err(account_disabled()),
+            None => Ok(credentials.id),
+        },
+        _ => Err(ApiError::unauthorized("invalid_credentials", "Invalid credentials")),
+    }
+}
+
+/// Attaches CORS headers to both successful responses and error envelopes.
+fn respond(result: ApiResult<Response>) -> Result<Response> {
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            console_log!("Request failed: {}", e);
+            e.into_response()?
+        }
+    };
+
+    let headers = response.headers().clone();
+    for (name, value) in cors_headers().entries() {
+        headers.set(&name, &value)?;
+    }
+    Ok(response.with_headers(headers))
+}
+
+async fn handle_register(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let body: RegisterRequest = parse_json(&mut req).await?;
+    let email = validation::validate_registration(&body.email, &body.password)?;
+    let user_id = create_user(&ctx.env, &email, &body.password).await?;
+    if body.session {
+        return Ok(Response::from_json(&sessions::issue(&ctx.env, user_id).await?)?);
+    }
+
+    let (key, api_key) = api_keys::create(&ctx.env, user_id, "Default").await?;
+    Ok(Response::from_json(&AuthResponse {
+        success: true,
+        api_key,
+        key_id: key.id,
+    })?)
+}
+
+async fn handle_login(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let body: LoginRequest = parse_json(&mut req).await?;
+    let email = validation::normalize_email(&body.email);
+    let guard = LoginGuard::check(&ctx.env, &email, login_guard::client_ip(&req)).await?;
+    let user_id = match authenticate_user(&ctx.env, &email, &body.password).await {
+        Ok(user_id) => user_id,
+        Err(e) if e.code == "invalid_credentials" => return Err(guard.failed(&ctx.env, e).await),
+        Err(e) => return Err(e),
+    };
+    guard.succeeded(&ctx.env).await;
+    if body.session {
+        return Ok(Response::from_json(&sessions::issue(&ctx.env, user_id).await?)?);
+    }
+
+    // A new key every time: existing keys are never handed back, so knowing
+    // the password does not reveal them. Nothing is revoked unless asked.
+    let key_name = body.key_name.as_deref().unwrap_or("Login");
+    let (key, api_key) = if body.replace {
+        api_keys::replace(&ctx.env, user_id, key_name).await?
+    } else {
+        api_keys::create(&ctx.env, user_id, key_name).await?
+    };
+    let key_id = key.id;
+
+    Ok(Response::from_json(&AuthResponse {
+        success: true,
+        api_key,
+        key_id,
+    })?)
+}
+
+async fn handle_generate(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: GenerateRequest = parse_json(&mut req).await?;
+    let count = image_count(body.count)?;
+    let settings = body.settings.validate()?;
+    let model = models::resolve(&ctx.env, GenerationKind::Generate, body.model.as_deref())?;
+    let limit = rate_limit::charge(&ctx.env, &user, LimitKind::Image, count).await?;
+    let gemini = GeminiClient::from_env(&ctx.env)?;
+
+    let started = Date::now().as_millis();
+    let result = match gemini.generate_image(&model, &body.prompt, &settings.options(count)).await {
+        // Models may return more images than asked for; never more than
+        // were paid for.
+        Ok(gemini_response) => extract_images_from_response(&gemini_response).map(|mut output| {
+            output.images.truncate(count as usize);
+            output
+        }),
+        Err(e) => Err(e.into()),
+    };
+
+    let (status, error_code) = usage::outcome(&result);
+    let mut parameters = serde_json::json!({
+        "count": count,
+        "images": result.as_ref().map(|output| output.images.len()).unwrap_or(0),
+    });
+    settings.record(&mut parameters);
+    let generation_id = usage::record(&ctx.env, &Generation {
+        user_id: user.id,
+        kind: GenerationKind::Generate,
+        model: &model,
+        prompt: &body.prompt,
+        parameters,
+        status,
+        error_code,
+        operation_name: None,
+        latency_ms: Date::now().as_millis() - started,
+        output_bytes: result
+            .as_ref()
+            .ok()
+            .map(|output| output.images.iter().map(|image| base64_decoded_len(&image.data)).sum()),
+    }).await;
+    let output = result?;
+
+    let response = image_response(&req, &ctx.env, user.id, generation_id.as_deref(), output, body.inline).await?;
+    limit.apply(response.headers())?;
+    Ok(response)
+}
+
+async fn handle_edit(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: EditRequest = uploads::read(&mut req, &EDIT_FORM).await?;
+    let inputs = edit_inputs(&body)?;
+    let settings = body.settings.validate()?;
+    let model = models::resolve(&ctx.env, GenerationKind::Edit, body.model.as_deref())?;
+    let limit = rate_limit::enforce(&ctx.env, &user, LimitKind::Image).await?;
+    let gemini = GeminiClient::from_env(&ctx.env)?;
+
+    let started = Date::now().as_millis();
+    let result = match gemini.edit_image(&model, &inputs, &body.prompt, &settings.options(1)).await {
+        Ok(gemini_response) => extract_images_from_response(&gemini_response).map(|mut output| {
+            output.images.truncate(1);
+            output
+        }),
+        Err(e) => Err(e.into()),
+    };
+
+    let (status, error_code) = usage::outcome(&result);
+    let input_sizes: Vec<u64> = inputs.iter().map(|image| base64_decoded_len(&image.data)).collect();
+    let mut parameters = serde_json::json!({
+        "mime_types": inputs.iter().map(|image| image.mime_type.as_str()).collect::<Vec<_>>(),
+        "input_sizes": input_sizes,
+        "input_bytes": input_sizes.iter().sum::<u64>(),
+    });
+    settings.record(&mut parameters);
+    let generation_id = usage::record(&ctx.env, &Generation {
+        user_id: user.id,
+        kind: GenerationKind::Edit,
+        model: &model,
+        prompt: &body.prompt,
+        parameters,
+        status,
+        error_code,
+        operation_name: None,
+        latency_ms: Date::now().as_millis() - started,
+        output_bytes: result.as_ref().ok().map(|output| base64_decoded_len(&output.images[0].data)),
+    }).await;
+    let output = result?;
+
+    let response = image_response(&req, &ctx.env, user.id, generation_id.as_deref(), output, body.inline).await?;
+    limit.apply(response.headers())?;
+    Ok(response)
+}
+
+/// Stores generated images as assets and builds the `/generate` and
+/// `/edit` response, keeping the base64 copies only when the caller asked.
+async fn image_response(req: &Request, env: &Env, user_id: i32, generation_id: Option<&str>, output: ModelOutput, inline: bool) -> ApiResult<Response> {
+    let stored = store_images(req, env, user_id, generation_id, output.images, inline).await?;
+    Ok(Response::from_json(&ImageResponse::new(stored, output.text))?)
+}
+
+async fn store_images(req: &Request, env: &Env, user_id: i32, generation_id: Option<&str>, images: Vec<InlineData>, inline: bool) -> ApiResult<Vec<StoredImage>> {
+    let mut stored = Vec::with_capacity(images.len());
+    for image in images {
+        let bytes = general_purpose::STANDARD
+            .decode(&image.data)
+            .map_err(|e| ApiError::upstream("upstream_invalid_response", format!("Invalid image data: {}", e)).with_retryable(false))?;
+        let asset = assets::store(env, user_id, generation_id, &image.mime_type, bytes).await?;
+        stored.push(StoredImage {
+            asset_url: asset.url(req, env)?,
+            asset_id: asset.id,
+            mime_type: asset.content_type,
+            image: inline.then_some(image.data),
+        });
+    }
+    Ok(stored)
+}
+
+fn video_parameters(options: &VideoOptions<'_>) -> serde_json::Value {
+    serde_json::json!({
+        "negative_prompt": options.negative_prompt,
+        "aspect_ratio": options.aspect_ratio,
+        "resolution": options.resolution,
+    })
+}
+
+/// Records a video request in the ledger, as pending when Veo accepted it or
+/// as failed straight away when it did not, and opens a job for the caller
+/// to poll. Returns the job id.
+#[allow(clippy::too_many_arguments)]
+async fn start_video_job(env: &Env, user: &AuthUser, kind: GenerationKind, model: &str, prompt: &str, parameters: serde_json::Value, callback_url: Option<&str>, origin: &str, result: ApiResult<String>, started: u64) -> ApiResult<String> {
+    let (status, error_code) = match &result {
+        Ok(_) => (GenerationStatus::Pending, None),
+        Err(e) => (GenerationStatus::Failed, Some(e.code)),
+    };
+
+    let generation_id = usage::record(env, &Generation {
+        user_id: user.id,
+        kind,
+        model,
+        prompt,
+        parameters: parameters.clone(),
+        status,
+        error_code,
+        operation_name: result.as_ref().ok().map(String::as_str),
+        latency_ms: Date::now().as_millis() - started,
+        output_bytes: None,
+    }).await;
+
+    let operation_name = result?;
+    video_jobs::create(env, &NewVideoJob {
+        user_id: user.id,
+        kind,
+        operation_name: &operation_name,
+        prompt,
+        parameters: &parameters,
+        generation_id: generation_id.as_deref(),
+        callback_url,
+        origin,
+    }).await
+}
+
+async fn handle_generate_video(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: GenerateVideoRequest = parse_json(&mut req).await?;
+    let model = models::resolve(&ctx.env, GenerationKind::GenerateVideo, body.model.as_deref())?;
+    webhooks::prepare_callback(&ctx.env, user.id, body.callback_url.as_deref()).await?;
+    let limit = rate_limit::enforce(&ctx.env, &user, LimitKind::Video).await?;
+    let gemini = GeminiClient::from_env(&ctx.env)?;
+
+    let options = VideoOptions {
+        model: &model,
+        negative_prompt: body.negative_prompt.as_deref(),
+        aspect_ratio: body.aspect_ratio.as_deref(),
+        resolution: body.resolution.as_deref(),
+    };
+
+    let started = Date::now().as_millis();
+    let result: ApiResult<String> = if is_test_mode(&req, &ctx.env) {
+        console_log!("TEST MODE: Using mock Veo generate");
+        mock_veo_generate(&body.prompt, &options).await.map_err(Into::into)
+    } else {
+        gemini.generate_video(&body.prompt, &options).await.map_err(Into::into)
+    };
+
+    let origin = req.url()?.origin().ascii_serialization();
+    let job_id = start_video_job(&ctx.env, &user, GenerationKind::GenerateVideo, &model, &body.prompt, video_parameters(&options), body.callback_url.as_deref(), &origin, result, started).await?;
+
+    let response = Response::from_json(&VideoOperationResponse {
+        success: true,
+        job_id,
+    })?;
+    limit.apply(response.headers())?;
+    Ok(response)
+}
+
+async fn handle_edit_video(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let body: EditVideoRequest = uploads::read(&mut req, &EDIT_VIDEO_FORM).await?;
+    uploads::check_input_size(base64_decoded_len(&body.image))?;
+    let mime_type = image_format::detect("Image", &body.image, &image_format::VIDEO_INPUT_TYPES)?;
+    let model = models::resolve(&ctx.env, GenerationKind::EditVideo, body.model.as_deref())?;
+    webhooks::prepare_callback(&ctx.env, user.id, body.callback_url.as_deref()).await?;
+    let limit = rate_limit::enforce(&ctx.env, &user, LimitKind::Video).await?;
+    let gemini = GeminiClient::from_env(&ctx.env)?;
+
+    let options = VideoOptions {
+        model: &model,
+        negative_prompt: body.negative_prompt.as_deref(),
+        aspect_ratio: body.aspect_ratio.as_deref(),
+        resolution: body.resolution.as_deref(),
+    };
+
+    let started = Date::now().as_millis();
+    let result: ApiResult<String> = if is_test_mode(&req, &ctx.env) {
+        console_log!("TEST MODE: Using mock Veo edit");
+        mock_veo_edit(&body.image, mime_type, &body.prompt, &options).await.map_err(Into::into)
+    } else {
+        gemini.edit_video(&body.image, mime_type, &body.prompt, &options).await.map_err(Into::into)
+    };
+
+    let origin = req.url()?.origin().ascii_serialization();
+    let mut parameters = video_parameters(&options);
+    parameters["mime_type"] = serde_json::json!(mime_type);
+    let job_id = start_video_job(&ctx.env, &user, GenerationKind::EditVideo, &model, &body.prompt, parameters, body.callback_url.as_deref(), &origin, result, started).await?;
+
+    let response = Response::from_json(&VideoOperationResponse {
+        success: true,
+        job_id,
+    })?;
+    limit.apply(response.headers())?;
+    Ok(response)
+}
+
+/// Builds the finished `/video_status` response. The MP4 is only read back
+/// and base64-encoded when the caller opted into `?inline=true`.
+async fn video_response(req: &Request, env: &Env, user_id: i32, job: &VideoJob, asset: Asset, inline: bool) -> ApiResult<Response> {
+    let mut body = serde_json::json!({
+        "success": true,
+        "job_id": job.id,
+        "done": true,
+        "asset_id": asset.id,
+        "video_url": asset.url(req, env)?,
+        "mime_type": asset.content_type,
+    });
+    if inline {
+        let bytes = assets::read(env, user_id, &asset).await?;
+        body["video"] = serde_json::json!(format!("data:{};base64,{}", asset.content_type, general_purpose::STANDARD.encode(&bytes)));
+    }
+
+    Ok(Response::from_json(&body)?)
+}
+
+/// `GET /video_status/{job_id}` — polls a video job owned by the caller and
+/// stores the video as an asset once Veo has finished it.
+async fn handle_video_status(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let limit = rate_limit::enforce(&ctx.env, &user, LimitKind::Status).await?;
+
+    let job_id = ctx.param("job_id").ok_or_else(|| ApiError::bad_request("Missing job id"))?;
+    let inline = req.url()?.query_pairs().any(|(key, value)| key == "inline" && value == "true");
+    let job = video_jobs::find(&ctx.env, user.id, job_id).await?;
+
+    let response = match poll_video_job(&ctx.env, &job).await? {
+        Some(asset) => video_response(&req, &ctx.env, user.id, &job, asset, inline).await?,
+        None => Response::from_json(&serde_json::json!({
+            "success": true,
+            "job_id": job.id,
+            "done": false,
+        }))?,
+    };
+    limit.apply(response.headers())?;
+    Ok(response)
+}
+
+/// Returns the stored video once the job has succeeded, `None` while Veo
+/// is still working, and the recorded error for jobs that failed.
+async fn poll_video_job(env: &Env, job: &VideoJob) -> ApiResult<Option<Asset>> {
+    if !job.is_pending() {
+        return match &job.asset_id {
+            Some(asset_id) => Ok(Some(assets::find(env, job.user_id, asset_id).await?)),
+            None => Err(ApiError::upstream("video_generation_failed", "Video generation failed")
+                .with_retryable(false)
+                .with_details(serde_json::json!({ "reason": job.error_code }))),
+        };
+    }
+
+    advance_video_job(env, job).await
+}
+
+/// Polls Veo once for a pending job and, when the operation is done, stores
+/// the video and finishes the job. Shared by `/video_status` and the
+/// scheduled handler, so whichever gets there first collects the result.
+async fn advance_video_job(env: &Env, job: &VideoJob) -> ApiResult<Option<Asset>> {
+    let gemini = GeminiClient::from_env(env)?;
+    let test_mode = is_mock_operation(&job.operation_name);
+
+    let status = if test_mode {
+        console_log!("TEST MODE: Using mock video status polling");
+        mock_poll_video_operation(&job.operation_name).await?
+    } else {
+        gemini.poll_video_operation(&job.operation_name).await?
+    };
+
+    if !status.done.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    if let Some(error) = &status.error {
+        let message = error.message.clone().unwrap_or_else(|| "Video generation failed".to_string());
+        console_log!("Video operation {} failed: {}", job.operation_name, message);
+        fail_video_job(env, job, "video_generation_failed").await?;
+        return Err(ApiError::upstream("video_generation_failed", message)
+            .with_retryable(false)
+            .with_details(serde_json::json!({ "reason": "video_generation_failed", "code": error.code })));
+    }
+
+    let video_uri = extract_video_uri(&status)
+        .map_err(|e| ApiError::upstream("upstream_invalid_response", e.to_string()).with_retryable(false))?;
+
+    // A cron run and a poll can both get here; only the claimant downloads.
+    // The other reports the job as still running until it is finished.
+    if !video_jobs::claim(env, job).await? {
+        return Ok(None);
+    }
+
+    let download = if test_mode {
+        console_log!("TEST MODE: Using mock video download");
+        mock_download_video(&video_uri).await.map_err(UpstreamError::from)
+    } else {
+        gemini.download(&video_uri).await
+    };
+
+    let video_bytes = match download {
+        Ok(video_bytes) => video_bytes,
+        Err(e) => {
+            console_log!("Failed to download video: {}", e);
+            fail_video_job(env, job, "video_download_failed").await?;
+            return Err(ApiError::upstream("video_download_failed", "Failed to download video"));
+        }
+    };
+
+    let output_bytes = video_bytes.len() as u64;
+    let asset = assets::store(env, job.user_id, job.generation_id.as_deref(), "video/mp4", video_bytes).await?;
+    if !video_jobs::finish(env, job, GenerationStatus::Succeeded, None, Some(&asset.id)).await? {
+        // Our claim went stale and someone else finished the job first.
+        assets::delete(env, job.user_id, std::slice::from_ref(&asset.id)).await?;
+        return Ok(None);
+    }
+    webhooks::notify_job_finished(env, job, GenerationStatus::Succeeded, None, Some(&asset)).await;
+    usage::complete_video(env, job.user_id, &job.operation_name, GenerationStatus::Succeeded, None, Some(output_bytes)).await;
+
+    Ok(Some(asset))
+}
+
+/// Marks a job, its ledger row and its callback as failed.
+async fn fail_video_job(env: &Env, job: &VideoJob, error_code: &str) -> Result<()> {
+    if video_jobs::finish(env, job, GenerationStatus::Failed, Some(error_code), None).await? {
+        webhooks::notify_job_finished(env, job, GenerationStatus::Failed, Some(error_code), None).await;
+        usage::complete_video(env, job.user_id, &job.operation_name, GenerationStatus::Failed, Some(error_code), None).await;
+    }
+    Ok(())
+}
+
+/// How many pending jobs one scheduled run polls.
+const VIDEO_JOB_BATCH: i32 = 20;
+
+/// Jobs still pending after this long are given up on.
+const VIDEO_JOB_MAX_AGE_HOURS: u32 = 24;
+
+/// Collects finished videos nobody came back to poll for, so results are
+/// stored (and callbacks sent) even when the client has gone away.
+async fn advance_pending_video_jobs(env: &Env) {
+    let result = async {
+        for job in video_jobs::stale(env, VIDEO_JOB_MAX_AGE_HOURS, VIDEO_JOB_BATCH).await? {
+            console_log!("Expiring video job {}", job.id);
+            fail_video_job(env, &job, "video_job_expired").await?;
+        }
+
+        for job in video_jobs::due(env, VIDEO_JOB_BATCH).await? {
+            match advance_video_job(env, &job).await {
+                Ok(Some(_)) => console_log!("Video job {} finished", job.id),
+                Ok(None) => video_jobs::touch(env, &job).await?,
+                Err(e) => {
+                    console_log!("Failed to advance video job {}: {}", job.id, e.message);
+                    video_jobs::touch(env, &job).await?;
+                }
             }
         }
-        None => Err(worker::Error::RustError("Invalid credentials".into())),
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to advance video jobs: {}", e);
     }
 }
 
 #[event(fetch)]
-async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
+async fn main(req: Request, env: Env, ctx: Context) -> Result<Response> {
     console_error_panic_hook::set_once();
-    
-    Router::new()
+
+    // Handlers reach the `Context` as `ctx.data`, for work that should
+    // finish after the response is sent.
+    Router::with_data(ctx)
         .get("/", |_, _| {
             let headers = cors_headers();
             headers.set("Content-Type", "text/html").unwrap();
@@ -720,8 +1384,14 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
             Response::ok(include_str!("../../../web/public/app.js"))
                 .map(|r| r.with_headers(headers))
         })
-        .get("/health", |_, _| {
-            Response::ok("{\"status\":\"healthy\"}")
+        .get("/health", |_, ctx| {
+            // Without SESSION_SECRET asset links come out unsigned, which
+            // breaks <img> tags and webhook receivers without failing a request.
+            let signed = assets::signing_configured(&ctx.env);
+            Response::from_json(&serde_json::json!({
+                "status": if signed { "healthy" } else { "degraded" },
+                "checks": { "asset_url_signing": signed },
+            }))
         })
         .get("/openapi", |_, _| {
             Response::ok(openapi_spec().to_string())
@@ -733,438 +1403,123 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
             Response::ok(swagger_ui_html())
                 .map(|r| r.with_headers(headers))
         })
-        .post_async("/register", |mut req, ctx| async move {
-            let body = match req.json::<RegisterRequest>().await {
-                Ok(body) => body,
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"Invalid request body\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            match create_user(&ctx.env, &body.email, &body.password).await {
-                Ok(api_key) => {
-                    let response = AuthResponse {
-                        success: true,
-                        api_key: Some(api_key),
-                        error: None,
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-                Err(e) => {
-                    let response = AuthResponse {
-                        success: false,
-                        api_key: None,
-                        error: Some(e.to_string()),
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-            }
+        .post_async("/register", |req, ctx| async move {
+            respond(handle_register(req, ctx).await)
         })
-        .post_async("/login", |mut req, ctx| async move {
-            let body = match req.json::<LoginRequest>().await {
-                Ok(body) => body,
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"Invalid request body\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            match authenticate_user(&ctx.env, &body.email, &body.password).await {
-                Ok(api_key) => {
-                    let response = AuthResponse {
-                        success: true,
-                        api_key: Some(api_key),
-                        error: None,
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-                Err(e) => {
-                    let response = AuthResponse {
-                        success: false,
-                        api_key: None,
-                        error: Some(e.to_string()),
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-            }
+        .post_async("/login", |req, ctx| async move {
+            respond(handle_login(req, ctx).await)
         })
-        .post_async("/generate", |mut req, ctx| async move {
-            let auth_header = match req.headers().get("Authorization") {
-                Ok(Some(header)) => header,
-                _ => {
-                    return Response::ok("{\"success\":false,\"error\":\"Missing API key\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let api_key = auth_header.trim_start_matches("Bearer ");
-            if !validate_api_key(&ctx.env, api_key).await.unwrap_or(false) {
-                return Response::ok("{\"success\":false,\"error\":\"Invalid API key\"}")
-                    .map(|r| r.with_headers(cors_headers()));
-            }
-
-            let body = match req.json::<GenerateRequest>().await {
-                Ok(body) => body,
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"Invalid request body\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let gemini_api_key = match ctx.env.secret("GEMINI_API_KEY") {
-                Ok(key) => key.to_string(),
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"API key not configured\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            match call_gemini_generate(&body.prompt, &gemini_api_key).await {
-                Ok(gemini_response) => {
-                    match extract_image_from_response(&gemini_response) {
-                        Ok(image_data) => {
-                            let response_json = format!(r#"{{"success":true,"image":"{}"}}"#, image_data);
-                            Response::ok(response_json).map(|r| r.with_headers(cors_headers()))
-                        }
-                        Err(e) => {
-                            console_log!("Failed to extract image: {}", e);
-                            Response::ok(format!(r#"{{"success":false,"error":"{}"}}"#, e))
-                                .map(|r| r.with_headers(cors_headers()))
-                        }
-                    }
-                }
-                Err(e) => {
-                    console_log!("Failed to call Gemini API: {}", e);
-                    Response::ok(format!(r#"{{"success":false,"error":"{}"}}"#, e))
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-            }
+        .post_async("/token/refresh", |req, ctx| async move {
+            respond(sessions::handle_refresh(req, ctx).await)
         })
-        .post_async("/edit", |mut req, ctx| async move {
-            let auth_header = match req.headers().get("Authorization") {
-                Ok(Some(header)) => header,
-                _ => {
-                    return Response::ok("{\"success\":false,\"error\":\"Missing API key\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let api_key = auth_header.trim_start_matches("Bearer ");
-            if !validate_api_key(&ctx.env, api_key).await.unwrap_or(false) {
-                return Response::ok("{\"success\":false,\"error\":\"Invalid API key\"}")
-                    .map(|r| r.with_headers(cors_headers()));
-            }
-
-            let body = match req.json::<EditRequest>().await {
-                Ok(body) => body,
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"Invalid request body\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let gemini_api_key = match ctx.env.secret("GEMINI_API_KEY") {
-                Ok(key) => key.to_string(),
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"API key not configured\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            match call_gemini_edit(&body.image, &body.prompt, &gemini_api_key).await {
-                Ok(gemini_response) => {
-                    match extract_image_from_response(&gemini_response) {
-                        Ok(image_data) => {
-                            let response_json = format!(r#"{{"success":true,"image":"{}"}}"#, image_data);
-                            Response::ok(response_json).map(|r| r.with_headers(cors_headers()))
-                        }
-                        Err(e) => {
-                            console_log!("Failed to extract image: {}", e);
-                            Response::ok(format!(r#"{{"success":false,"error":"{}"}}"#, e))
-                                .map(|r| r.with_headers(cors_headers()))
-                        }
-                    }
-                }
-                Err(e) => {
-                    console_log!("Failed to call Gemini API: {}", e);
-                    Response::ok(format!(r#"{{"success":false,"error":"{}"}}"#, e))
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-            }
+        .post_async("/logout", |req, ctx| async move {
+            respond(sessions::handle_logout(req, ctx).await)
         })
-        .post_async("/generate_video", |mut req, ctx| async move {
-            let auth_header = match req.headers().get("Authorization") {
-                Ok(Some(header)) => header,
-                _ => {
-                    return Response::ok("{\"success\":false,\"error\":\"Missing API key\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let api_key = auth_header.trim_start_matches("Bearer ");
-            if !validate_api_key(&ctx.env, api_key).await.unwrap_or(false) {
-                return Response::ok("{\"success\":false,\"error\":\"Invalid API key\"}")
-                    .map(|r| r.with_headers(cors_headers()));
-            }
-
-            let body = match req.json::<GenerateVideoRequest>().await {
-                Ok(body) => body,
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"Invalid request body\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let gemini_api_key = match ctx.env.secret("GEMINI_API_KEY") {
-                Ok(key) => key.to_string(),
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"API key not configured\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let operation_result = if is_test_mode(&req, &ctx.env) {
-                console_log!("TEST MODE: Using mock Veo generate");
-                mock_veo_generate(
-                    &body.prompt,
-                    body.negative_prompt.as_deref(),
-                    body.aspect_ratio.as_deref(),
-                    body.resolution.as_deref()
-                ).await
-            } else {
-                call_veo_generate(
-                    &body.prompt,
-                    body.negative_prompt.as_deref(),
-                    body.aspect_ratio.as_deref(),
-                    body.resolution.as_deref(),
-                    &gemini_api_key
-                ).await
-            };
-
-            match operation_result {
-                Ok(operation_name) => {
-                    let response = VideoOperationResponse {
-                        success: true,
-                        operation_name: Some(operation_name),
-                        error: None,
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-                Err(e) => {
-                    console_log!("Failed to start video generation: {}", e);
-                    let response = VideoOperationResponse {
-                        success: false,
-                        operation_name: None,
-                        error: Some(e.to_string()),
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-            }
+        .post_async("/password/forgot", |req, ctx| async move {
+            respond(password_reset::handle_forgot(req, ctx).await)
         })
-        .post_async("/edit_video", |mut req, ctx| async move {
-            let auth_header = match req.headers().get("Authorization") {
-                Ok(Some(header)) => header,
-                _ => {
-                    return Response::ok("{\"success\":false,\"error\":\"Missing API key\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let api_key = auth_header.trim_start_matches("Bearer ");
-            if !validate_api_key(&ctx.env, api_key).await.unwrap_or(false) {
-                return Response::ok("{\"success\":false,\"error\":\"Invalid API key\"}")
-                    .map(|r| r.with_headers(cors_headers()));
-            }
-
-            let body = match req.json::<EditVideoRequest>().await {
-                Ok(body) => body,
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"Invalid request body\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let gemini_api_key = match ctx.env.secret("GEMINI_API_KEY") {
-                Ok(key) => key.to_string(),
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"API key not configured\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let operation_result = if is_test_mode(&req, &ctx.env) {
-                console_log!("TEST MODE: Using mock Veo edit");
-                mock_veo_edit(
-                    &body.image,
-                    &body.mime_type,
-                    &body.prompt,
-                    body.negative_prompt.as_deref(),
-                    body.aspect_ratio.as_deref(),
-                    body.resolution.as_deref()
-                ).await
-            } else {
-                call_veo_edit(
-                    &body.image,
-                    &body.mime_type,
-                    &body.prompt,
-                    body.negative_prompt.as_deref(),
-                    body.aspect_ratio.as_deref(),
-                    body.resolution.as_deref(),
-                    &gemini_api_key
-                ).await
-            };
-
-            match operation_result {
-                Ok(operation_name) => {
-                    let response = VideoOperationResponse {
-                        success: true,
-                        operation_name: Some(operation_name),
-                        error: None,
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-                Err(e) => {
-                    let response = VideoOperationResponse {
-                        success: false,
-                        operation_name: None,
-                        error: Some(e.to_string()),
-                    };
-                    Response::ok(serde_json::to_string(&response).unwrap())
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-            }
+        .post_async("/password/reset", |req, ctx| async move {
+            respond(password_reset::handle_reset(req, ctx).await)
         })
-        .get_async("/video_status/*operation", |req, ctx| async move {
-            let auth_header = match req.headers().get("Authorization") {
-                Ok(Some(header)) => header,
-                _ => {
-                    return Response::ok("{\"success\":false,\"error\":\"Missing API key\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let api_key = auth_header.trim_start_matches("Bearer ");
-            if !validate_api_key(&ctx.env, api_key).await.unwrap_or(false) {
-                return Response::ok("{\"success\":false,\"error\":\"Invalid API key\"}")
-                    .map(|r| r.with_headers(cors_headers()));
-            }
-
-            let operation_name = match ctx.param("operation") {
-                Some(name) => {
-                    let clean_name = name.trim_start_matches('/');
-                    clean_name.to_string()
-                },
-                None => {
-                    return Response::ok("{\"success\":false,\"error\":\"Missing operation name\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let gemini_api_key = match ctx.env.secret("GEMINI_API_KEY") {
-                Ok(key) => key.to_string(),
-                Err(_) => {
-                    return Response::ok("{\"success\":false,\"error\":\"API key not configured\"}")
-                        .map(|r| r.with_headers(cors_headers()));
-                }
-            };
-
-            let status_result = if is_test_mode(&req, &ctx.env) {
-                console_log!("TEST MODE: Using mock video status polling");
-                mock_poll_video_operation(&operation_name).await
-            } else {
-                poll_video_operation(&operation_name, &gemini_api_key).await
-            };
-
-            match status_result {
-                Ok(status) => {
-                    if status.done.unwrap_or(false) && status.response.is_some() {
-                        match extract_video_uri(&status) {
-                            Ok(video_uri) => {
-                                if is_test_mode(&req, &ctx.env) {
-                                    console_log!("TEST MODE: Using mock video download");
-                                    match mock_download_video(&video_uri).await {
-                                        Ok(video_data) => {
-                                            let response_json = format!(r#"{{"success":true,"done":true,"video":"{}"}}"#, video_data);
-                                            Response::ok(response_json).map(|r| r.with_headers(cors_headers()))
-                                        }
-                                        Err(e) => {
-                                            Response::ok(format!(r#"{{"success":false,"done":true,"error":"{}"}}"#, e))
-                                                .map(|r| r.with_headers(cors_headers()))
-                                        }
-                                    }
-                                } else {
-                                    let headers = Headers::new();
-                                    let google_api_key = gemini_api_key.strip_prefix("gp_").unwrap_or(&gemini_api_key);
-                                    headers.set("x-goog-api-key", google_api_key).unwrap();
-
-                                    let request = Request::new_with_init(
-                                        &video_uri,
-                                        &RequestInit::new()
-                                            .with_method(Method::Get)
-                                            .with_headers(headers),
-                                    );
-
-                                    match request {
-                                        Ok(req) => {
-                                            match Fetch::Request(req).send().await {
-                                                Ok(mut response) => {
-                                                    if response.status_code() >= 200 && response.status_code() < 300 {
-                                                        match response.bytes().await {
-                                                            Ok(video_bytes) => {
-                                                                let video_base64 = general_purpose::STANDARD.encode(&video_bytes);
-                                                                let response_json = format!(r#"{{"success":true,"done":true,"video":"data:video/mp4;base64,{}"}}"#, video_base64);
-                                                                Response::ok(response_json).map(|r| r.with_headers(cors_headers()))
-                                                            }
-                                                        Err(_e) => {
-                                                            Response::ok(format!(r#"{{"success":false,"done":true,"error":"Failed to download video"}}"#))
-                                                                .map(|r| r.with_headers(cors_headers()))
-                                                        }
-                                                        }
-                                                    } else {
-                                                        Response::ok(format!(r#"{{"success":false,"done":true,"error":"Failed to download video from Google"}}"#))
-                                                            .map(|r| r.with_headers(cors_headers()))
-                                                    }
-                                                }
-                                            Err(_e) => {
-                                                Response::ok(format!(r#"{{"success":false,"done":true,"error":"Failed to download video"}}"#))
-                                                    .map(|r| r.with_headers(cors_headers()))
-                                            }
-                                            }
-                                        }
-                                    Err(_e) => {
-                                        Response::ok(format!(r#"{{"success":false,"done":true,"error":"Failed to download video"}}"#))
-                                            .map(|r| r.with_headers(cors_headers()))
-                                    }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                Response::ok(format!(r#"{{"success":false,"done":true,"error":"{}"}}"#, e))
-                                    .map(|r| r.with_headers(cors_headers()))
-                            }
-                        }
-                    } else {
-                        Response::ok(r#"{"success":true,"done":false}"#)
-                            .map(|r| r.with_headers(cors_headers()))
-                    }
-                }
-                Err(e) => {
-                    Response::ok(format!(r#"{{"success":false,"error":"{}"}}"#, e))
-                        .map(|r| r.with_headers(cors_headers()))
-                }
-            }
+        .post_async("/generate", |req, ctx| async move {
+            respond(handle_generate(req, ctx).await)
+        })
+        .post_async("/edit", |req, ctx| async move {
+            respond(handle_edit(req, ctx).await)
+        })
+        .post_async("/sessions", |req, ctx| async move {
+            respond(edit_sessions::handle_create(req, ctx).await)
+        })
+        .post_async("/sessions/:id/turns", |req, ctx| async move {
+            respond(edit_sessions::handle_turn(req, ctx).await)
+        })
+        .post_async("/generate_video", |req, ctx| async move {
+            respond(handle_generate_video(req, ctx).await)
+        })
+        .post_async("/edit_video", |req, ctx| async move {
+            respond(handle_edit_video(req, ctx).await)
+        })
+        .get_async("/video_status/:job_id", |req, ctx| async move {
+            respond(handle_video_status(req, ctx).await)
+        })
+        .get_async("/assets/:id", |req, ctx| async move {
+            respond(assets::handle_get(req, ctx).await)
+        })
+        .post_async("/keys", |req, ctx| async move {
+            respond(api_keys::handle_create(req, ctx).await)
+        })
+        .get_async("/keys", |req, ctx| async move {
+            respond(api_keys::handle_list(req, ctx).await)
+        })
+        .delete_async("/keys/:id", |req, ctx| async move {
+            respond(api_keys::handle_revoke(req, ctx).await)
+        })
+        .get_async("/webhook_secret", |req, ctx| async move {
+            respond(webhooks::handle_get_secret(req, ctx).await)
+        })
+        .post_async("/webhook_secret", |req, ctx| async move {
+            respond(webhooks::handle_rotate_secret(req, ctx).await)
+        })
+        .get_async("/models", |req, ctx| async move {
+            respond(models::handle_list(req, ctx).await)
+        })
+        .get_async("/me", |req, ctx| async move {
+            respond(account::handle_get(req, ctx).await)
+        })
+        .patch_async("/me", |req, ctx| async move {
+            respond(account::handle_update(req, ctx).await)
+        })
+        .delete_async("/me", |req, ctx| async move {
+            respond(account::handle_delete(req, ctx).await)
+        })
+        .get_async("/admin/users", |req, ctx| async move {
+            respond(admin::handle_list_users(req, ctx).await)
+        })
+        .get_async("/admin/users/:id", |req, ctx| async move {
+            respond(admin::handle_get_user(req, ctx).await)
+        })
+        .post_async("/admin/users/:id/disable", |req, ctx| async move {
+            respond(admin::handle_disable_user(req, ctx).await)
+        })
+        .post_async("/admin/users/:id/enable", |req, ctx| async move {
+            respond(admin::handle_enable_user(req, ctx).await)
+        })
+        .post_async("/admin/users/:id/reset_quota", |req, ctx| async move {
+            respond(admin::handle_reset_quota(req, ctx).await)
+        })
+        .delete_async("/admin/users/:id/keys", |req, ctx| async move {
+            respond(admin::handle_revoke_keys(req, ctx).await)
+        })
+        .delete_async("/admin/users/:id/keys/:key_id", |req, ctx| async move {
+            respond(admin::handle_revoke_keys(req, ctx).await)
+        })
+        .get_async("/admin/users/:id/usage", |req, ctx| async move {
+            respond(admin::handle_user_usage(req, ctx).await)
+        })
+        .get_async("/usage", |req, ctx| async move {
+            respond(usage::handle_usage(req, ctx).await)
+        })
+        .get_async("/history", |req, ctx| async move {
+            respond(history::handle_list(req, ctx).await)
+        })
+        .get_async("/history/:id", |req, ctx| async move {
+            respond(history::handle_get(req, ctx).await)
         })
         .options("/*catchall", |_, _| {
             Response::ok("").map(|r| r.with_headers(cors_headers()))
         })
         .run(req, env)
         .await
-}
\ No newline at end of file
+}
+
+/// Runs on the cron trigger in `wrangler.toml`.
+#[event(scheduled)]
+async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    console_error_panic_hook::set_once();
+    advance_pending_video_jobs(&env).await;
+    api_keys::migrate_legacy_keys(&env).await;
+    webhooks::retry_due(&env).await;
+    login_guard::prune(&env).await;
+    rate_limit::prune(&env).await;
+    edit_sessions::expire(&env).await;
+}
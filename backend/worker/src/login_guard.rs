@@ -0,0 +1,346 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+
+const MINUTE: u64 = 60;
+
+/// How failed logins against one subject are throttled. The first
+/// `free_attempts` failures cost nothing; after that each one doubles the
+/// wait before the next attempt, up to `max_delay`, and `lock_after`
+/// failures lock the subject out for `lock_seconds`. Failures older than
+/// `window` are forgotten.
+struct Policy {
+    scope: &'static str,
+    free_attempts: u32,
+    max_delay: u64,
+    lock_after: u32,
+    lock_seconds: u64,
+    window: u64,
+}
+
+/// Per normalized email, whether or not an account exists, so responses do
+/// not reveal which addresses are registered.
+const ACCOUNT: Policy = Policy {
+    scope: "account",
+    free_attempts: 3,
+    max_delay: MINUTE,
+    lock_after: 10,
+    lock_seconds: 15 * MINUTE,
+    window: 15 * MINUTE,
+};
+
+/// Per `CF-Connecting-IP`. Looser, since many people can share an address,
+/// but it stops one client from cycling through emails.
+const IP: Policy = Policy {
+    scope: "ip",
+    free_attempts: 10,
+    max_delay: MINUTE,
+    lock_after: 50,
+    lock_seconds: 15 * MINUTE,
+    window: 60 * MINUTE,
+};
+
+/// `login_failures` as D1 returns it.
+#[derive(Debug, Default, Deserialize)]
+struct Failures {
+    failures: u32,
+    last_failed_at: u64,
+    locked_until: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    Allow,
+    /// Backing off; seconds until the next attempt is accepted.
+    Wait(u64),
+    /// Locked out; seconds until the lock ends.
+    Locked(u64),
+}
+
+impl Policy {
+    fn delay(&self, failures: u32) -> u64 {
+        if failures < self.free_attempts {
+            return 0;
+        }
+        let doublings = (failures - self.free_attempts).min(16);
+        (1u64 << doublings).min(self.max_delay)
+    }
+
+    fn verdict(&self, record: &Failures, now: u64) -> Verdict {
+        if record.locked_until > now {
+            return Verdict::Locked(record.locked_until - now);
+        }
+        if now.saturating_sub(record.last_failed_at) >= self.window {
+            return Verdict::Allow;
+        }
+        let next_attempt = record.last_failed_at + self.delay(record.failures);
+        if next_attempt > now {
+            Verdict::Wait(next_attempt - now)
+        } else {
+            Verdict::Allow
+        }
+    }
+}
+
+fn now() -> u64 {
+    Date::now().as_millis() / 1000
+}
+
+fn locked_error(retry_after: u64) -> ApiError {
+    let minutes = retry_after.div_ceil(MINUTE);
+    ApiError::new(
+        423,
+        "account_locked",
+        format!(
+            "Account temporarily locked after too many failed logins. Try again in {} minute{}, or reset your password.",
+            minutes,
+            if minutes == 1 { "" } else { "s" }
+        ),
+    )
+    .with_retryable(true)
+    .with_header("Retry-After", retry_after.to_string())
+    .with_details(json!({ "retry_after": retry_after }))
+}
+
+fn throttled_error(retry_after: u64) -> ApiError {
+    ApiError::new(
+        429,
+        "login_throttled",
+        format!("Too many failed logins. Try again in {} seconds.", retry_after),
+    )
+    .with_header("Retry-After", retry_after.to_string())
+    .with_details(json!({ "retry_after": retry_after }))
+}
+
+/// The address Cloudflare saw the request come from. Absent under
+/// `wrangler dev`, in which case only the per-account limit applies.
+pub fn client_ip(req: &Request) -> Option<String> {
+    req.headers()
+        .get("CF-Connecting-IP")
+        .ok()
+        .flatten()
+        .filter(|ip| !ip.is_empty())
+}
+
+/// Failed-login bookkeeping for one `/login` request.
+pub struct LoginGuard {
+    email: String,
+    ip: Option<String>,
+}
+
+impl LoginGuard {
+    fn subjects(&self) -> Vec<(&'static Policy, &str)> {
+        let mut subjects = vec![(&ACCOUNT, self.email.as_str())];
+        if let Some(ip) = &self.ip {
+            subjects.push((&IP, ip.as_str()));
+        }
+        subjects
+    }
+
+    /// Runs before the password is checked, so a locked or backing-off
+    /// client never gets to spend bcrypt time.
+    pub async fn check(env: &Env, email: &str, ip: Option<String>) -> ApiResult<Self> {
+        let guard = Self {
+            email: email.to_string(),
+            ip,
+        };
+        let db = env.d1("DB")?;
+        let now = now();
+
+        for (policy, subject) in guard.subjects() {
+            let statement = db.prepare("SELECT failures, last_failed_at, locked_until FROM login_failures WHERE scope = ? AND subject = ?");
+            let query = statement.bind_refs(&[D1Type::Text(policy.scope), D1Type::Text(subject)])?;
+            let record: Failures = query.first(None).await?.unwrap_or_default();
+
+            match policy.verdict(&record, now) {
+                Verdict::Allow => {}
+                Verdict::Locked(retry_after) if policy.scope == ACCOUNT.scope => return Err(locked_error(retry_after)),
+                Verdict::Locked(retry_after) | Verdict::Wait(retry_after) => return Err(throttled_error(retry_after)),
+            }
+        }
+
+        Ok(guard)
+    }
+
+    /// Counts a wrong password against the account and the IP. Returns
+    /// `error` unchanged, unless this failure is the one that locked the
+    /// account.
+    pub async fn failed(&self, env: &Env, error: ApiError) -> ApiError {
+        let mut result = error;
+        let mut events = vec![("login_failed", json!({}))];
+
+        let recorded = async {
+            let now = now();
+            for (policy, subject) in self.subjects() {
+                let failures = count_failure(env, policy, subject, now).await?;
+                if failures < policy.lock_after {
+                    continue;
+                }
+
+                lock(env, policy, subject, now + policy.lock_seconds).await?;
+                let details = json!({ "failures": failures, "locked_for": policy.lock_seconds });
+                if policy.scope == ACCOUNT.scope {
+                    events.push(("account_locked", details));
+                    result = locked_error(policy.lock_seconds);
+                } else {
+                    events.push(("ip_locked", details));
+                }
+            }
+            Ok::<(), worker::Error>(())
+        }
+        .await;
+
+        if let Err(e) = recorded {
+            console_log!("Failed to record failed login: {}", e);
+        }
+        for (event, details) in events {
+            record_event(env, event, Some(&self.email), self.ip.as_deref(), details).await;
+        }
+        result
+    }
+
+    /// A correct password clears the account's failures. The IP's are kept:
+    /// one valid login must not reset the budget for guessing other accounts.
+    pub async fn succeeded(&self, env: &Env) {
+        if let Err(e) = clear_account(env, &self.email).await {
+            console_log!("Failed to clear failed logins: {}", e);
+        }
+    }
+}
+
+/// Adds one failure, starting over if the previous one has aged out of the
+/// window, and returns the new count.
+async fn count_failure(env: &Env, policy: &Policy, subject: &str, now: u64) -> Result<u32> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "INSERT INTO login_failures (scope, subject, failures, last_failed_at, locked_until) VALUES (?, ?, 1, ?, 0) \
+         ON CONFLICT(scope, subject) DO UPDATE SET \
+         failures = CASE WHEN last_failed_at <= ? THEN 1 ELSE failures + 1 END, \
+         last_failed_at = excluded.last_failed_at \
+         RETURNING failures",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(policy.scope),
+        D1Type::Text(subject),
+        D1Type::Real(now as f64),
+        D1Type::Real(now.saturating_sub(policy.window) as f64),
+    ])?;
+    Ok(query.first(Some("failures")).await?.unwrap_or(1))
+}
+
+/// Locks `subject` and restarts its count, so once the lock ends the
+/// backoff starts again from the free attempts.
+async fn lock(env: &Env, policy: &Policy, subject: &str, until: u64) -> Result<()> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("UPDATE login_failures SET failures = 0, locked_until = ? WHERE scope = ? AND subject = ?");
+    let query = statement.bind_refs(&[D1Type::Real(until as f64), D1Type::Text(policy.scope), D1Type::Text(subject)])?;
+    query.run().await?;
+    Ok(())
+}
+
+/// Forgets failures and any lock on `email`, e.g. after a password reset.
+pub async fn clear_account(env: &Env, email: &str) -> Result<()> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("DELETE FROM login_failures WHERE scope = ? AND subject = ?");
+    statement.bind_refs(&[D1Type::Text(ACCOUNT.scope), D1Type::Text(email)])?.run().await?;
+    Ok(())
+}
+
+/// Appends to `security_events`. The user is looked up by email, so events
+/// for addresses without an account are kept with no `user_id`.
+pub async fn record_event(env: &Env, event: &str, email: Option<&str>, ip: Option<&str>, details: Value) {
+    let result = async {
+        let db = env.d1("DB")?;
+        let statement = db.prepare(
+            "INSERT INTO security_events (event, user_id, email, ip, details, created_at) \
+             VALUES (?1, (SELECT id FROM users WHERE lower(email) = ?2), ?2, ?3, ?4, datetime('now'))",
+        );
+        let email = email.map(D1Type::Text).unwrap_or(D1Type::Null);
+        let ip = ip.map(D1Type::Text).unwrap_or(D1Type::Null);
+        let details = details.to_string();
+        let query = statement.bind_refs(&[D1Type::Text(event), email, ip, D1Type::Text(&details)])?;
+        query.run().await?;
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to record security event {}: {}", event, e);
+    }
+}
+
+/// Drops rows whose failures have aged out and whose lock has ended.
+/// Called from the scheduled handler.
+pub async fn prune(env: &Env) {
+    let result = async {
+        let now = now();
+        let db = env.d1("DB")?;
+        for policy in [&ACCOUNT, &IP] {
+            let statement = db.prepare("DELETE FROM login_failures WHERE scope = ? AND last_failed_at <= ? AND locked_until <= ?");
+            let query = statement.bind_refs(&[
+                D1Type::Text(policy.scope),
+                D1Type::Real(now.saturating_sub(policy.window) as f64),
+                D1Type::Real(now as f64),
+            ])?;
+            query.run().await?;
+        }
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to prune failed logins: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failures(failures: u32, last_failed_at: u64) -> Failures {
+        Failures {
+            failures,
+            last_failed_at,
+            locked_until: 0,
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_after_free_attempts() {
+        let delays: Vec<u64> = (0..12).map(|n| ACCOUNT.delay(n)).collect();
+        assert_eq!(delays, vec![0, 0, 0, 1, 2, 4, 8, 16, 32, 60, 60, 60]);
+        assert_eq!(IP.delay(9), 0);
+        assert_eq!(IP.delay(u32::MAX), MINUTE);
+    }
+
+    #[test]
+    fn test_verdict() {
+        let now = 10_000;
+        assert_eq!(ACCOUNT.verdict(&Failures::default(), now), Verdict::Allow);
+        assert_eq!(ACCOUNT.verdict(&failures(2, now), now), Verdict::Allow);
+        assert_eq!(ACCOUNT.verdict(&failures(5, now - 1), now), Verdict::Wait(3));
+        assert_eq!(ACCOUNT.verdict(&failures(5, now - 4), now), Verdict::Allow);
+        // Old failures no longer count.
+        assert_eq!(ACCOUNT.verdict(&failures(9, now - ACCOUNT.window), now), Verdict::Allow);
+
+        let locked = Failures {
+            locked_until: now + 90,
+            ..failures(0, now)
+        };
+        assert_eq!(ACCOUNT.verdict(&locked, now), Verdict::Locked(90));
+        assert_eq!(ACCOUNT.verdict(&locked, now + 90), Verdict::Allow);
+    }
+
+    #[test]
+    fn test_locked_error_is_distinct() {
+        let error = locked_error(61);
+        assert_eq!(error.status, 423);
+        assert_eq!(error.code, "account_locked");
+        assert!(error.message.contains("2 minutes"));
+        assert_eq!(error.details.unwrap()["retry_after"], 61);
+        assert_eq!(throttled_error(5).code, "login_throttled");
+    }
+}
@@ -0,0 +1,149 @@
+use serde_json::json;
+use worker::*;
+
+/// A plain-text message. Nothing the worker sends needs HTML.
+#[derive(Debug)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers mail on behalf of the worker.
+#[allow(async_fn_in_trait)]
+pub trait MailSender {
+    async fn send(&self, mail: &Mail) -> Result<()>;
+}
+
+/// Sends through an HTTP mail API: a JSON `POST` of `from`, `to`,
+/// `subject` and `text` with a bearer key, as Resend and most others take.
+pub struct HttpMailer {
+    url: String,
+    api_key: String,
+    from: String,
+}
+
+impl MailSender for HttpMailer {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        let headers = Headers::new();
+        headers.set("Authorization", &format!("Bearer {}", self.api_key))?;
+        headers.set("Content-Type", "application/json")?;
+
+        let body = json!({
+            "from": self.from,
+            "to": mail.to,
+            "subject": mail.subject,
+            "text": mail.body,
+        });
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post)
+            .with_headers(headers)
+            .with_body(Some(body.to_string().into()));
+
+        let request = Request::new_with_init(&self.url, &init)?;
+        let response = Fetch::Request(request).send().await?;
+        let status = response.status_code();
+        if !(200..300).contains(&status) {
+            return Err(Error::RustError(format!("Mail API answered HTTP {}", status)));
+        }
+        Ok(())
+    }
+}
+
+/// Writes messages, tokens included, to the worker log instead of
+/// delivering them. Only for local development and tests.
+pub struct LogMailer;
+
+impl MailSender for LogMailer {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        console_log!("=== MAIL to {} ===\nSubject: {}\n\n{}", mail.to, mail.subject, mail.body);
+        Ok(())
+    }
+}
+
+/// The mail settings in the environment. Empty values count as unset.
+#[derive(Default)]
+struct MailConfig {
+    api_url: Option<String>,
+    api_key: Option<String>,
+    from: Option<String>,
+    log_only: bool,
+}
+
+/// The sender the environment configures.
+pub enum Mailer {
+    Http(HttpMailer),
+    Log(LogMailer),
+}
+
+impl Mailer {
+    /// Uses the `MAIL_API_URL` and `MAIL_FROM` vars with the `MAIL_API_KEY`
+    /// secret. Without them, mail is only logged when `MAIL_LOG_ONLY` is
+    /// `"true"`, and otherwise there is no mailer at all.
+    pub fn from_env(env: &Env) -> Option<Self> {
+        let read = |name: &str| env.var(name).ok().map(|value| value.to_string());
+        Self::from_config(MailConfig {
+            api_url: read("MAIL_API_URL"),
+            api_key: env.secret("MAIL_API_KEY").ok().map(|value| value.to_string()),
+            from: read("MAIL_FROM"),
+            log_only: read("MAIL_LOG_ONLY").as_deref() == Some("true"),
+        })
+    }
+
+    fn from_config(config: MailConfig) -> Option<Self> {
+        let set = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+        match (set(config.api_url), set(config.api_key), set(config.from)) {
+            (Some(url), Some(api_key), Some(from)) => Some(Mailer::Http(HttpMailer { url, api_key, from })),
+            _ if config.log_only => Some(Mailer::Log(LogMailer)),
+            _ => None,
+        }
+    }
+}
+
+impl MailSender for Mailer {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        match self {
+            Mailer::Http(mailer) => mailer.send(mail).await,
+            Mailer::Log(mailer) => mailer.send(mail).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured() -> MailConfig {
+        MailConfig {
+            api_url: Some("https://api.resend.com/emails".to_string()),
+            api_key: Some("re_test".to_string()),
+            from: Some("GeminiPocket <noreply@example.com>".to_string()),
+            log_only: false,
+        }
+    }
+
+    #[test]
+    fn test_mailer_needs_config_or_explicit_log_flag() {
+        assert!(matches!(Mailer::from_config(configured()), Some(Mailer::Http(_))));
+        assert!(Mailer::from_config(MailConfig::default()).is_none());
+
+        let partial = MailConfig {
+            api_key: Some(" ".to_string()),
+            ..configured()
+        };
+        assert!(Mailer::from_config(partial).is_none());
+
+        let log_only = MailConfig {
+            log_only: true,
+            ..MailConfig::default()
+        };
+        assert!(matches!(Mailer::from_config(log_only), Some(Mailer::Log(_))));
+
+        // A configured provider wins over the flag.
+        let both = MailConfig {
+            log_only: true,
+            ..configured()
+        };
+        assert!(matches!(Mailer::from_config(both), Some(Mailer::Http(_))));
+    }
+}
@@ -0,0 +1,119 @@
+use serde_json::{json, Value};
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+use crate::upstream::{IMAGE_EDIT_MODEL, IMAGE_GENERATE_MODEL, VIDEO_MODEL};
+use crate::usage::GenerationKind;
+
+const KINDS: [GenerationKind; 4] = [
+    GenerationKind::Generate,
+    GenerationKind::Edit,
+    GenerationKind::GenerateVideo,
+    GenerationKind::EditVideo,
+];
+
+/// The env var holding the allowlist for `kind`, and the model used when it
+/// is not set. Both video kinds run on Veo and share a list.
+fn source(kind: GenerationKind) -> (&'static str, &'static str) {
+    match kind {
+        GenerationKind::Generate => ("GENERATE_MODELS", IMAGE_GENERATE_MODEL),
+        GenerationKind::Edit => ("EDIT_MODELS", IMAGE_EDIT_MODEL),
+        GenerationKind::GenerateVideo | GenerationKind::EditVideo => ("VIDEO_MODELS", VIDEO_MODEL),
+    }
+}
+
+/// Parses a comma-separated allowlist. The first entry is the default, so
+/// an empty or missing list falls back to the built-in model.
+fn parse_list(value: Option<&str>, fallback: &str) -> Vec<String> {
+    let models: Vec<String> = value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|model| !model.is_empty())
+        .map(String::from)
+        .collect();
+    if models.is_empty() {
+        vec![fallback.to_string()]
+    } else {
+        models
+    }
+}
+
+/// The models `kind` requests may use, default first.
+pub fn allowed(env: &Env, kind: GenerationKind) -> Vec<String> {
+    let (var, fallback) = source(kind);
+    let value = env.var(var).ok().map(|value| value.to_string());
+    parse_list(value.as_deref(), fallback)
+}
+
+fn select(allowed: Vec<String>, kind: GenerationKind, requested: Option<&str>) -> ApiResult<String> {
+    let requested = requested
+        .map(|model| model.trim().trim_start_matches("models/"))
+        .filter(|model| !model.is_empty());
+    let Some(model) = requested else {
+        return Ok(allowed[0].clone());
+    };
+
+    if allowed.iter().any(|allowed| allowed == model) {
+        return Ok(model.to_string());
+    }
+    Err(ApiError::new(
+        400,
+        "model_not_allowed",
+        format!("Model {} is not available for {} requests. See GET /models", model, kind.as_str()),
+    )
+    .with_details(json!({ "allowed": allowed })))
+}
+
+/// Picks the model for a request: the one asked for if it is on the
+/// allowlist, the kind's default if none was given.
+pub fn resolve(env: &Env, kind: GenerationKind, requested: Option<&str>) -> ApiResult<String> {
+    select(allowed(env, kind), kind, requested)
+}
+
+/// `GET /models` — the models each request kind accepts and its default.
+pub async fn handle_list(_req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let mut models = serde_json::Map::new();
+    for kind in KINDS {
+        let allowed = allowed(&ctx.env, kind);
+        models.insert(
+            kind.as_str().to_string(),
+            json!({
+                "default": allowed[0],
+                "allowed": allowed,
+            }),
+        );
+    }
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "models": Value::Object(models),
+    }))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list_falls_back_to_builtin() {
+        assert_eq!(parse_list(None, "a"), vec!["a"]);
+        assert_eq!(parse_list(Some(" , "), "a"), vec!["a"]);
+        assert_eq!(parse_list(Some("b, c ,,d"), "a"), vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_select_enforces_allowlist() {
+        let allowed = || vec!["first".to_string(), "second".to_string()];
+        let kind = GenerationKind::Generate;
+
+        assert_eq!(select(allowed(), kind, None).unwrap(), "first");
+        assert_eq!(select(allowed(), kind, Some("  ")).unwrap(), "first");
+        assert_eq!(select(allowed(), kind, Some("second")).unwrap(), "second");
+        assert_eq!(select(allowed(), kind, Some("models/second")).unwrap(), "second");
+
+        let error = select(allowed(), kind, Some("other")).unwrap_err();
+        assert_eq!(error.code, "model_not_allowed");
+        assert_eq!(error.details.unwrap()["allowed"], json!(["first", "second"]));
+    }
+}
@@ -42,7 +42,10 @@ pub fn openapi_spec() -> serde_json::Value {
                                         "$ref": "#/components/schemas/HealthStatus"
                                     },
                                      "example": {
-                                         "status": "healthy"
+                                         "status": "healthy",
+                                         "checks": {
+                                             "asset_url_signing": true
+                                         }
                                      }
                                 }
                             }
@@ -119,7 +122,9 @@ pub fn openapi_spec() -> serde_json::Value {
                                      },
                                      "example": {
                                          "success": true,
-                                         "image": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="
+                                         "asset_id": "5f0c6a52-3c1e-4a8e-9a61-0d2b7f3e9c41",
+                                         "asset_url": "https://geminipocket.guitaripod.workers.dev/assets/5f0c6a52-3c1e-4a8e-9a61-0d2b7f3e9c41",
+                                         "mime_type": "image/png"
                                      }
                                  }
                              }
@@ -134,6 +139,16 @@ pub fn openapi_spec() -> serde_json::Value {
                                  }
                              }
                          },
+                         "422": {
+                             "description": "Blocked by the model's safety filters (content_blocked). error.details has blocked (prompt or response), reason and safety_ratings",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
                          "401": {
                              "description": "Unauthorized - Invalid or missing API key",
                              "content": {
@@ -144,8 +159,18 @@ pub fn openapi_spec() -> serde_json::Value {
                                  }
                              }
                          },
-                         "500": {
-                             "description": "Internal server error or Gemini API error",
+                         "429": {
+                             "description": "Rate limit exceeded. See Retry-After and X-RateLimit-* headers",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "502": {
+                             "description": "Upstream Gemini API error",
                              "content": {
                                  "application/json": {
                                      "schema": {
@@ -160,7 +185,7 @@ pub fn openapi_spec() -> serde_json::Value {
             "/register": {
                 "post": {
                     "summary": "Register New User",
-                    "description": "Creates a new user account and returns an API key",
+                    "description": "Creates a new user account and returns its first API key, named Default. With session=true it returns a session token and refresh token instead, and no key is issued",
                     "operationId": "registerUser",
                     "tags": ["Authentication"],
                     "requestBody": {
@@ -183,7 +208,10 @@ pub fn openapi_spec() -> serde_json::Value {
                             "content": {
                                 "application/json": {
                                     "schema": {
-                                        "$ref": "#/components/schemas/AuthResponse"
+                                        "oneOf": [
+                                            { "$ref": "#/components/schemas/AuthResponse" },
+                                            { "$ref": "#/components/schemas/SessionResponse" }
+                                        ]
                                     },
                                     "example": {
                                         "success": true,
@@ -193,7 +221,30 @@ pub fn openapi_spec() -> serde_json::Value {
                             }
                         },
                         "400": {
-                            "description": "Invalid request or user already exists",
+                            "description": "validation_failed - error.details.fields lists each violation as {field, code, message}. Codes: email_required, email_invalid, email_too_long, password_too_short, password_too_long, password_too_weak, password_contains_email",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    },
+                                    "example": {
+                                        "success": false,
+                                        "error": {
+                                            "code": "validation_failed",
+                                            "message": "Password must be at least 8 characters",
+                                            "retryable": false,
+                                            "details": {
+                                                "fields": [
+                                                    { "field": "password", "code": "password_too_short", "message": "Password must be at least 8 characters" }
+                                                ]
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "409": {
+                            "description": "User already exists",
                             "content": {
                                 "application/json": {
                                     "schema": {
@@ -208,7 +259,7 @@ pub fn openapi_spec() -> serde_json::Value {
             "/login": {
                 "post": {
                     "summary": "User Login",
-                    "description": "Authenticates a user and returns their API key",
+                    "description": "Authenticates a user and issues a new named API key. Other keys are only revoked when replace=true. Existing keys are never returned. Repeated failures for an email or client IP are answered with increasing delays and then a temporary lockout. With session=true it returns a short-lived session token and a refresh token instead of a key",
                     "operationId": "loginUser",
                     "tags": ["Authentication"],
                     "requestBody": {
@@ -231,7 +282,10 @@ pub fn openapi_spec() -> serde_json::Value {
                             "content": {
                                 "application/json": {
                                     "schema": {
-                                        "$ref": "#/components/schemas/AuthResponse"
+                                        "oneOf": [
+                                            { "$ref": "#/components/schemas/AuthResponse" },
+                                            { "$ref": "#/components/schemas/SessionResponse" }
+                                        ]
                                     },
                                     "example": {
                                         "success": true,
@@ -249,6 +303,26 @@ pub fn openapi_spec() -> serde_json::Value {
                                     }
                                 }
                             }
+                        },
+                        "423": {
+                            "description": "account_locked: too many failed logins for this email. The password is not checked until the lock ends. See Retry-After and details.retry_after, or reset the password to lift it",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "429": {
+                            "description": "login_throttled: backing off after recent failed logins for this email or client IP. See Retry-After and details.retry_after",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
                         }
                     }
                 }
@@ -256,7 +330,7 @@ pub fn openapi_spec() -> serde_json::Value {
              "/edit": {
                  "post": {
                      "summary": "Edit Image with Text Prompt",
-                     "description": "Edits an existing image based on a text prompt using Google's Gemini API",
+                     "description": "Edits an existing image based on a text prompt using Google's Gemini API. Send JSON with base64 images, or multipart/form-data with the images as files. Request bodies over 21 MiB are refused with 413",
                      "operationId": "editImage",
                      "tags": ["Image Generation"],
                      "security": [
@@ -267,6 +341,11 @@ pub fn openapi_spec() -> serde_json::Value {
                      "requestBody": {
                          "required": true,
                          "content": {
+                             "multipart/form-data": {
+                                 "schema": {
+                                     "$ref": "#/components/schemas/EditImageForm"
+                                 }
+                             },
                              "application/json": {
                                  "schema": {
                                      "$ref": "#/components/schemas/EditImageRequest"
@@ -280,6 +359,16 @@ pub fn openapi_spec() -> serde_json::Value {
                                              "mime_type": "image/png"
                                          }
                                      },
+                                     "combine": {
+                                         "summary": "Combine several images",
+                                         "value": {
+                                             "prompt": "Put the person from image 1 into the scene of image 2",
+                                             "images": [
+                                                 {"data": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==", "mime_type": "image/png"},
+                                                 {"data": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==", "mime_type": "image/png"}
+                                             ]
+                                         }
+                                     },
                                      "enhance": {
                                          "summary": "Enhance an image",
                                          "value": {
@@ -301,13 +390,55 @@ pub fn openapi_spec() -> serde_json::Value {
                                      },
                                      "example": {
                                          "success": true,
-                                         "image": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="
+                                         "asset_id": "5f0c6a52-3c1e-4a8e-9a61-0d2b7f3e9c41",
+                                         "asset_url": "https://geminipocket.guitaripod.workers.dev/assets/5f0c6a52-3c1e-4a8e-9a61-0d2b7f3e9c41",
+                                         "mime_type": "image/png"
                                      }
                                  }
                              }
                          },
                          "400": {
-                             "description": "Invalid request body",
+                             "description": "Invalid request body, or an image that is not valid base64 or is truncated or corrupt (invalid_image)",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "411": {
+                             "description": "A multipart body was sent without a Content-Length header (length_required)",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "413": {
+                             "description": "The request body exceeds 21 MiB or the input images together exceed 15 MiB (payload_too_large). error.details has size and limit",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "415": {
+                             "description": "The input is not a supported image format (unsupported_image_type). error.details has detected and supported",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "422": {
+                             "description": "Blocked by the model's safety filters (content_blocked). error.details has blocked (prompt or response), reason and safety_ratings",
                              "content": {
                                  "application/json": {
                                      "schema": {
@@ -326,8 +457,18 @@ pub fn openapi_spec() -> serde_json::Value {
                                  }
                              }
                          },
-                         "500": {
-                             "description": "Internal server error or Gemini API error",
+                         "429": {
+                             "description": "Rate limit exceeded. See Retry-After and X-RateLimit-* headers",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "502": {
+                             "description": "Upstream Gemini API error",
                              "content": {
                                  "application/json": {
                                      "schema": {
@@ -387,7 +528,7 @@ pub fn openapi_spec() -> serde_json::Value {
                                      },
                                      "example": {
                                          "success": true,
-                                         "operation_name": "projects/project-id/locations/us-central1/operations/operation-id"
+                                         "job_id": "3d9f1c2a-7b64-4e0f-a8d5-1c2b3e4f5a6b"
                                      }
                                  }
                              }
@@ -412,8 +553,18 @@ pub fn openapi_spec() -> serde_json::Value {
                                  }
                              }
                          },
-                         "500": {
-                             "description": "Internal server error or Veo API error",
+                         "429": {
+                             "description": "Rate limit exceeded. See Retry-After and X-RateLimit-* headers",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "502": {
+                             "description": "Upstream Veo API error",
                              "content": {
                                  "application/json": {
                                      "schema": {
@@ -428,7 +579,7 @@ pub fn openapi_spec() -> serde_json::Value {
              "/edit_video": {
                  "post": {
                      "summary": "Edit Video with Image and Text Prompt",
-                     "description": "Edits a video based on an input image and text prompt using Google's Veo API",
+                     "description": "Edits a video based on an input image and text prompt using Google's Veo API. Send JSON with a base64 image, or multipart/form-data with the image as a file. Request bodies over 21 MiB are refused with 413",
                      "operationId": "editVideo",
                      "tags": ["Video Generation"],
                      "security": [
@@ -439,6 +590,11 @@ pub fn openapi_spec() -> serde_json::Value {
                      "requestBody": {
                          "required": true,
                          "content": {
+                             "multipart/form-data": {
+                                 "schema": {
+                                     "$ref": "#/components/schemas/EditVideoForm"
+                                 }
+                             },
                              "application/json": {
                                  "schema": {
                                      "$ref": "#/components/schemas/EditVideoRequest"
@@ -477,13 +633,43 @@ pub fn openapi_spec() -> serde_json::Value {
                                      },
                                      "example": {
                                          "success": true,
-                                         "operation_name": "projects/project-id/locations/us-central1/operations/operation-id"
+                                         "job_id": "3d9f1c2a-7b64-4e0f-a8d5-1c2b3e4f5a6b"
                                      }
                                  }
                              }
                          },
                          "400": {
-                             "description": "Invalid request body",
+                             "description": "Invalid request body, or an image that is not valid base64 or is truncated or corrupt (invalid_image)",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "411": {
+                             "description": "A multipart body was sent without a Content-Length header (length_required)",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "413": {
+                             "description": "The request body exceeds 21 MiB or the image exceeds 15 MiB (payload_too_large). error.details has size and limit",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "415": {
+                             "description": "The input is not a supported image format (unsupported_image_type). error.details has detected and supported",
                              "content": {
                                  "application/json": {
                                      "schema": {
@@ -502,8 +688,18 @@ pub fn openapi_spec() -> serde_json::Value {
                                  }
                              }
                          },
-                         "500": {
-                             "description": "Internal server error or Veo API error",
+                         "429": {
+                             "description": "Rate limit exceeded. See Retry-After and X-RateLimit-* headers",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
+                         "502": {
+                             "description": "Upstream Veo API error",
                              "content": {
                                  "application/json": {
                                      "schema": {
@@ -515,10 +711,10 @@ pub fn openapi_spec() -> serde_json::Value {
                      }
                  }
              },
-             "/video_status/{operation}": {
+             "/video_status/{job_id}": {
                  "get": {
                      "summary": "Check Video Generation Status",
-                     "description": "Checks the status of a video generation or editing operation",
+                     "description": "Checks the status of a video generation or editing job started by the caller. Jobs are also advanced server-side every minute, so the result can be collected at any time after it finishes",
                      "operationId": "getVideoStatus",
                      "tags": ["Video Generation"],
                      "security": [
@@ -528,14 +724,24 @@ pub fn openapi_spec() -> serde_json::Value {
                      ],
                      "parameters": [
                          {
-                             "name": "operation",
+                             "name": "job_id",
                              "in": "path",
                              "required": true,
                              "schema": {
                                  "type": "string"
                              },
-                             "description": "The operation name returned from video generation/editing request",
-                             "example": "projects/project-id/locations/us-central1/operations/operation-id"
+                             "description": "The job id returned from the video generation/editing request",
+                             "example": "3d9f1c2a-7b64-4e0f-a8d5-1c2b3e4f5a6b"
+                         },
+                         {
+                             "name": "inline",
+                             "in": "query",
+                             "required": false,
+                             "schema": {
+                                 "type": "boolean",
+                                 "default": false
+                             },
+                             "description": "Also return the finished video as a base64 data: URL"
                          }
                      ],
                      "responses": {
@@ -551,6 +757,7 @@ pub fn openapi_spec() -> serde_json::Value {
                                              "summary": "Video generation in progress",
                                              "value": {
                                                  "success": true,
+                                                 "job_id": "3d9f1c2a-7b64-4e0f-a8d5-1c2b3e4f5a6b",
                                                  "done": false
                                              }
                                          },
@@ -558,8 +765,11 @@ pub fn openapi_spec() -> serde_json::Value {
                                              "summary": "Video generation completed",
                                              "value": {
                                                  "success": true,
+                                                 "job_id": "3d9f1c2a-7b64-4e0f-a8d5-1c2b3e4f5a6b",
                                                  "done": true,
-                                                 "video_uri": "https://storage.googleapis.com/generated-videos/video.mp4"
+                                                 "asset_id": "0b8e2d7c-9f14-4a5b-8c3e-6a1f2d4b7e90",
+                                                 "video_url": "https://geminipocket.guitaripod.workers.dev/assets/0b8e2d7c-9f14-4a5b-8c3e-6a1f2d4b7e90",
+                                                 "mime_type": "video/mp4"
                                              }
                                          }
                                      }
@@ -576,8 +786,18 @@ pub fn openapi_spec() -> serde_json::Value {
                                  }
                              }
                          },
+                         "429": {
+                             "description": "Rate limit exceeded. See Retry-After and X-RateLimit-* headers",
+                             "content": {
+                                 "application/json": {
+                                     "schema": {
+                                         "$ref": "#/components/schemas/ErrorResponse"
+                                     }
+                                 }
+                             }
+                         },
                          "404": {
-                             "description": "Operation not found",
+                             "description": "Job not found or owned by another user",
                              "content": {
                                  "application/json": {
                                      "schema": {
@@ -598,305 +818,3245 @@ pub fn openapi_spec() -> serde_json::Value {
                          }
                      }
                  }
-             }
-        },
-         "components": {
-             "securitySchemes": {
-                 "bearerAuth": {
-                     "type": "http",
-                     "scheme": "bearer",
-                     "description": "API key obtained from user registration or login"
-                 }
              },
-             "schemas": {
-                "ApiInfo": {
-                    "type": "object",
-                    "required": ["name", "version", "endpoints"],
-                    "properties": {
-                        "name": {
-                            "type": "string",
-                            "description": "Name of the API"
+            "/usage": {
+                "get": {
+                    "summary": "Usage Summary",
+                    "description": "Returns the caller's generation totals grouped by day and kind, recorded in the generations ledger",
+                    "operationId": "getUsage",
+                    "tags": ["Account"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "days",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "maximum": 365,
+                                "default": 30
+                            },
+                            "description": "Number of days to include"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Usage retrieved successfully",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/UsageResponse"
+                                    }
+                                }
+                            }
                         },
-                        "version": {
-                            "type": "string",
-                            "description": "Version of the API"
+                        "400": {
+                            "description": "Invalid days parameter",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
                         },
-                         "endpoints": {
-                             "type": "object",
-                             "description": "Available endpoints",
-                             "properties": {
-                                 "generate": {
-                                     "type": "string",
-                                     "description": "Text-to-image generation endpoint"
-                                 },
-                                 "edit": {
-                                     "type": "string",
-                                     "description": "Image editing endpoint"
-                                 },
-                                 "generate_video": {
-                                     "type": "string",
-                                     "description": "Text-to-video generation endpoint"
-                                 },
-                                 "edit_video": {
-                                     "type": "string",
-                                     "description": "Video editing with image input endpoint"
-                                 },
-                                 "video_status": {
-                                     "type": "string",
-                                     "description": "Video generation status check endpoint"
-                                 },
-                                 "health": {
-                                     "type": "string",
-                                     "description": "Health check endpoint"
-                                 }
-                             }
-                         }
-                    }
-                },
-                 "HealthStatus": {
-                     "type": "object",
-                     "required": ["status"],
-                     "properties": {
-                         "status": {
-                             "type": "string",
-                             "enum": ["healthy", "unhealthy"],
-                             "description": "Health status of the service"
-                         }
-                     }
-                 },
-                "GenerateImageRequest": {
-                    "type": "object",
-                    "required": ["prompt"],
-                    "properties": {
-                        "prompt": {
-                            "type": "string",
-                            "description": "Text prompt describing the image to generate",
-                            "minLength": 1,
-                            "maxLength": 10000,
-                            "example": "A beautiful sunset over mountains"
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
                         }
                     }
-                },
-                "EditImageRequest": {
-                    "type": "object",
-                    "required": ["prompt", "image"],
-                    "properties": {
-                        "prompt": {
-                            "type": "string",
-                            "description": "Text prompt describing how to edit the image",
-                            "minLength": 1,
+                }
+            },
+            "/history": {
+                "get": {
+                    "summary": "List Generations",
+                    "description": "Returns the caller's generations newest first, with cursor pagination and optional filters",
+                    "operationId": "listHistory",
+                    "tags": ["Account"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "limit",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "maximum": 100,
+                                "default": 20
+                            },
+                            "description": "Page size"
+                        },
+                        {
+                            "name": "cursor",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Opaque cursor from a previous page's next_cursor"
+                        },
+                        {
+                            "name": "kind",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string",
+                                "enum": ["generate", "edit", "generate_video", "edit_video"]
+                            },
+                            "description": "Only include one kind of generation"
+                        },
+                        {
+                            "name": "from",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string",
+                                "format": "date"
+                            },
+                            "description": "Only include generations created on or after this date"
+                        },
+                        {
+                            "name": "to",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string",
+                                "format": "date"
+                            },
+                            "description": "Only include generations created on or before this date. A full timestamp (YYYY-MM-DD HH:MM:SS) is exclusive"
+                        },
+                        {
+                            "name": "q",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Case-insensitive substring of the prompt"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "History page retrieved successfully",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/HistoryPage"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Invalid filter or cursor",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/history/{id}": {
+                "get": {
+                    "summary": "Get Generation",
+                    "description": "Returns one of the caller's generations with its parameters and a download link when the output can be fetched again",
+                    "operationId": "getHistoryItem",
+                    "tags": ["Account"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Generation id"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Generation retrieved successfully",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/HistoryDetailResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "Generation not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/assets/{id}": {
+                "get": {
+                    "summary": "Download Asset",
+                    "description": "Streams a generated image or video owned by the caller. Supports single byte ranges for seeking. The asset_url and video_url in responses are signed with exp and sig query parameters, so they work without an Authorization header until they expire (1 hour; 24 hours in webhook payloads). Send the header, or fetch a fresh URL, after that",
+                    "operationId": "getAsset",
+                    "tags": ["Assets"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        },
+                        {}
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Asset id from asset_id in a generation response"
+                        },
+                        {
+                            "name": "Range",
+                            "in": "header",
+                            "required": false,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Single byte range, e.g. bytes=0-1023",
+                            "example": "bytes=0-1023"
+                        },
+                        {
+                            "name": "exp",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "Expiry of a signed URL, in Unix seconds"
+                        },
+                        {
+                            "name": "sig",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Signature of a signed URL"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Full asset body",
+                            "content": {
+                                "image/png": {
+                                    "schema": {
+                                        "type": "string",
+                                        "format": "binary"
+                                    }
+                                },
+                                "video/mp4": {
+                                    "schema": {
+                                        "type": "string",
+                                        "format": "binary"
+                                    }
+                                }
+                            }
+                        },
+                        "206": {
+                            "description": "Requested byte range, described by Content-Range",
+                            "content": {
+                                "video/mp4": {
+                                    "schema": {
+                                        "type": "string",
+                                        "format": "binary"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Signed URL is invalid (invalid_asset_signature) or has expired (asset_url_expired)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "Asset not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "416": {
+                            "description": "Range not satisfiable",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/webhook_secret": {
+                "get": {
+                    "summary": "Get Webhook Secret",
+                    "description": "Returns the secret used to sign video job callbacks, creating one on first use. Callbacks carry X-GeminiPocket-Timestamp and X-GeminiPocket-Signature: sha256=<hex HMAC-SHA256 of \"{timestamp}.{body}\">",
+                    "operationId": "getWebhookSecret",
+                    "tags": ["Webhooks"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Webhook secret retrieved successfully",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/WebhookSecretResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "post": {
+                    "summary": "Rotate Webhook Secret",
+                    "description": "Replaces the secret used to sign video job callbacks. Pending retries are signed with the new secret",
+                    "operationId": "rotateWebhookSecret",
+                    "tags": ["Webhooks"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Webhook secret rotated successfully",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/WebhookSecretResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/keys": {
+                "get": {
+                    "summary": "List API Keys",
+                    "description": "Lists the caller's API keys, newest first. Only the prefix of each key is shown",
+                    "operationId": "listApiKeys",
+                    "tags": ["API Keys"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "API keys listed successfully",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ApiKeyListResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "post": {
+                    "summary": "Create API Key",
+                    "description": "Issues an additional named API key. The plaintext key is only returned in this response",
+                    "operationId": "createApiKey",
+                    "tags": ["API Keys"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/CreateKeyRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "201": {
+                            "description": "API key created",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/CreatedKeyResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Bad request - Missing or invalid name",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/keys/{id}": {
+                "delete": {
+                    "summary": "Revoke API Key",
+                    "description": "Revokes one of the caller's API keys. Revoked keys stay listed but are rejected immediately",
+                    "operationId": "revokeApiKey",
+                    "tags": ["API Keys"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Key id from GET /keys"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "API key revoked",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/RevokeKeyResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "Not found - No active key with this id",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/password/forgot": {
+                "post": {
+                    "summary": "Request Password Reset",
+                    "description": "Emails a single-use reset token valid for 60 minutes. The response is identical whether or not the email has an account",
+                    "operationId": "forgotPassword",
+                    "tags": ["Authentication"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/ForgotPasswordRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Reset requested",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/MessageResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Bad request - Invalid JSON",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "503": {
+                            "description": "mail_not_configured - the server has no mail sender set up",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/password/reset": {
+                "post": {
+                    "summary": "Reset Password",
+                    "description": "Sets a new password using a token from /password/forgot. Each token works once. Ends every session and revokes every API key of the account",
+                    "operationId": "resetPassword",
+                    "tags": ["Authentication"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/ResetPasswordRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Password updated",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "allOf": [
+                                            { "$ref": "#/components/schemas/MessageResponse" },
+                                            {
+                                                "type": "object",
+                                                "required": ["revoked_keys"],
+                                                "properties": {
+                                                    "revoked_keys": {
+                                                        "type": "integer",
+                                                        "description": "How many API keys the reset revoked"
+                                                    }
+                                                }
+                                            }
+                                        ]
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Bad request - invalid_reset_token when the token is unknown, expired or used",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/token/refresh": {
+                "post": {
+                    "summary": "Refresh Session",
+                    "description": "Trades a refresh token for a new session token. The refresh token is rotated and the old one stops working",
+                    "operationId": "refreshSession",
+                    "tags": ["Authentication"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/RefreshRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Session refreshed",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/SessionResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "invalid_refresh_token - Unknown, expired, used or revoked refresh token",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/logout": {
+                "post": {
+                    "summary": "Log Out",
+                    "description": "Ends the session the calling session token belongs to, or every session of the user with all=true. API keys are revoked with DELETE /keys/{id}",
+                    "operationId": "logout",
+                    "tags": ["Authentication"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "requestBody": {
+                        "required": false,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/LogoutRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Session ended",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/SuccessResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Bad request - Authorization is not a session token",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid session token",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/me": {
+                "get": {
+                    "summary": "Get Account",
+                    "description": "Returns the caller's account, the rate limits that apply to it and usage totals for the last 30 days",
+                    "operationId": "getAccount",
+                    "tags": ["Account"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Account retrieved successfully",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/AccountResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "patch": {
+                    "summary": "Update Account",
+                    "description": "Changes the email, the password or both. current_password is re-verified, and wrong guesses count towards the login lockout. A new password ends every session; API keys keep working",
+                    "operationId": "updateAccount",
+                    "tags": ["Account"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/UpdateAccountRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Account updated",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/UpdateAccountResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "validation_failed, or nothing to change",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "invalid_password: current password is incorrect",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "409": {
+                            "description": "email_taken: another account already uses that email",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "423": {
+                            "description": "account_locked: too many wrong passwords",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "429": {
+                            "description": "login_throttled: backing off after wrong passwords",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "delete": {
+                    "summary": "Delete Account",
+                    "description": "Deletes the account after checking the password, together with its API keys, sessions, history, video jobs and stored assets",
+                    "operationId": "deleteAccount",
+                    "tags": ["Account"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/DeleteAccountRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Account deleted",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/DeleteAccountResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "invalid_password: password is incorrect",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "423": {
+                            "description": "account_locked: too many wrong passwords",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "429": {
+                            "description": "login_throttled: backing off after wrong passwords",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users": {
+                "get": {
+                    "summary": "List Users",
+                    "description": "All users, newest first. Admin only",
+                    "operationId": "adminListUsers",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "q",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "Case-insensitive substring of the email"
+                        },
+                        {
+                            "name": "disabled",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "boolean"
+                            },
+                            "description": "Only disabled (true) or only enabled (false) users"
+                        },
+                        {
+                            "name": "limit",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "maximum": 200,
+                                "default": 50
+                            }
+                        },
+                        {
+                            "name": "cursor",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "string"
+                            },
+                            "description": "next_cursor from the previous page"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Users",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/AdminUserList"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Invalid query parameter",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users/{id}": {
+                "get": {
+                    "summary": "Get User",
+                    "description": "One user with their rate limits, API keys and usage totals for the last 30 days. Admin only",
+                    "operationId": "adminGetUser",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "User id"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "User detail",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/AdminUserDetail"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "User not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users/{id}/disable": {
+                "post": {
+                    "summary": "Disable User",
+                    "description": "Rejects the user's API keys and session tokens with account_disabled, refuses their logins and ends their sessions. Admins cannot disable themselves",
+                    "operationId": "adminDisableUser",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "User id"
+                        }
+                    ],
+                    "requestBody": {
+                        "required": false,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/DisableUserRequest"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "User disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/AdminUserResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Invalid reason, or the caller's own account",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "User not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users/{id}/enable": {
+                "post": {
+                    "summary": "Enable User",
+                    "description": "Lifts a disable. Keys that were not revoked work again",
+                    "operationId": "adminEnableUser",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "User id"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "User enabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/AdminUserResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "User not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users/{id}/reset_quota": {
+                "post": {
+                    "summary": "Reset Quota",
+                    "description": "Clears the user's rate limit counters so every window starts from zero",
+                    "operationId": "adminResetQuota",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "User id"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Counters cleared",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "success": {
+                                                "type": "boolean"
+                                            },
+                                            "user_id": {
+                                                "type": "integer"
+                                            },
+                                            "cleared": {
+                                                "type": "integer",
+                                                "description": "Counter rows removed"
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "User not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users/{id}/keys": {
+                "delete": {
+                    "summary": "Revoke User Keys",
+                    "description": "Revokes every active API key of the user",
+                    "operationId": "adminRevokeUserKeys",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "User id"
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Keys revoked",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/RevokeKeysResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "User not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users/{id}/keys/{key_id}": {
+                "delete": {
+                    "summary": "Revoke User Key",
+                    "description": "Revokes one API key of the user",
+                    "operationId": "adminRevokeUserKey",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "User id"
+                        },
+                        {
+                            "name": "key_id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "string"
+                            }
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Key revoked",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/RevokeKeysResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "User or active key not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/admin/users/{id}/usage": {
+                "get": {
+                    "summary": "User Usage",
+                    "description": "The user's per-day, per-kind totals, as /usage returns them for the caller",
+                    "operationId": "adminUserUsage",
+                    "tags": ["Admin"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {
+                                "type": "integer"
+                            },
+                            "description": "User id"
+                        },
+                        {
+                            "name": "days",
+                            "in": "query",
+                            "required": false,
+                            "schema": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "maximum": 365,
+                                "default": 30
+                            }
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Usage",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/UsageResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "403": {
+                            "description": "Not an admin, or the caller's account is disabled",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Invalid days parameter",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "User not found",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/models": {
+                "get": {
+                    "summary": "List Models",
+                    "description": "The models each request kind accepts, configured per deployment. Requests naming any other model fail with 400 model_not_allowed",
+                    "operationId": "listModels",
+                    "tags": ["System"],
+                    "responses": {
+                        "200": {
+                            "description": "Allowed models per kind",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ModelsResponse"
+                                    },
+                                    "example": {
+                                        "success": true,
+                                        "models": {
+                                            "generate": {
+                                                "default": "gemini-2.5-flash-preview",
+                                                "allowed": ["gemini-2.5-flash-preview"]
+                                            },
+                                            "edit": {
+                                                "default": "gemini-2.5-flash-image-preview",
+                                                "allowed": ["gemini-2.5-flash-image-preview"]
+                                            },
+                                            "generate_video": {
+                                                "default": "veo-3.0-fast-generate-001",
+                                                "allowed": ["veo-3.0-fast-generate-001", "veo-3.0-generate-001"]
+                                            },
+                                            "edit_video": {
+                                                "default": "veo-3.0-fast-generate-001",
+                                                "allowed": ["veo-3.0-fast-generate-001", "veo-3.0-generate-001"]
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/sessions": {
+                "post": {
+                    "summary": "Start an Edit Session",
+                    "description": "Starts a multi-turn edit session, optionally from one to three images. Turns are sent to POST /sessions/{id}/turns. Counts against the image rate limit. Sessions unused for 7 days are deleted with their uploaded images. Takes JSON or multipart/form-data with the images as image file parts",
+                    "operationId": "createSession",
+                    "tags": ["Edit Sessions"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "requestBody": {
+                        "required": false,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/CreateSessionRequest"
+                                }
+                            },
+                            "multipart/form-data": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/CreateSessionForm"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "201": {
+                            "description": "Session created",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/SessionResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Invalid request body or model_not_allowed",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "411": {
+                            "description": "A multipart body was sent without a Content-Length header (length_required)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "413": {
+                            "description": "The images exceed 15 MiB (payload_too_large)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "415": {
+                            "description": "An image is not a supported format (unsupported_image_type)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "429": {
+                            "description": "Image rate limit exceeded",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/sessions/{id}/turns": {
+                "post": {
+                    "summary": "Send the Next Turn of an Edit Session",
+                    "description": "Sends an instruction together with the session's earlier prompts, images and replies, and stores the result as the next turn. Answers like /edit, plus session_id and turn",
+                    "operationId": "sessionTurn",
+                    "tags": ["Edit Sessions"],
+                    "security": [
+                        {
+                            "bearerAuth": []
+                        }
+                    ],
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "description": "session_id from POST /sessions",
+                            "schema": {
+                                "type": "string"
+                            }
+                        }
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/SessionTurnRequest"
+                                },
+                                "example": {
+                                    "prompt": "Now make the sky purple"
+                                }
+                            },
+                            "multipart/form-data": {
+                                "schema": {
+                                    "$ref": "#/components/schemas/EditImageForm"
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Turn completed",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/SessionTurnResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Invalid request body",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "401": {
+                            "description": "Unauthorized - Invalid or missing API key",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "404": {
+                            "description": "No such session for this user",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "409": {
+                            "description": "The session already has 20 turns (session_full), or its previous turn is still running (turn_in_progress)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "411": {
+                            "description": "A multipart body was sent without a Content-Length header (length_required)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "413": {
+                            "description": "The session's images with the new ones exceed 15 MiB (payload_too_large)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "422": {
+                            "description": "Blocked by the model's safety filters (content_blocked). The turn is not added to the session",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "429": {
+                            "description": "Rate limit exceeded. See Retry-After and X-RateLimit-* headers",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        },
+                        "502": {
+                            "description": "Upstream Gemini API error",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ErrorResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+         "components": {
+             "securitySchemes": {
+                 "bearerAuth": {
+                     "type": "http",
+                     "scheme": "bearer",
+                     "description": "API key obtained from registration, login or POST /keys, or a gps_ session token from login with session=true"
+                 }
+             },
+             "schemas": {
+                "ApiInfo": {
+                    "type": "object",
+                    "required": ["name", "version", "endpoints"],
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "description": "Name of the API"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "Version of the API"
+                        },
+                         "endpoints": {
+                             "type": "object",
+                             "description": "Available endpoints",
+                             "properties": {
+                                 "generate": {
+                                     "type": "string",
+                                     "description": "Text-to-image generation endpoint"
+                                 },
+                                 "edit": {
+                                     "type": "string",
+                                     "description": "Image editing endpoint"
+                                 },
+                                 "generate_video": {
+                                     "type": "string",
+                                     "description": "Text-to-video generation endpoint"
+                                 },
+                                 "edit_video": {
+                                     "type": "string",
+                                     "description": "Video editing with image input endpoint"
+                                 },
+                                 "video_status": {
+                                     "type": "string",
+                                     "description": "Video generation status check endpoint"
+                                 },
+                                 "health": {
+                                     "type": "string",
+                                     "description": "Health check endpoint"
+                                 }
+                             }
+                         }
+                    }
+                },
+                 "HealthStatus": {
+                     "type": "object",
+                     "required": ["status"],
+                     "properties": {
+                         "status": {
+                             "type": "string",
+                             "enum": ["healthy", "degraded", "unhealthy"],
+                             "description": "Health status of the service. degraded means a check in checks failed"
+                         },
+                         "checks": {
+                             "type": "object",
+                             "description": "Configuration checks",
+                             "properties": {
+                                 "asset_url_signing": {
+                                     "type": "boolean",
+                                     "description": "SESSION_SECRET is set, so asset_url and video_url are signed and work without an Authorization header"
+                                 }
+                             }
+                         }
+                     }
+                 },
+                "GenerateImageRequest": {
+                    "type": "object",
+                    "required": ["prompt"],
+                    "properties": {
+                        "prompt": {
+                            "type": "string",
+                            "description": "Text prompt describing the image to generate",
+                            "minLength": 1,
+                            "maxLength": 10000,
+                            "example": "A beautiful sunset over mountains"
+                        },
+                        "model": {
+                            "type": "string",
+                            "description": "Model to use; one of the allowed models listed by GET /models. Defaults to the first of them",
+                            "example": "gemini-2.5-flash-preview"
+                        },
+                        "aspect_ratio": {
+                            "type": "string",
+                            "description": "Aspect ratio of the output image. The model's default is 1:1",
+                            "enum": ["1:1", "2:3", "3:2", "3:4", "4:3", "4:5", "5:4", "9:16", "16:9", "21:9"],
+                            "example": "16:9"
+                        },
+                        "seed": {
+                            "type": "integer",
+                            "description": "Seed for more repeatable results",
+                            "minimum": 0,
+                            "maximum": 2147483647,
+                            "example": 42
+                        },
+                        "temperature": {
+                            "type": "number",
+                            "description": "Sampling temperature; lower is more predictable",
+                            "minimum": 0,
+                            "maximum": 2,
+                            "example": 0.7
+                        },
+                        "response_modalities": {
+                            "type": "array",
+                            "description": "What the model may return. Case-insensitive; must include IMAGE",
+                            "items": {
+                                "type": "string",
+                                "enum": ["TEXT", "IMAGE"]
+                            },
+                            "example": ["IMAGE"]
+                        },
+                        "inline": {
+                            "type": "boolean",
+                            "description": "Also return the image as base64 in the response",
+                            "default": false
+                        },
+                        "count": {
+                            "type": "integer",
+                            "description": "Number of images to generate. Each counts against the image rate limit. The model may return fewer",
+                            "minimum": 1,
+                            "maximum": 4,
+                            "default": 1
+                        }
+                    }
+                },
+                "EditImageRequest": {
+                    "type": "object",
+                    "description": "Send either image (with mime_type) for a single input, or images for several. The decoded inputs may total at most 15 MiB",
+                    "required": ["prompt"],
+                    "properties": {
+                        "prompt": {
+                            "type": "string",
+                            "description": "Text prompt describing how to edit the image",
+                            "minLength": 1,
                             "maxLength": 10000,
                             "example": "Add a rainbow to the sky"
                         },
-                        "image": {
+                        "model": {
+                            "type": "string",
+                            "description": "Model to use; one of the allowed models listed by GET /models. Defaults to the first of them",
+                            "example": "gemini-2.5-flash-image-preview"
+                        },
+                        "aspect_ratio": {
+                            "type": "string",
+                            "description": "Aspect ratio of the output image. The model's default is 1:1",
+                            "enum": ["1:1", "2:3", "3:2", "3:4", "4:3", "4:5", "5:4", "9:16", "16:9", "21:9"],
+                            "example": "16:9"
+                        },
+                        "seed": {
+                            "type": "integer",
+                            "description": "Seed for more repeatable results",
+                            "minimum": 0,
+                            "maximum": 2147483647,
+                            "example": 42
+                        },
+                        "temperature": {
+                            "type": "number",
+                            "description": "Sampling temperature; lower is more predictable",
+                            "minimum": 0,
+                            "maximum": 2,
+                            "example": 0.7
+                        },
+                        "response_modalities": {
+                            "type": "array",
+                            "description": "What the model may return. Case-insensitive; must include IMAGE",
+                            "items": {
+                                "type": "string",
+                                "enum": ["TEXT", "IMAGE"]
+                            },
+                            "example": ["IMAGE"]
+                        },
+                        "image": {
+                            "type": "string",
+                            "description": "Base64-encoded image data, for a single input image",
+                            "format": "byte",
+                            "example": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="
+                        },
+                        "mime_type": {
+                            "type": "string",
+                            "description": "Accepted for compatibility and ignored: the server reads the type from the image data",
+                            "deprecated": true
+                        },
+                        "images": {
+                            "type": "array",
+                            "description": "Input images in the order the prompt refers to them (image 1, image 2, ...)",
+                            "minItems": 1,
+                            "maxItems": 3,
+                            "items": {
+                                "$ref": "#/components/schemas/EditImageInput"
+                            }
+                        },
+                        "inline": {
+                            "type": "boolean",
+                            "description": "Also return the image as base64 in the response",
+                            "default": false
+                        }
+                    }
+                },
+                "EditImageInput": {
+                    "type": "object",
+                    "required": ["data"],
+                    "properties": {
+                        "data": {
+                            "type": "string",
+                            "description": "Base64-encoded PNG, JPEG, WebP or GIF",
+                            "format": "byte"
+                        },
+                        "mime_type": {
+                            "type": "string",
+                            "description": "Accepted for compatibility and ignored: the server reads the type from the image data",
+                            "deprecated": true
+                        }
+                    }
+                },
+                "EditImageForm": {
+                    "type": "object",
+                    "description": "The fields of EditImageRequest as form parts, with the images as files",
+                    "required": ["prompt", "image"],
+                    "properties": {
+                        "prompt": { "type": "string" },
+                        "image": {
+                            "type": "array",
+                            "description": "One to three image files, in the order the prompt refers to them",
+                            "items": { "type": "string", "format": "binary" },
+                            "maxItems": 3
+                        },
+                        "model": { "type": "string" },
+                        "aspect_ratio": { "type": "string" },
+                        "seed": { "type": "integer" },
+                        "temperature": { "type": "number" },
+                        "response_modalities": {
+                            "type": "array",
+                            "description": "Repeated parts, or one comma-separated part",
+                            "items": { "type": "string" }
+                        },
+                        "inline": { "type": "boolean" }
+                    }
+                },
+                "EditVideoForm": {
+                    "type": "object",
+                    "description": "The fields of EditVideoRequest as form parts, with the image as a file",
+                    "required": ["prompt", "image"],
+                    "properties": {
+                        "prompt": { "type": "string" },
+                        "image": { "type": "string", "format": "binary" },
+                        "model": { "type": "string" },
+                        "negative_prompt": { "type": "string" },
+                        "aspect_ratio": { "type": "string" },
+                        "resolution": { "type": "string" },
+                        "callback_url": { "type": "string", "format": "uri" }
+                    }
+                },
+                 "ImageResponse": {
+                     "type": "object",
+                     "required": ["success", "asset_id", "asset_url", "mime_type"],
+                     "properties": {
+                         "success": {
+                             "type": "boolean",
+                             "description": "Whether the operation was successful"
+                         },
+                         "asset_id": {
+                             "type": "string",
+                             "description": "Id of the stored image"
+                         },
+                         "asset_url": {
+                             "type": "string",
+                             "format": "uri",
+                             "description": "URL to download the image from GET /assets/{id}"
+                         },
+                         "mime_type": {
+                             "type": "string",
+                             "example": "image/png"
+                         },
+                         "image": {
+                             "type": "string",
+                             "description": "Base64-encoded image, only present when the request set inline",
+                             "format": "byte"
+                         },
+                         "images": {
+                             "type": "array",
+                             "description": "Every image, in the order the model returned them. The fields above repeat the first",
+                             "items": {
+                                 "type": "object",
+                                 "properties": {
+                                     "asset_id": {"type": "string"},
+                                     "asset_url": {"type": "string", "format": "uri"},
+                                     "mime_type": {"type": "string"},
+                                     "image": {"type": "string", "format": "byte"}
+                                 }
+                             }
+                         },
+                         "text": {
+                             "type": "string",
+                             "description": "The model's text parts, one per line, when it sent any"
+                         }
+                     }
+                 },
+                "ErrorResponse": {
+                    "type": "object",
+                    "required": ["success", "error"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean",
+                            "description": "Always false for errors"
+                        },
+                        "error": {
+                            "type": "object",
+                            "required": ["code", "message", "retryable"],
+                            "properties": {
+                                "code": {
+                                    "type": "string",
+                                    "description": "Stable machine-readable error code",
+                                    "example": "invalid_api_key"
+                                },
+                                "message": {
+                                    "type": "string",
+                                    "description": "Human-readable error message"
+                                },
+                                "retryable": {
+                                    "type": "boolean",
+                                    "description": "Whether retrying the same request may succeed"
+                                },
+                                "details": {
+                                    "type": "object",
+                                    "description": "Optional code-specific details"
+                                }
+                            }
+                        }
+                    }
+                },
+                "RegisterRequest": {
+                    "type": "object",
+                    "required": ["email", "password"],
+                    "properties": {
+                        "email": {
+                            "type": "string",
+                            "format": "email",
+                            "maxLength": 254,
+                            "description": "User's email address. Stored trimmed and lowercased",
+                            "example": "user@example.com"
+                        },
+                        "password": {
+                            "type": "string",
+                            "description": "User's password: at least 8 characters, at most 72 bytes, with a letter and a digit, and not containing the email",
+                            "minLength": 8,
+                            "example": "securepassword123"
+                        },
+                        "session": {
+                            "type": "boolean",
+                            "description": "Return a session token and refresh token instead of issuing an API key",
+                            "default": false
+                        }
+                    }
+                },
+                "LoginRequest": {
+                    "type": "object",
+                    "required": ["email", "password"],
+                    "properties": {
+                        "email": {
+                            "type": "string",
+                            "format": "email",
+                            "description": "User's email address",
+                            "example": "user@example.com"
+                        },
+                        "password": {
+                            "type": "string",
+                            "description": "User's password",
+                            "example": "securepassword123"
+                        },
+                        "key_name": {
+                            "type": "string",
+                            "description": "Name for the API key this login issues",
+                            "default": "Login",
+                            "example": "laptop"
+                        },
+                        "session": {
+                            "type": "boolean",
+                            "description": "Return a session token and refresh token instead of issuing an API key",
+                            "default": false
+                        },
+                        "replace": {
+                            "type": "boolean",
+                            "description": "Also revoke the caller's other active keys with the same key_name",
+                            "default": false
+                        }
+                    }
+                },
+                 "AuthResponse": {
+                     "type": "object",
+                     "required": ["success"],
+                     "properties": {
+                         "success": {
+                             "type": "boolean",
+                             "description": "Whether the authentication was successful"
+                         },
+                         "api_key": {
+                             "type": "string",
+                             "description": "Newly issued API key for authenticated requests. It is only returned once",
+                             "example": "gp_1234567890_abcdef"
+                         },
+                         "key_id": {
+                             "type": "string",
+                             "description": "Id of the issued key, for use with DELETE /keys/{id}"
+                         },
+                         "error": {
+                             "type": "string",
+                             "description": "Error message if authentication failed"
+                         }
+                     }
+                 },
+                 "GenerateVideoRequest": {
+                     "type": "object",
+                     "required": ["prompt"],
+                     "properties": {
+                         "prompt": {
+                             "type": "string",
+                             "description": "Text prompt describing the video to generate",
+                             "minLength": 1,
+                             "maxLength": 10000,
+                             "example": "A cat playing with a ball of yarn in a sunny garden"
+                         },
+                         "model": {
+                             "type": "string",
+                             "description": "Model to use; one of the allowed models listed by GET /models. Defaults to the first of them",
+                             "example": "veo-3.0-fast-generate-001"
+                         },
+                         "negative_prompt": {
+                             "type": "string",
+                             "description": "Text prompt describing what to avoid in the video",
+                             "example": "dark, stormy, rain"
+                         },
+                         "aspect_ratio": {
+                             "type": "string",
+                             "description": "Aspect ratio of the generated video",
+                             "enum": ["16:9", "9:16", "1:1"],
+                             "example": "16:9"
+                         },
+                         "resolution": {
+                             "type": "string",
+                             "description": "Resolution of the generated video",
+                             "enum": ["1280x720", "720x1280", "1024x1024"],
+                             "example": "1280x720"
+                         },
+                         "callback_url": {
+                             "type": "string",
+                             "format": "uri",
+                             "description": "HTTPS URL that receives a signed POST, sent by the next scheduled run, when the job succeeds or fails",
+                             "example": "https://example.com/hooks/geminipocket"
+                         }
+                     }
+                 },
+                 "EditVideoRequest": {
+                     "type": "object",
+                     "required": ["prompt", "image"],
+                     "properties": {
+                         "prompt": {
+                             "type": "string",
+                             "description": "Text prompt describing how to edit the video",
+                             "minLength": 1,
+                             "maxLength": 10000,
+                             "example": "A butterfly emerging from a cocoon and flying away"
+                         },
+                         "model": {
+                             "type": "string",
+                             "description": "Model to use; one of the allowed models listed by GET /models. Defaults to the first of them",
+                             "example": "veo-3.0-fast-generate-001"
+                         },
+                         "image": {
+                             "type": "string",
+                             "description": "Base64-encoded PNG, JPEG or WebP to use as the first frame",
+                             "format": "byte",
+                             "example": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="
+                         },
+                         "mime_type": {
+                             "type": "string",
+                             "description": "Accepted for compatibility and ignored: the server reads the type from the image data",
+                             "deprecated": true
+                         },
+                         "negative_prompt": {
+                             "type": "string",
+                             "description": "Text prompt describing what to avoid in the video",
+                             "example": "ground, trees, buildings"
+                         },
+                         "aspect_ratio": {
+                             "type": "string",
+                             "description": "Aspect ratio of the generated video",
+                             "enum": ["16:9", "9:16", "1:1"],
+                             "example": "9:16"
+                         },
+                         "resolution": {
+                             "type": "string",
+                             "description": "Resolution of the generated video",
+                             "enum": ["1280x720", "720x1280", "1024x1024"],
+                             "example": "720x1280"
+                         },
+                         "callback_url": {
+                             "type": "string",
+                             "format": "uri",
+                             "description": "HTTPS URL that receives a signed POST, sent by the next scheduled run, when the job succeeds or fails",
+                             "example": "https://example.com/hooks/geminipocket"
+                         }
+                     }
+                 },
+                 "VideoOperationResponse": {
+                     "type": "object",
+                     "required": ["success"],
+                     "properties": {
+                         "success": {
+                             "type": "boolean",
+                             "description": "Whether the video operation was started successfully"
+                         },
+                         "job_id": {
+                             "type": "string",
+                             "description": "The job id to use for checking status",
+                             "example": "3d9f1c2a-7b64-4e0f-a8d5-1c2b3e4f5a6b"
+                         },
+                         "error": {
+                             "type": "string",
+                             "description": "Error message if the operation failed to start"
+                         }
+                     }
+                 },
+                 "VideoStatusResponse": {
+                     "type": "object",
+                     "required": ["success", "done"],
+                     "properties": {
+                         "success": {
+                             "type": "boolean",
+                             "description": "Whether the status check was successful"
+                         },
+                         "job_id": {
+                             "type": "string",
+                             "description": "The job that was polled"
+                         },
+                         "done": {
+                             "type": "boolean",
+                             "description": "Whether the video generation is complete"
+                         },
+                         "asset_id": {
+                             "type": "string",
+                             "description": "Id of the stored video (only present when done is true)"
+                         },
+                         "video_url": {
+                             "type": "string",
+                             "description": "URL to download the video from GET /assets/{id} (only present when done is true)",
+                             "format": "uri"
+                         },
+                         "mime_type": {
+                             "type": "string",
+                             "example": "video/mp4"
+                         },
+                         "video": {
+                             "type": "string",
+                             "description": "data: URL of the video, only present with ?inline=true"
+                         },
+                         "error": {
+                             "type": "string",
+                             "description": "Error message if the operation failed"
+                         }
+                     }
+                 },
+                "UsageResponse": {
+                    "type": "object",
+                    "required": ["success", "days", "usage", "totals"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "days": {
+                            "type": "integer",
+                            "description": "Number of days covered"
+                        },
+                        "usage": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "day": {
+                                        "type": "string",
+                                        "format": "date"
+                                    },
+                                    "kind": {
+                                        "type": "string",
+                                        "enum": ["generate", "edit", "generate_video", "edit_video"]
+                                    },
+                                    "requests": {
+                                        "type": "integer"
+                                    },
+                                    "succeeded": {
+                                        "type": "integer"
+                                    },
+                                    "failed": {
+                                        "type": "integer"
+                                    },
+                                    "output_bytes": {
+                                        "type": "integer"
+                                    },
+                                    "latency_ms": {
+                                        "type": "integer",
+                                        "description": "Sum of request latency"
+                                    }
+                                }
+                            }
+                        },
+                        "totals": {
+                            "type": "object",
+                            "description": "Totals per kind over the whole period",
+                            "additionalProperties": {
+                                "type": "object"
+                            }
+                        }
+                    }
+                },
+                "HistoryItem": {
+                    "type": "object",
+                    "required": ["id", "kind", "model", "prompt", "status", "created_at"],
+                    "properties": {
+                        "id": {
+                            "type": "string"
+                        },
+                        "kind": {
+                            "type": "string",
+                            "enum": ["generate", "edit", "generate_video", "edit_video"]
+                        },
+                        "model": {
+                            "type": "string"
+                        },
+                        "prompt": {
+                            "type": "string"
+                        },
+                        "status": {
+                            "type": "string",
+                            "enum": ["pending", "succeeded", "failed"]
+                        },
+                        "error_code": {
+                            "type": "string",
+                            "nullable": true
+                        },
+                        "latency_ms": {
+                            "type": "integer",
+                            "nullable": true
+                        },
+                        "output_bytes": {
+                            "type": "integer",
+                            "nullable": true
+                        },
+                        "created_at": {
+                            "type": "string",
+                            "description": "UTC timestamp (YYYY-MM-DD HH:MM:SS)"
+                        }
+                    }
+                },
+                "HistoryPage": {
+                    "type": "object",
+                    "required": ["success", "items"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "items": {
+                            "type": "array",
+                            "items": {
+                                "$ref": "#/components/schemas/HistoryItem"
+                            }
+                        },
+                        "next_cursor": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "Pass as cursor to fetch the next page; null on the last page"
+                        }
+                    }
+                },
+                "HistoryDetailResponse": {
+                    "type": "object",
+                    "required": ["success", "generation"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "generation": {
+                            "allOf": [
+                                {
+                                    "$ref": "#/components/schemas/HistoryItem"
+                                },
+                                {
+                                    "type": "object",
+                                    "properties": {
+                                        "parameters": {
+                                            "type": "object",
+                                            "description": "Request parameters recorded with the generation"
+                                        },
+                                        "download_url": {
+                                            "type": "string",
+                                            "nullable": true,
+                                            "description": "Path to fetch the output again, when available. For several outputs, the first"
+                                        },
+                                        "assets": {
+                                            "type": "array",
+                                            "description": "Every stored output, in the order the model returned them",
+                                            "items": {
+                                                "type": "object",
+                                                "required": ["asset_id", "asset_url", "mime_type", "size"],
+                                                "properties": {
+                                                    "asset_id": {
+                                                        "type": "string"
+                                                    },
+                                                    "asset_url": {
+                                                        "type": "string",
+                                                        "format": "uri"
+                                                    },
+                                                    "mime_type": {
+                                                        "type": "string",
+                                                        "example": "image/png"
+                                                    },
+                                                    "size": {
+                                                        "type": "integer",
+                                                        "description": "Bytes"
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            ]
+                        }
+                    }
+                },
+                "WebhookSecretResponse": {
+                    "type": "object",
+                    "required": ["success", "webhook_secret"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "webhook_secret": {
+                            "type": "string",
+                            "example": "whsec_8f14e45fceea167a5a36dedd4bea2543c9f0f895fb98ab9159f51fd0297e236d"
+                        }
+                    }
+                },
+                "WebhookPayload": {
+                    "type": "object",
+                    "description": "Body POSTed to callback_url when a video job finishes. Failed deliveries are retried with backoff for about eight hours",
+                    "required": ["event", "job_id", "status"],
+                    "properties": {
+                        "event": {
+                            "type": "string",
+                            "enum": ["video_job.succeeded", "video_job.failed"]
+                        },
+                        "job_id": {
+                            "type": "string"
+                        },
+                        "status": {
+                            "type": "string",
+                            "enum": ["succeeded", "failed"]
+                        },
+                        "error_code": {
+                            "type": "string",
+                            "nullable": true
+                        },
+                        "asset_id": {
+                            "type": "string",
+                            "nullable": true
+                        },
+                        "video_url": {
+                            "type": "string",
+                            "format": "uri",
+                            "nullable": true
+                        },
+                        "mime_type": {
+                            "type": "string",
+                            "nullable": true
+                        }
+                    }
+                },
+                "CreateKeyRequest": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "maxLength": 100,
+                            "example": "ci"
+                        }
+                    }
+                },
+                "ApiKey": {
+                    "type": "object",
+                    "required": ["id", "name", "prefix", "created_at", "revoked"],
+                    "properties": {
+                        "id": {
+                            "type": "string"
+                        },
+                        "name": {
+                            "type": "string",
+                            "example": "ci"
+                        },
+                        "prefix": {
+                            "type": "string",
+                            "description": "First characters of the key, for recognising it",
+                            "example": "gp_1a2b3c4d"
+                        },
+                        "created_at": {
+                            "type": "string"
+                        },
+                        "last_used_at": {
+                            "type": "string",
+                            "nullable": true
+                        },
+                        "revoked": {
+                            "type": "boolean"
+                        }
+                    }
+                },
+                "ApiKeyListResponse": {
+                    "type": "object",
+                    "required": ["success", "keys"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "keys": {
+                            "type": "array",
+                            "items": {
+                                "$ref": "#/components/schemas/ApiKey"
+                            }
+                        }
+                    }
+                },
+                "CreatedKeyResponse": {
+                    "type": "object",
+                    "required": ["success", "key", "api_key"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "key": {
+                            "$ref": "#/components/schemas/ApiKey"
+                        },
+                        "api_key": {
+                            "type": "string",
+                            "description": "The full key. It cannot be retrieved again",
+                            "example": "gp_1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d"
+                        }
+                    }
+                },
+                "RevokeKeyResponse": {
+                    "type": "object",
+                    "required": ["success", "id", "revoked"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "id": {
+                            "type": "string"
+                        },
+                        "revoked": {
+                            "type": "boolean"
+                        }
+                    }
+                },
+                "ForgotPasswordRequest": {
+                    "type": "object",
+                    "required": ["email"],
+                    "properties": {
+                        "email": {
+                            "type": "string",
+                            "format": "email",
+                            "example": "user@example.com"
+                        }
+                    }
+                },
+                "ResetPasswordRequest": {
+                    "type": "object",
+                    "required": ["token", "password"],
+                    "properties": {
+                        "token": {
+                            "type": "string",
+                            "description": "Token from the reset email"
+                        },
+                        "password": {
+                            "type": "string",
+                            "description": "New password",
+                            "example": "newsecurepassword456"
+                        }
+                    }
+                },
+                "MessageResponse": {
+                    "type": "object",
+                    "required": ["success", "message"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "message": {
+                            "type": "string"
+                        }
+                    }
+                },
+                "SessionResponse": {
+                    "type": "object",
+                    "required": ["success", "session_id", "session_token", "refresh_token", "expires_in"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "session_id": {
+                            "type": "string"
+                        },
+                        "session_token": {
+                            "type": "string",
+                            "description": "Bearer token accepted by every authenticated route. Expired tokens fail with session_expired",
+                            "example": "gps_eyJzaWQiOi...Ig.qL8..."
+                        },
+                        "refresh_token": {
+                            "type": "string",
+                            "description": "Single-use token for POST /token/refresh. Valid for 30 days",
+                            "example": "gpr_5f2b..."
+                        },
+                        "expires_in": {
+                            "type": "integer",
+                            "description": "Seconds until session_token expires",
+                            "example": 900
+                        }
+                    }
+                },
+                "RefreshRequest": {
+                    "type": "object",
+                    "required": ["refresh_token"],
+                    "properties": {
+                        "refresh_token": {
+                            "type": "string"
+                        }
+                    }
+                },
+                "LogoutRequest": {
+                    "type": "object",
+                    "properties": {
+                        "all": {
+                            "type": "boolean",
+                            "default": false,
+                            "description": "End every session of the user"
+                        }
+                    }
+                },
+                "SuccessResponse": {
+                    "type": "object",
+                    "required": ["success"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        }
+                    }
+                },
+                "AccountResponse": {
+                    "type": "object",
+                    "properties": {
+                        "success": {
+                            "type": "boolean",
+                            "example": true
+                        },
+                        "user": {
+                            "type": "object",
+                            "properties": {
+                                "id": {
+                                    "type": "integer"
+                                },
+                                "email": {
+                                    "type": "string",
+                                    "format": "email"
+                                },
+                                "created_at": {
+                                    "type": "string"
+                                },
+                                "updated_at": {
+                                    "type": "string",
+                                    "nullable": true
+                                },
+                                "plan": {
+                                    "type": "object",
+                                    "properties": {
+                                        "name": {
+                                            "type": "string",
+                                            "enum": ["default", "custom"],
+                                            "description": "custom when the account has per-user limit overrides"
+                                        },
+                                        "limits": {
+                                            "type": "object",
+                                            "description": "Limits by request kind and window",
+                                            "additionalProperties": {
+                                                "type": "object",
+                                                "additionalProperties": {
+                                                    "type": "integer"
+                                                }
+                                            },
+                                            "example": {
+                                                "image": {
+                                                    "per_minute": 10,
+                                                    "per_day": 200
+                                                },
+                                                "video": {
+                                                    "per_minute": 5,
+                                                    "per_day": 20
+                                                },
+                                                "status": {
+                                                    "per_minute": 30
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "usage": {
+                            "type": "object",
+                            "properties": {
+                                "days": {
+                                    "type": "integer",
+                                    "example": 30
+                                },
+                                "totals": {
+                                    "type": "object",
+                                    "description": "Totals by generation kind, as in /usage"
+                                }
+                            }
+                        }
+                    }
+                },
+                "UpdateAccountRequest": {
+                    "type": "object",
+                    "required": ["current_password"],
+                    "properties": {
+                        "current_password": {
+                            "type": "string",
+                            "format": "password"
+                        },
+                        "email": {
+                            "type": "string",
+                            "format": "email",
+                            "description": "New email address"
+                        },
+                        "new_password": {
+                            "type": "string",
+                            "format": "password",
+                            "description": "Same rules as registration"
+                        }
+                    }
+                },
+                "UpdateAccountResponse": {
+                    "type": "object",
+                    "properties": {
+                        "success": {
+                            "type": "boolean",
+                            "example": true
+                        },
+                        "email": {
+                            "type": "string",
+                            "format": "email"
+                        },
+                        "email_changed": {
+                            "type": "boolean"
+                        },
+                        "password_changed": {
+                            "type": "boolean"
+                        },
+                        "sessions_revoked": {
+                            "type": "boolean"
+                        }
+                    }
+                },
+                "DeleteAccountRequest": {
+                    "type": "object",
+                    "required": ["password"],
+                    "properties": {
+                        "password": {
+                            "type": "string",
+                            "format": "password"
+                        }
+                    }
+                },
+                "DeleteAccountResponse": {
+                    "type": "object",
+                    "properties": {
+                        "success": {
+                            "type": "boolean",
+                            "example": true
+                        },
+                        "deleted": {
+                            "type": "boolean",
+                            "example": true
+                        }
+                    }
+                },
+                "AdminUser": {
+                    "type": "object",
+                    "properties": {
+                        "id": {
+                            "type": "integer"
+                        },
+                        "email": {
+                            "type": "string",
+                            "format": "email"
+                        },
+                        "is_admin": {
+                            "type": "boolean"
+                        },
+                        "disabled_at": {
+                            "type": "string",
+                            "nullable": true
+                        },
+                        "disabled_reason": {
+                            "type": "string",
+                            "nullable": true
+                        },
+                        "created_at": {
+                            "type": "string"
+                        },
+                        "active_keys": {
+                            "type": "integer"
+                        }
+                    }
+                },
+                "AdminUserList": {
+                    "type": "object",
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "users": {
+                            "type": "array",
+                            "items": {
+                                "$ref": "#/components/schemas/AdminUser"
+                            }
+                        },
+                        "next_cursor": {
                             "type": "string",
-                            "description": "Base64-encoded image data",
-                            "format": "byte",
-                            "example": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="
+                            "nullable": true
+                        }
+                    }
+                },
+                "AdminUserDetail": {
+                    "type": "object",
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
                         },
-                        "mime_type": {
+                        "user": {
+                            "$ref": "#/components/schemas/AdminUser"
+                        },
+                        "plan": {
+                            "type": "object",
+                            "description": "As in GET /me"
+                        },
+                        "keys": {
+                            "type": "array",
+                            "items": {
+                                "$ref": "#/components/schemas/ApiKey"
+                            }
+                        },
+                        "usage": {
+                            "type": "object",
+                            "properties": {
+                                "days": {
+                                    "type": "integer"
+                                },
+                                "totals": {
+                                    "type": "object"
+                                }
+                            }
+                        }
+                    }
+                },
+                "AdminUserResponse": {
+                    "type": "object",
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "user": {
+                            "$ref": "#/components/schemas/AdminUser"
+                        }
+                    }
+                },
+                "DisableUserRequest": {
+                    "type": "object",
+                    "properties": {
+                        "reason": {
                             "type": "string",
-                            "description": "MIME type of the image (defaults to image/jpeg if not specified)",
-                            "enum": ["image/jpeg", "image/png", "image/gif", "image/webp"],
-                            "default": "image/jpeg",
-                            "example": "image/png"
+                            "maxLength": 500
                         }
                     }
                 },
-                 "ImageResponse": {
-                     "type": "object",
-                     "required": ["success", "image"],
-                     "properties": {
-                         "success": {
-                             "type": "boolean",
-                             "description": "Whether the operation was successful"
-                         },
-                         "image": {
-                             "type": "string",
-                             "description": "Base64-encoded generated or edited image",
-                             "format": "byte"
-                         }
-                     }
-                 },
-                "ErrorResponse": {
+                "RevokeKeysResponse": {
                     "type": "object",
-                    "required": ["error"],
                     "properties": {
-                        "error": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "user_id": {
+                            "type": "integer"
+                        },
+                        "revoked": {
+                            "type": "integer"
+                        }
+                    }
+                },
+                "ModelChoice": {
+                    "type": "object",
+                    "properties": {
+                        "default": {
                             "type": "string",
-                            "description": "Error message describing what went wrong"
+                            "description": "Used when a request names no model"
+                        },
+                        "allowed": {
+                            "type": "array",
+                            "items": {
+                                "type": "string"
+                            }
                         }
                     }
                 },
-                "RegisterRequest": {
+                "ModelsResponse": {
                     "type": "object",
-                    "required": ["email", "password"],
                     "properties": {
-                        "email": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "models": {
+                            "type": "object",
+                            "properties": {
+                                "generate": {
+                                    "$ref": "#/components/schemas/ModelChoice"
+                                },
+                                "edit": {
+                                    "$ref": "#/components/schemas/ModelChoice"
+                                },
+                                "generate_video": {
+                                    "$ref": "#/components/schemas/ModelChoice"
+                                },
+                                "edit_video": {
+                                    "$ref": "#/components/schemas/ModelChoice"
+                                }
+                            }
+                        }
+                    }
+                },
+                "CreateSessionRequest": {
+                    "type": "object",
+                    "properties": {
+                        "model": {
                             "type": "string",
-                            "format": "email",
-                            "description": "User's email address",
-                            "example": "user@example.com"
+                            "description": "Model for every turn; one of the allowed edit models listed by GET /models"
                         },
-                        "password": {
+                        "image": {
                             "type": "string",
-                            "description": "User's password",
-                            "minLength": 6,
-                            "example": "securepassword123"
+                            "description": "Base64-encoded image to start from",
+                            "format": "byte"
+                        },
+                        "images": {
+                            "type": "array",
+                            "description": "Images to start from, in the order the first prompt refers to them",
+                            "maxItems": 3,
+                            "items": {
+                                "$ref": "#/components/schemas/EditImageInput"
+                            }
                         }
                     }
                 },
-                "LoginRequest": {
+                "CreateSessionForm": {
                     "type": "object",
-                    "required": ["email", "password"],
                     "properties": {
-                        "email": {
+                        "model": {
+                            "type": "string"
+                        },
+                        "image": {
+                            "type": "array",
+                            "items": {
+                                "type": "string",
+                                "format": "binary"
+                            },
+                            "maxItems": 3
+                        }
+                    }
+                },
+                "SessionResponse": {
+                    "type": "object",
+                    "required": ["success", "session_id", "model", "max_turns"],
+                    "properties": {
+                        "success": {
+                            "type": "boolean"
+                        },
+                        "session_id": {
+                            "type": "string"
+                        },
+                        "model": {
+                            "type": "string"
+                        },
+                        "image_asset_ids": {
+                            "type": "array",
+                            "description": "The starting images, stored as assets",
+                            "items": {
+                                "type": "string"
+                            }
+                        },
+                        "max_turns": {
+                            "type": "integer",
+                            "example": 20
+                        }
+                    }
+                },
+                "SessionTurnRequest": {
+                    "type": "object",
+                    "description": "The fields of EditImageRequest except model; images are optional",
+                    "required": ["prompt"],
+                    "properties": {
+                        "prompt": {
                             "type": "string",
-                            "format": "email",
-                            "description": "User's email address",
-                            "example": "user@example.com"
+                            "minLength": 1
                         },
-                        "password": {
+                        "image": {
                             "type": "string",
-                            "description": "User's password",
-                            "example": "securepassword123"
+                            "format": "byte"
+                        },
+                        "images": {
+                            "type": "array",
+                            "maxItems": 3,
+                            "items": {
+                                "$ref": "#/components/schemas/EditImageInput"
+                            }
+                        },
+                        "aspect_ratio": {
+                            "type": "string"
+                        },
+                        "seed": {
+                            "type": "integer"
+                        },
+                        "temperature": {
+                            "type": "number"
+                        },
+                        "response_modalities": {
+                            "type": "array",
+                            "items": {
+                                "type": "string"
+                            }
+                        },
+                        "inline": {
+                            "type": "boolean",
+                            "default": false
                         }
                     }
                 },
-                 "AuthResponse": {
-                     "type": "object",
-                     "required": ["success"],
-                     "properties": {
-                         "success": {
-                             "type": "boolean",
-                             "description": "Whether the authentication was successful"
-                         },
-                         "api_key": {
-                             "type": "string",
-                             "description": "API key for authenticated requests",
-                             "example": "gp_1234567890_abcdef"
-                         },
-                         "error": {
-                             "type": "string",
-                             "description": "Error message if authentication failed"
-                         }
-                     }
-                 },
-                 "GenerateVideoRequest": {
-                     "type": "object",
-                     "required": ["prompt"],
-                     "properties": {
-                         "prompt": {
-                             "type": "string",
-                             "description": "Text prompt describing the video to generate",
-                             "minLength": 1,
-                             "maxLength": 10000,
-                             "example": "A cat playing with a ball of yarn in a sunny garden"
-                         },
-                         "negative_prompt": {
-                             "type": "string",
-                             "description": "Text prompt describing what to avoid in the video",
-                             "example": "dark, stormy, rain"
-                         },
-                         "aspect_ratio": {
-                             "type": "string",
-                             "description": "Aspect ratio of the generated video",
-                             "enum": ["16:9", "9:16", "1:1"],
-                             "example": "16:9"
-                         },
-                         "resolution": {
-                             "type": "string",
-                             "description": "Resolution of the generated video",
-                             "enum": ["1280x720", "720x1280", "1024x1024"],
-                             "example": "1280x720"
-                         }
-                     }
-                 },
-                 "EditVideoRequest": {
-                     "type": "object",
-                     "required": ["prompt", "image", "mime_type"],
-                     "properties": {
-                         "prompt": {
-                             "type": "string",
-                             "description": "Text prompt describing how to edit the video",
-                             "minLength": 1,
-                             "maxLength": 10000,
-                             "example": "A butterfly emerging from a cocoon and flying away"
-                         },
-                         "image": {
-                             "type": "string",
-                             "description": "Base64-encoded image data to use as input for video editing",
-                             "format": "byte",
-                             "example": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="
-                         },
-                         "mime_type": {
-                             "type": "string",
-                             "description": "MIME type of the input image",
-                             "enum": ["image/jpeg", "image/png", "image/gif", "image/webp"],
-                             "example": "image/png"
-                         },
-                         "negative_prompt": {
-                             "type": "string",
-                             "description": "Text prompt describing what to avoid in the video",
-                             "example": "ground, trees, buildings"
-                         },
-                         "aspect_ratio": {
-                             "type": "string",
-                             "description": "Aspect ratio of the generated video",
-                             "enum": ["16:9", "9:16", "1:1"],
-                             "example": "9:16"
-                         },
-                         "resolution": {
-                             "type": "string",
-                             "description": "Resolution of the generated video",
-                             "enum": ["1280x720", "720x1280", "1024x1024"],
-                             "example": "720x1280"
-                         }
-                     }
-                 },
-                 "VideoOperationResponse": {
-                     "type": "object",
-                     "required": ["success"],
-                     "properties": {
-                         "success": {
-                             "type": "boolean",
-                             "description": "Whether the video operation was started successfully"
-                         },
-                         "operation_name": {
-                             "type": "string",
-                             "description": "The operation name to use for checking status",
-                             "example": "projects/project-id/locations/us-central1/operations/operation-id"
-                         },
-                         "error": {
-                             "type": "string",
-                             "description": "Error message if the operation failed to start"
-                         }
-                     }
-                 },
-                 "VideoStatusResponse": {
-                     "type": "object",
-                     "required": ["success", "done"],
-                     "properties": {
-                         "success": {
-                             "type": "boolean",
-                             "description": "Whether the status check was successful"
-                         },
-                         "done": {
-                             "type": "boolean",
-                             "description": "Whether the video generation is complete"
-                         },
-                         "video_uri": {
-                             "type": "string",
-                             "description": "URI of the generated video (only present when done is true)",
-                             "format": "uri",
-                             "example": "https://storage.googleapis.com/generated-videos/video.mp4"
-                         },
-                         "error": {
-                             "type": "string",
-                             "description": "Error message if the operation failed"
-                         }
-                     }
-                 }
+                "SessionTurnResponse": {
+                    "allOf": [
+                        {
+                            "$ref": "#/components/schemas/ImageResponse"
+                        },
+                        {
+                            "type": "object",
+                            "required": ["session_id", "turn"],
+                            "properties": {
+                                "session_id": {
+                                    "type": "string"
+                                },
+                                "turn": {
+                                    "type": "integer",
+                                    "description": "1 for the first turn"
+                                }
+                            }
+                        }
+                    ]
+                }
             }
         },
          "tags": [
@@ -915,6 +4075,30 @@ pub fn openapi_spec() -> serde_json::Value {
              {
                  "name": "Video Generation",
                  "description": "Endpoints for generating and editing videos using Veo AI"
+             },
+             {
+                 "name": "Account",
+                 "description": "Per-user account, usage and history endpoints"
+             },
+             {
+                 "name": "Assets",
+                 "description": "Stored generation outputs"
+             },
+             {
+                 "name": "Webhooks",
+                 "description": "Signed callbacks for finished video jobs"
+             },
+             {
+                 "name": "API Keys",
+                 "description": "Named, revocable API keys"
+             },
+             {
+                 "name": "Admin",
+                 "description": "User management for accounts with is_admin set"
+             },
+             {
+                 "name": "Edit Sessions",
+                 "description": "Multi-turn image editing that keeps earlier turns as context"
              }
          ]
     })
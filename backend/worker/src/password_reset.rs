@@ -0,0 +1,241 @@
+use serde::Deserialize;
+use serde_json::json;
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::api_keys;
+use crate::error::{ApiError, ApiResult};
+use crate::login_guard;
+use crate::mail::{Mail, MailSender, Mailer};
+use crate::sessions;
+use crate::validation;
+use crate::{hash_password, parse_json};
+
+/// How long an emailed token stays valid.
+const TOKEN_TTL_MINUTES: u32 = 60;
+
+/// A new token is not sent while the previous one is younger than this,
+/// so `/password/forgot` cannot be used to flood an inbox.
+const RESEND_AFTER_SECONDS: u32 = 60;
+
+#[derive(Deserialize)]
+struct ForgotPasswordRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct ResetPasswordRequest {
+    token: String,
+    password: String,
+}
+
+fn generate_token() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+fn reset_mail(email: &str, token: &str) -> Mail {
+    Mail {
+        to: email.to_string(),
+        subject: "Reset your GeminiPocket password".to_string(),
+        body: format!(
+            "Someone asked to reset the password for this GeminiPocket account.\n\n\
+             Your reset token is:\n\n    {}\n\n\
+             Run `geminipocket-cli auth reset-password` and paste it when asked, or POST it to\n\
+             /password/reset with your new password. The token expires in {} minutes and\n\
+             can only be used once.\n\n\
+             If you did not ask for this, you can ignore this email.",
+            token, TOKEN_TTL_MINUTES
+        ),
+    }
+}
+
+/// Issues a token for `email` and returns it, or `None` for unknown
+/// addresses and repeat requests inside `RESEND_AFTER_SECONDS`. Both cases
+/// run the same statements as a real reset, so the time taken does not tell
+/// them apart.
+pub async fn issue_token(env: &Env, email: &str) -> Result<Option<String>> {
+    let db = env.d1("DB")?;
+    let token = generate_token();
+    let token_id = uuid::Uuid::new_v4().to_string();
+    let expires = format!("+{} minutes", TOKEN_TTL_MINUTES);
+    let resend_window = format!("-{} seconds", RESEND_AFTER_SECONDS);
+
+    let statement = db.prepare(
+        "INSERT INTO password_reset_tokens (id, user_id, token_hash, expires_at, created_at) \
+         SELECT ?, u.id, ?, datetime('now', ?), datetime('now') FROM users u \
+         WHERE lower(u.email) = ? AND NOT EXISTS (\
+             SELECT 1 FROM password_reset_tokens t WHERE t.user_id = u.id AND t.created_at > datetime('now', ?)\
+         ) RETURNING user_id",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&token_id),
+        D1Type::Text(&api_keys::hash(&token)),
+        D1Type::Text(&expires),
+        D1Type::Text(email),
+        D1Type::Text(&resend_window),
+    ])?;
+    let issued = query.first::<i32>(Some("user_id")).await?.is_some();
+
+    // Only the newest token works. Matches nothing when none was issued.
+    let statement = db.prepare(
+        "UPDATE password_reset_tokens SET used_at = datetime('now') \
+         WHERE user_id IN (SELECT user_id FROM password_reset_tokens WHERE id = ?) AND id != ? AND used_at IS NULL",
+    );
+    statement.bind_refs(&[D1Type::Text(&token_id), D1Type::Text(&token_id)])?.run().await?;
+
+    Ok(issued.then_some(token))
+}
+
+/// Mails a token from [`issue_token`] to its address.
+pub async fn deliver_token(mailer: &impl MailSender, email: &str, token: &str) -> Result<()> {
+    mailer.send(&reset_mail(email, token)).await
+}
+
+/// Consumes `token` and sets the new password. Claiming the token and
+/// checking its expiry happen in one statement, so it works exactly once.
+/// Returns how many API keys were revoked.
+pub async fn reset(env: &Env, token: &str, password: &str) -> ApiResult<u32> {
+    let errors = validation::check_password(password, None);
+    if !errors.is_empty() {
+        return Err(validation::into_api_error(errors));
+    }
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "UPDATE password_reset_tokens SET used_at = datetime('now') \
+         WHERE token_hash = ? AND used_at IS NULL AND expires_at > datetime('now') RETURNING user_id",
+    );
+    let query = statement.bind_refs(&[D1Type::Text(&api_keys::hash(token.trim()))])?;
+    let user_id = query
+        .first::<i32>(Some("user_id"))
+        .await?
+        .ok_or_else(|| ApiError::new(400, "invalid_reset_token", "Reset token is invalid, expired or already used"))?;
+
+    let password_hash = hash_password(password)?;
+    let statement = db.prepare("UPDATE users SET password_hash = ?, updated_at = datetime('now') WHERE id = ? RETURNING email");
+    let query = statement.bind_refs(&[D1Type::Text(&password_hash), D1Type::Integer(user_id)])?;
+    let email = query.first::<String>(Some("email")).await?.map(|email| validation::normalize_email(&email));
+
+    // Whoever knew the old password may still be logged in, or may have
+    // minted keys with it.
+    sessions::revoke_all(env, user_id).await?;
+    let revoked_keys = api_keys::revoke(env, user_id, None).await?;
+
+    // Resetting is the way out of a login lockout.
+    if let Some(email) = &email {
+        login_guard::clear_account(env, email).await?;
+    }
+    login_guard::record_event(env, "password_reset", email.as_deref(), None, json!({ "revoked_keys": revoked_keys })).await;
+
+    Ok(revoked_keys)
+}
+
+/// `POST /password/forgot` — emails a reset token. The response is the same
+/// whether or not the address has an account.
+pub async fn handle_forgot(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let body: ForgotPasswordRequest = parse_json(&mut req).await?;
+    let Some(mailer) = Mailer::from_env(&ctx.env) else {
+        console_log!("Password reset requested but mail is not configured");
+        return Err(ApiError::new(503, "mail_not_configured", "Password reset by email is not available on this server")
+            .with_retryable(false));
+    };
+
+    let email = validation::normalize_email(&body.email);
+    match issue_token(&ctx.env, &email).await {
+        // Sent after the response, so its latency does not show which
+        // addresses have accounts.
+        Ok(Some(token)) => ctx.data.wait_until(async move {
+            if let Err(e) = deliver_token(&mailer, &email, &token).await {
+                console_log!("Failed to send password reset: {}", e);
+            }
+        }),
+        Ok(None) => {}
+        Err(e) => console_log!("Failed to issue password reset: {}", e),
+    }
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "message": "If an account exists for that email, a reset token has been sent to it",
+    }))?)
+}
+
+/// `POST /password/reset` — sets a new password using an emailed token.
+pub async fn handle_reset(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let body: ResetPasswordRequest = parse_json(&mut req).await?;
+    let revoked_keys = reset(&ctx.env, &body.token, &body.password).await?;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "message": "Password updated, all sessions ended and all API keys revoked. Log in with the new password to get an API key",
+        "revoked_keys": revoked_keys,
+    }))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task;
+
+    #[test]
+    fn test_tokens_are_random_and_stored_hashed() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+
+        let hash = api_keys::hash(&token);
+        assert_eq!(hash.len(), 64);
+        assert_ne!(hash, token);
+        assert_eq!(hash, api_keys::hash(&token));
+    }
+
+    /// Keeps what it is asked to send.
+    #[derive(Default)]
+    struct CapturingMailer {
+        sent: RefCell<Vec<Mail>>,
+    }
+
+    impl MailSender for CapturingMailer {
+        async fn send(&self, mail: &Mail) -> Result<()> {
+            self.sent.borrow_mut().push(Mail {
+                to: mail.to.clone(),
+                subject: mail.subject.clone(),
+                body: mail.body.clone(),
+            });
+            Ok(())
+        }
+    }
+
+    /// Runs a future that never waits on anything.
+    fn run<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = task::Context::from_waker(task::Waker::noop());
+        match future.as_mut().poll(&mut context) {
+            task::Poll::Ready(output) => output,
+            task::Poll::Pending => panic!("future did not complete"),
+        }
+    }
+
+    #[test]
+    fn test_deliver_token_mails_the_plaintext_token() {
+        let mailer = CapturingMailer::default();
+        let token = generate_token();
+        run(deliver_token(&mailer, "user@example.com", &token)).unwrap();
+
+        let sent = mailer.sent.borrow();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "user@example.com");
+        assert!(sent[0].body.contains(&token));
+        assert!(!sent[0].body.contains(&api_keys::hash(&token)));
+    }
+
+    #[test]
+    fn test_reset_mail_carries_token() {
+        let mail = reset_mail("user@example.com", "abc123");
+        assert_eq!(mail.to, "user@example.com");
+        assert!(mail.body.contains("abc123"));
+        assert!(mail.body.contains("60 minutes"));
+    }
+}
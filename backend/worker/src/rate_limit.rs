@@ -0,0 +1,276 @@
+use serde_json::{json, Value};
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+use crate::AuthUser;
+
+const MINUTE: u64 = 60;
+const DAY: u64 = 24 * 60 * 60;
+
+/// Groups of endpoints that share a counter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LimitKind {
+    Image,
+    Video,
+    Status,
+}
+
+impl LimitKind {
+    fn bucket(&self) -> &'static str {
+        match self {
+            LimitKind::Image => "image",
+            LimitKind::Video => "video",
+            LimitKind::Status => "status",
+        }
+    }
+
+    /// Returns `(window_seconds, limit)` pairs, using the per-user override
+    /// from the `users` row when one is set.
+    fn windows(&self, user: &AuthUser) -> Vec<(u64, u32)> {
+        match self {
+            LimitKind::Image => vec![
+                (MINUTE, user.image_limit_per_minute.unwrap_or(10)),
+                (DAY, user.image_limit_per_day.unwrap_or(200)),
+            ],
+            LimitKind::Video => vec![
+                (MINUTE, user.video_limit_per_minute.unwrap_or(5)),
+                (DAY, user.video_limit_per_day.unwrap_or(20)),
+            ],
+            LimitKind::Status => vec![(MINUTE, user.status_limit_per_minute.unwrap_or(30))],
+        }
+    }
+}
+
+fn window_name(window: u64) -> &'static str {
+    if window == MINUTE {
+        "per_minute"
+    } else {
+        "per_day"
+    }
+}
+
+/// The limits that apply to `user`, as shown by `GET /me`. Accounts with any
+/// override in `users` are on the `custom` plan, everyone else on `default`.
+pub fn plan(user: &AuthUser) -> Value {
+    let custom = [
+        user.image_limit_per_minute,
+        user.image_limit_per_day,
+        user.video_limit_per_minute,
+        user.video_limit_per_day,
+        user.status_limit_per_minute,
+    ]
+    .iter()
+    .any(Option::is_some);
+
+    let mut limits = serde_json::Map::new();
+    for kind in [LimitKind::Image, LimitKind::Video, LimitKind::Status] {
+        let windows: serde_json::Map<String, Value> = kind
+            .windows(user)
+            .into_iter()
+            .map(|(window, limit)| (window_name(window).to_string(), json!(limit)))
+            .collect();
+        limits.insert(kind.bucket().to_string(), Value::Object(windows));
+    }
+
+    json!({
+        "name": if custom { "custom" } else { "default" },
+        "limits": limits,
+    })
+}
+
+/// The tightest window after counting a request, reported back to the
+/// caller through `X-RateLimit-*` headers.
+#[derive(Debug, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: u64,
+}
+
+impl RateLimitStatus {
+    pub fn apply(&self, headers: &Headers) -> Result<()> {
+        headers.set("X-RateLimit-Limit", &self.limit.to_string())?;
+        headers.set("X-RateLimit-Remaining", &self.remaining.to_string())?;
+        headers.set("X-RateLimit-Reset", &self.reset.to_string())?;
+        Ok(())
+    }
+}
+
+fn window_start(now: u64, window: u64) -> u64 {
+    now - now % window
+}
+
+/// Picks the window closest to exhaustion; among equals, the one that
+/// resets last.
+fn tightest(counts: &[(u64, u32, u32)], now: u64) -> RateLimitStatus {
+    counts
+        .iter()
+        .map(|&(window, limit, count)| RateLimitStatus {
+            limit,
+            remaining: limit.saturating_sub(count),
+            reset: window_start(now, window) + window,
+        })
+        .min_by_key(|status| (status.remaining, std::cmp::Reverse(status.reset)))
+        .expect("every limit kind has at least one window")
+}
+
+/// Counts one request against every window for `kind` and fails with 429
+/// once any of them is over its limit.
+pub async fn enforce(env: &Env, user: &AuthUser, kind: LimitKind) -> ApiResult<RateLimitStatus> {
+    charge(env, user, kind, 1).await
+}
+
+fn limited(kind: LimitKind, status: RateLimitStatus, now: u64) -> ApiError {
+    let retry_after = status.reset.saturating_sub(now).max(1);
+    ApiError::rate_limited(
+        format!("Rate limit exceeded for {} requests. Try again in {} seconds.", kind.bucket(), retry_after),
+        retry_after,
+    )
+    .with_header("X-RateLimit-Limit", status.limit.to_string())
+    .with_header("X-RateLimit-Remaining", "0".to_string())
+    .with_header("X-RateLimit-Reset", status.reset.to_string())
+}
+
+/// Like [`enforce`], for a request that counts as `units` requests, such as
+/// a `/generate` call asking for several images. Only admitted requests are
+/// counted, so retrying while limited does not push the reset further out.
+pub async fn charge(env: &Env, user: &AuthUser, kind: LimitKind, units: u32) -> ApiResult<RateLimitStatus> {
+    let db = env.d1("DB")?;
+    let now = Date::now().as_millis() / 1000;
+
+    let mut counts = Vec::new();
+    for (window, limit) in kind.windows(user) {
+        let bucket = format!("{}:{}", kind.bucket(), window);
+        let start = window_start(now, window);
+        // The increment only happens while it stays within the limit, so
+        // concurrent requests cannot both take the last unit.
+        let count = if units > limit {
+            None
+        } else {
+            let statement = db.prepare(
+                "INSERT INTO rate_limit_counters (user_id, bucket, window_start, count) VALUES (?, ?, ?, ?) \
+                 ON CONFLICT(user_id, bucket, window_start) DO UPDATE SET count = count + excluded.count \
+                 WHERE count + excluded.count <= ? RETURNING count",
+            );
+            let query = statement.bind_refs(&[
+                D1Type::Integer(user.id),
+                D1Type::Text(&bucket),
+                D1Type::Real(start as f64),
+                D1Type::Integer(units as i32),
+                D1Type::Integer(limit as i32),
+            ])?;
+            query.first::<u32>(Some("count")).await?
+        };
+
+        let Some(count) = count else {
+            // Hand back what the windows before this one already counted.
+            for &(window, _, _) in &counts {
+                let statement = db.prepare(
+                    "UPDATE rate_limit_counters SET count = count - ? WHERE user_id = ? AND bucket = ? AND window_start = ?",
+                );
+                let query = statement.bind_refs(&[
+                    D1Type::Integer(units as i32),
+                    D1Type::Integer(user.id),
+                    D1Type::Text(&format!("{}:{}", kind.bucket(), window)),
+                    D1Type::Real(window_start(now, window) as f64),
+                ])?;
+                query.run().await?;
+            }
+            let status = RateLimitStatus {
+                limit,
+                remaining: 0,
+                reset: start + window,
+            };
+            return Err(limited(kind, status, now));
+        };
+        counts.push((window, limit, count));
+    }
+
+    Ok(tightest(&counts, now))
+}
+
+/// Deletes counters whose window has ended. Called from the scheduled
+/// handler.
+pub async fn prune(env: &Env) {
+    let result = async {
+        let now = Date::now().as_millis() / 1000;
+        let db = env.d1("DB")?;
+        for window in [MINUTE, DAY] {
+            let statement = db.prepare("DELETE FROM rate_limit_counters WHERE bucket LIKE ? AND window_start <= ?");
+            let query = statement.bind_refs(&[
+                D1Type::Text(&format!("%:{}", window)),
+                D1Type::Real(now.saturating_sub(window) as f64),
+            ])?;
+            query.run().await?;
+        }
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to prune rate limit counters: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_start_aligns_to_window() {
+        assert_eq!(window_start(125, MINUTE), 120);
+        assert_eq!(window_start(DAY + 5, DAY), DAY);
+    }
+
+    #[test]
+    fn test_tightest_prefers_exhausted_window() {
+        let now = 1_000_000;
+        let status = tightest(&[(MINUTE, 10, 3), (DAY, 200, 201)], now);
+        assert_eq!(status.limit, 200);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.reset, window_start(now, DAY) + DAY);
+
+        let status = tightest(&[(MINUTE, 10, 9), (DAY, 200, 50)], now);
+        assert_eq!(status.limit, 10);
+        assert_eq!(status.remaining, 1);
+    }
+
+    #[test]
+    fn test_limited_reports_blocking_window() {
+        let status = RateLimitStatus {
+            limit: 10,
+            remaining: 0,
+            reset: 1_000_020,
+        };
+        let error = limited(LimitKind::Image, status, 1_000_000);
+        assert_eq!((error.status, error.code), (429, "rate_limited"));
+        assert!(error.headers.contains(&("Retry-After", "20".to_string())));
+        assert!(error.headers.contains(&("X-RateLimit-Limit", "10".to_string())));
+        assert!(error.headers.contains(&("X-RateLimit-Remaining", "0".to_string())));
+    }
+
+    #[test]
+    fn test_plan_reports_effective_limits() {
+        let mut user = AuthUser {
+            id: 1,
+            email: "user@example.com".to_string(),
+            image_limit_per_minute: None,
+            image_limit_per_day: None,
+            video_limit_per_minute: None,
+            video_limit_per_day: None,
+            status_limit_per_minute: None,
+            is_admin: false,
+            disabled_at: None,
+        };
+        let default = plan(&user);
+        assert_eq!(default["name"], "default");
+        assert_eq!(default["limits"]["image"]["per_day"], 200);
+        assert_eq!(default["limits"]["status"], json!({ "per_minute": 30 }));
+
+        user.video_limit_per_day = Some(100);
+        let custom = plan(&user);
+        assert_eq!(custom["name"], "custom");
+        assert_eq!(custom["limits"]["video"]["per_day"], 100);
+    }
+}
@@ -0,0 +1,273 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::api_keys;
+use crate::error::{ApiError, ApiResult};
+use crate::{parse_json, AuthUser, AUTH_USER_COLUMNS};
+
+/// Session tokens are told apart from `gp_` API keys by this prefix.
+pub const TOKEN_PREFIX: &str = "gps_";
+
+const REFRESH_PREFIX: &str = "gpr_";
+
+/// Lifetime of a session token. Short, because it is only checked against
+/// revocation when it is used, and it lives in the browser.
+const TOKEN_TTL_SECONDS: u64 = 15 * 60;
+
+/// Lifetime of a session, i.e. how long refresh tokens keep working.
+const SESSION_TTL_DAYS: u32 = 30;
+
+/// What a session token vouches for.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Claims {
+    /// Session id, checked against `sessions` on every request.
+    sid: String,
+    sub: i32,
+    exp: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum TokenError {
+    Invalid,
+    Expired,
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    success: bool,
+    session_id: String,
+    session_token: String,
+    refresh_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Deserialize, Default)]
+struct LogoutRequest {
+    /// End every session of the user, not only the calling one.
+    #[serde(default)]
+    all: bool,
+}
+
+pub fn secret(env: &Env) -> ApiResult<String> {
+    env.secret("SESSION_SECRET")
+        .map(|secret| secret.to_string())
+        .map_err(|_| ApiError::new(503, "sessions_not_configured", "Session tokens are not configured on this server"))
+}
+
+fn now() -> u64 {
+    Date::now().as_millis() / 1000
+}
+
+fn mac(secret: &str, payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// `gps_<base64url claims>.<base64url HMAC-SHA256 of the claims>`.
+fn sign_token(secret: &str, claims: &Claims) -> String {
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).expect("claims serialize"));
+    let signature = URL_SAFE_NO_PAD.encode(mac(secret, &payload).finalize().into_bytes());
+    format!("{}{}.{}", TOKEN_PREFIX, payload, signature)
+}
+
+fn verify_token(secret: &str, token: &str, now: u64) -> std::result::Result<Claims, TokenError> {
+    let (payload, signature) = token
+        .strip_prefix(TOKEN_PREFIX)
+        .and_then(|rest| rest.split_once('.'))
+        .ok_or(TokenError::Invalid)?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| TokenError::Invalid)?;
+    mac(secret, payload)
+        .verify_slice(&signature)
+        .map_err(|_| TokenError::Invalid)?;
+
+    let claims: Claims = URL_SAFE_NO_PAD
+        .decode(payload)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or(TokenError::Invalid)?;
+    if claims.exp <= now {
+        return Err(TokenError::Expired);
+    }
+    Ok(claims)
+}
+
+fn generate_refresh_token() -> String {
+    format!("{}{}{}", REFRESH_PREFIX, uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+fn response(secret: &str, session_id: String, user_id: i32, refresh_token: String) -> SessionResponse {
+    let claims = Claims {
+        sid: session_id,
+        sub: user_id,
+        exp: now() + TOKEN_TTL_SECONDS,
+    };
+    SessionResponse {
+        success: true,
+        session_token: sign_token(secret, &claims),
+        session_id: claims.sid,
+        refresh_token,
+        expires_in: TOKEN_TTL_SECONDS,
+    }
+}
+
+/// Starts a session for a user who has just proven their password.
+pub async fn issue(env: &Env, user_id: i32) -> ApiResult<SessionResponse> {
+    let secret = secret(env)?;
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let refresh_token = generate_refresh_token();
+    let lifetime = format!("+{} days", SESSION_TTL_DAYS);
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "INSERT INTO sessions (id, user_id, refresh_hash, expires_at, created_at) \
+         VALUES (?, ?, ?, datetime('now', ?), datetime('now'))",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&session_id),
+        D1Type::Integer(user_id),
+        D1Type::Text(&api_keys::hash(&refresh_token)),
+        D1Type::Text(&lifetime),
+    ])?;
+    query.run().await?;
+
+    Ok(response(&secret, session_id, user_id, refresh_token))
+}
+
+/// Resolves a session token to its user. Expired tokens get a distinct
+/// error code so clients know to refresh rather than log in again.
+pub async fn authenticate(env: &Env, token: &str) -> ApiResult<AuthUser> {
+    let claims = match verify_token(&secret(env)?, token, now()) {
+        Ok(claims) => claims,
+        Err(TokenError::Expired) => return Err(ApiError::unauthorized("session_expired", "Session token has expired")),
+        Err(TokenError::Invalid) => return Err(ApiError::unauthorized("invalid_session", "Invalid session token")),
+    };
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare(format!(
+        "SELECT {} \
+         FROM sessions s JOIN users u ON u.id = s.user_id \
+         WHERE s.id = ? AND s.user_id = ? AND s.revoked_at IS NULL AND s.expires_at > datetime('now')",
+        AUTH_USER_COLUMNS
+    ));
+    let query = statement.bind_refs(&[D1Type::Text(&claims.sid), D1Type::Integer(claims.sub)])?;
+    query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::unauthorized("session_revoked", "Session has been ended"))
+}
+
+/// Ends every session of `user_id`, e.g. after a password change.
+pub async fn revoke_all(env: &Env, user_id: i32) -> Result<()> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("UPDATE sessions SET revoked_at = datetime('now') WHERE user_id = ? AND revoked_at IS NULL");
+    statement.bind_refs(&[D1Type::Integer(user_id)])?.run().await?;
+    Ok(())
+}
+
+/// `POST /token/refresh` — trades a refresh token for a new session token.
+/// The refresh token is rotated, so each one works once.
+pub async fn handle_refresh(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let body: RefreshRequest = parse_json(&mut req).await?;
+    let secret = secret(&ctx.env)?;
+    let refresh_token = generate_refresh_token();
+
+    let db = ctx.env.d1("DB")?;
+    let statement = db.prepare(
+        "UPDATE sessions SET refresh_hash = ?, last_refreshed_at = datetime('now') \
+         WHERE refresh_hash = ? AND revoked_at IS NULL AND expires_at > datetime('now') \
+         RETURNING id, user_id",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&api_keys::hash(&refresh_token)),
+        D1Type::Text(&api_keys::hash(body.refresh_token.trim())),
+    ])?;
+
+    #[derive(Deserialize)]
+    struct Refreshed {
+        id: String,
+        user_id: i32,
+    }
+
+    let session: Refreshed = query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::unauthorized("invalid_refresh_token", "Refresh token is invalid, expired or already used"))?;
+
+    Ok(Response::from_json(&response(&secret, session.id, session.user_id, refresh_token))?)
+}
+
+/// `POST /logout` — ends the session the calling token belongs to, or all
+/// of the user's sessions with `{"all": true}`. API keys are revoked through
+/// `DELETE /keys/{id}` instead.
+pub async fn handle_logout(mut req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let token = req
+        .headers()
+        .get("Authorization")?
+        .map(|header| header.trim_start_matches("Bearer ").to_string())
+        .filter(|token| token.starts_with(TOKEN_PREFIX))
+        .ok_or_else(|| ApiError::bad_request("Logout needs a session token; revoke API keys with DELETE /keys/{id}"))?;
+
+    // Expired tokens may still log out: they prove the session once existed
+    // and ending it is harmless.
+    let claims = match verify_token(&secret(&ctx.env)?, &token, 0) {
+        Ok(claims) => claims,
+        Err(_) => return Err(ApiError::unauthorized("invalid_session", "Invalid session token")),
+    };
+    let body: LogoutRequest = parse_json(&mut req).await.unwrap_or_default();
+
+    if body.all {
+        revoke_all(&ctx.env, claims.sub).await?;
+    } else {
+        let db = ctx.env.d1("DB")?;
+        let statement = db.prepare("UPDATE sessions SET revoked_at = datetime('now') WHERE id = ? AND revoked_at IS NULL");
+        statement.bind_refs(&[D1Type::Text(&claims.sid)])?.run().await?;
+    }
+
+    Ok(Response::from_json(&json!({ "success": true }))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(exp: u64) -> Claims {
+        Claims {
+            sid: "session-1".to_string(),
+            sub: 42,
+            exp,
+        }
+    }
+
+    #[test]
+    fn test_token_round_trips_until_expiry() {
+        let token = sign_token("secret", &claims(1_000));
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(verify_token("secret", &token, 999), Ok(claims(1_000)));
+        assert_eq!(verify_token("secret", &token, 1_000), Err(TokenError::Expired));
+    }
+
+    #[test]
+    fn test_token_rejects_tampering() {
+        let token = sign_token("secret", &claims(1_000));
+        assert_eq!(verify_token("other-secret", &token, 0), Err(TokenError::Invalid));
+
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&Claims { sub: 1, ..claims(1_000) }).unwrap());
+        let signature = token.split_once('.').unwrap().1;
+        let forged = format!("{}{}.{}", TOKEN_PREFIX, forged_payload, signature);
+        assert_eq!(verify_token("secret", &forged, 0), Err(TokenError::Invalid));
+
+        assert_eq!(verify_token("secret", "gps_garbage", 0), Err(TokenError::Invalid));
+        assert_eq!(verify_token("secret", "gp_12345678901234567890123456789012", 0), Err(TokenError::Invalid));
+    }
+}
@@ -0,0 +1,249 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use worker::*;
+
+use crate::error::{ApiError, ApiResult};
+
+/// Total decoded size of the input images of one request. Gemini caps
+/// inline data at 20 MB per request, and base64 adds a third.
+pub const MAX_INPUT_BYTES: u64 = 15 * 1024 * 1024;
+
+/// Largest request body `/edit` and `/edit_video` read: the input limit
+/// in base64, plus room for the other fields.
+pub const MAX_BODY_BYTES: u64 = MAX_INPUT_BYTES / 3 * 4 + 1024 * 1024;
+
+/// Largest text value one multipart field may carry. Prompts are far
+/// shorter; this only bounds what a form can smuggle past the JSON limits.
+const MAX_FIELD_BYTES: usize = 64 * 1024;
+
+/// Most parts one list field may repeat.
+const MAX_LIST_PARTS: usize = 32;
+
+/// How the parts of a `multipart/form-data` upload map onto the JSON body
+/// of the same endpoint. Image files go in the `image` part; several are
+/// sent on as `images`, in order.
+pub struct Form {
+    /// Copied as strings.
+    pub text: &'static [&'static str],
+    /// Numbers and booleans, written as in JSON (`42`, `0.7`, `true`).
+    pub scalars: &'static [&'static str],
+    /// Repeated parts, or one comma-separated part.
+    pub lists: &'static [&'static str],
+    pub max_files: usize,
+}
+
+pub fn too_large(what: &str, size: u64, limit: u64) -> ApiError {
+    ApiError::payload_too_large(format!("{} is {} bytes; the limit is {} bytes", what, size, limit))
+        .with_details(json!({ "size": size, "limit": limit }))
+}
+
+/// Rejects input images over [`MAX_INPUT_BYTES`] in total.
+pub fn check_input_size(size: u64) -> ApiResult<()> {
+    if size > MAX_INPUT_BYTES {
+        return Err(too_large("Input image data", size, MAX_INPUT_BYTES));
+    }
+    Ok(())
+}
+
+fn check_body_size(size: u64) -> ApiResult<()> {
+    if size > MAX_BODY_BYTES {
+        return Err(too_large("Request body", size, MAX_BODY_BYTES));
+    }
+    Ok(())
+}
+
+fn check_field(name: &str, value: &str) -> ApiResult<()> {
+    if value.len() > MAX_FIELD_BYTES {
+        return Err(too_large(&format!("Field {}", name), value.len() as u64, MAX_FIELD_BYTES as u64));
+    }
+    Ok(())
+}
+
+fn scalar(name: &str, value: &str) -> ApiResult<Value> {
+    serde_json::from_str::<Value>(value.trim())
+        .ok()
+        .filter(|value| value.is_number() || value.is_boolean())
+        .ok_or_else(|| ApiError::bad_request(format!("{} must be a number or true/false, got {}", name, value)))
+}
+
+fn list(values: Vec<String>) -> Value {
+    values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| json!(item))
+        .collect()
+}
+
+/// Puts the base64-encoded files where the JSON body would have them.
+fn insert_images(body: &mut Map<String, Value>, mut images: Vec<String>) {
+    match images.len() {
+        0 => {}
+        1 => {
+            body.insert("image".to_string(), json!(images.remove(0)));
+        }
+        _ => {
+            let images = images.into_iter().map(|data| json!({ "data": data })).collect();
+            body.insert("images".to_string(), Value::Array(images));
+        }
+    }
+}
+
+async fn read_form(req: &mut Request, form: &Form) -> ApiResult<Value> {
+    let data = req
+        .form_data()
+        .await
+        .map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {}", e)))?;
+    let mut body = Map::new();
+
+    for &name in form.text {
+        if let Some(value) = data.get_field(name) {
+            check_field(name, &value)?;
+            body.insert(name.to_string(), json!(value));
+        }
+    }
+    for &name in form.scalars {
+        if let Some(value) = data.get_field(name).filter(|value| !value.trim().is_empty()) {
+            check_field(name, &value)?;
+            body.insert(name.to_string(), scalar(name, &value)?);
+        }
+    }
+    for &name in form.lists {
+        let values: Vec<String> = data
+            .get_all(name)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                FormEntry::Field(value) => Some(value),
+                FormEntry::File(_) => None,
+            })
+            .collect();
+        if values.len() > MAX_LIST_PARTS {
+            return Err(ApiError::bad_request(format!("At most {} {} part(s) can be sent", MAX_LIST_PARTS, name)));
+        }
+        for value in &values {
+            check_field(name, value)?;
+        }
+        if !values.is_empty() {
+            body.insert(name.to_string(), list(values));
+        }
+    }
+
+    let entries = data.get_all("image").unwrap_or_default();
+    if entries.len() > form.max_files {
+        return Err(ApiError::bad_request(format!("At most {} image part(s) can be sent", form.max_files)));
+    }
+    // File sizes are known before anything is read.
+    let total: u64 = entries
+        .iter()
+        .map(|entry| match entry {
+            FormEntry::File(file) => file.size() as u64,
+            FormEntry::Field(_) => 0,
+        })
+        .sum();
+    check_input_size(total)?;
+
+    let mut images = Vec::with_capacity(entries.len());
+    for entry in entries {
+        images.push(match entry {
+            FormEntry::File(file) => general_purpose::STANDARD.encode(file.bytes().await?),
+            // A plain field is taken to be base64 already.
+            FormEntry::Field(value) => value,
+        });
+    }
+    insert_images(&mut body, images);
+
+    Ok(Value::Object(body))
+}
+
+/// Multipart bodies are parsed in one go, so their size has to be declared
+/// up front rather than measured after buffering.
+fn check_form_length(declared: Option<u64>) -> ApiResult<()> {
+    match declared {
+        Some(length) => check_body_size(length),
+        None => Err(ApiError::new(411, "length_required", "Multipart uploads need a Content-Length header")
+            .with_details(json!({ "limit": MAX_BODY_BYTES }))),
+    }
+}
+
+/// Reads an upload body, either JSON or `multipart/form-data` laid out as
+/// `form` describes, refusing bodies over [`MAX_BODY_BYTES`].
+pub async fn read<T: DeserializeOwned>(req: &mut Request, form: &Form) -> ApiResult<T> {
+    let declared = req.headers().get("Content-Length")?.and_then(|length| length.parse::<u64>().ok());
+    if let Some(length) = declared {
+        check_body_size(length)?;
+    }
+
+    let content_type = req.headers().get("Content-Type")?.unwrap_or_default().to_lowercase();
+    let body = if content_type.starts_with("multipart/form-data") {
+        check_form_length(declared)?;
+        read_form(req, form).await?
+    } else {
+        // Without a Content-Length the size is only known once read.
+        let bytes = req.bytes().await?;
+        check_body_size(bytes.len() as u64)?;
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            // An empty body is an empty object, so endpoints whose fields
+            // are all optional can be called without one.
+            Value::Object(Map::new())
+        } else {
+            serde_json::from_slice(&bytes).map_err(|e| ApiError::bad_request(format!("Invalid request body: {}", e)))?
+        }
+    };
+
+    serde_json::from_value(body).map_err(|e| ApiError::bad_request(format!("Invalid request body: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_limits() {
+        assert!(check_input_size(MAX_INPUT_BYTES).is_ok());
+        let error = check_input_size(MAX_INPUT_BYTES + 1).unwrap_err();
+        assert_eq!((error.status, error.code), (413, "payload_too_large"));
+        assert_eq!(error.details.unwrap()["limit"], MAX_INPUT_BYTES);
+
+        // A JSON body holding the largest allowed input still fits.
+        assert!(check_body_size(MAX_INPUT_BYTES.div_ceil(3) * 4 + 4096).is_ok());
+        assert_eq!(check_body_size(MAX_BODY_BYTES + 1).unwrap_err().status, 413);
+    }
+
+    #[test]
+    fn test_form_limits() {
+        assert!(check_form_length(Some(1024)).is_ok());
+        assert_eq!(check_form_length(Some(MAX_BODY_BYTES + 1)).unwrap_err().status, 413);
+        let error = check_form_length(None).unwrap_err();
+        assert_eq!((error.status, error.code), (411, "length_required"));
+
+        assert!(check_field("prompt", &"a".repeat(MAX_FIELD_BYTES)).is_ok());
+        let error = check_field("prompt", &"a".repeat(MAX_FIELD_BYTES + 1)).unwrap_err();
+        assert_eq!((error.status, error.code), (413, "payload_too_large"));
+        assert!(error.message.starts_with("Field prompt"));
+    }
+
+    #[test]
+    fn test_form_values() {
+        assert_eq!(scalar("seed", " 42 ").unwrap(), json!(42));
+        assert_eq!(scalar("inline", "true").unwrap(), json!(true));
+        assert_eq!(scalar("seed", "forty").unwrap_err().status, 400);
+        assert_eq!(scalar("seed", "\"42\"").unwrap_err().status, 400);
+
+        let values = vec!["IMAGE, TEXT".to_string(), "image".to_string()];
+        assert_eq!(list(values), json!(["IMAGE", "TEXT", "image"]));
+    }
+
+    #[test]
+    fn test_insert_images() {
+        let mut body = Map::new();
+        insert_images(&mut body, vec!["a".to_string()]);
+        assert_eq!(Value::Object(body), json!({ "image": "a" }));
+
+        let mut body = Map::new();
+        insert_images(&mut body, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(Value::Object(body), json!({ "images": [{"data": "a"}, {"data": "b"}] }));
+    }
+}
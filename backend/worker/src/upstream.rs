@@ -0,0 +1,562 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use worker::*;
+
+pub const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Built-in defaults, used when the worker env does not configure an
+/// allowlist for the kind (see `models.rs`).
+pub const IMAGE_GENERATE_MODEL: &str = "gemini-2.5-flash-preview";
+pub const IMAGE_EDIT_MODEL: &str = "gemini-2.5-flash-image-preview";
+pub const VIDEO_MODEL: &str = "veo-3.0-fast-generate-001";
+
+#[derive(Debug)]
+pub enum UpstreamError {
+    NotConfigured,
+    RateLimited,
+    AccessDenied,
+    InvalidRequest(String),
+    ServerError,
+    Api { code: u16, message: String },
+    Http { status: u16, body: String },
+    Transport(String),
+    Parse(String),
+    MissingData(&'static str),
+}
+
+impl UpstreamError {
+    /// Maps a non-2xx upstream response onto a typed error, preferring the
+    /// structured Google error body when one is present.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        match serde_json::from_str::<GeminiError>(body) {
+            Ok(error_response) => match error_response.error.code {
+                429 => UpstreamError::RateLimited,
+                403 => UpstreamError::AccessDenied,
+                400 => UpstreamError::InvalidRequest(error_response.error.message),
+                500 => UpstreamError::ServerError,
+                code => UpstreamError::Api {
+                    code,
+                    message: error_response.error.message,
+                },
+            },
+            Err(_) => UpstreamError::Http {
+                status,
+                body: body.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpstreamError::NotConfigured => write!(f, "API key not configured"),
+            UpstreamError::RateLimited => write!(f, "Rate limit exceeded. You've reached your API quota. Please wait a few minutes before trying again."),
+            UpstreamError::AccessDenied => write!(f, "Access denied. Please check your API key and permissions."),
+            UpstreamError::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            UpstreamError::ServerError => write!(f, "Server error. Please try again later."),
+            UpstreamError::Api { code, message } => write!(f, "API Error ({}): {}", code, message),
+            UpstreamError::Http { status, body } => write!(f, "API request failed with status {}: {}", status, body),
+            UpstreamError::Transport(message) => write!(f, "Upstream request failed: {}", message),
+            UpstreamError::Parse(message) => write!(f, "Failed to parse upstream response: {}", message),
+            UpstreamError::MissingData(what) => write!(f, "{}", what),
+        }
+    }
+}
+
+impl From<worker::Error> for UpstreamError {
+    fn from(e: worker::Error) -> Self {
+        UpstreamError::Transport(e.to_string())
+    }
+}
+
+impl From<UpstreamError> for worker::Error {
+    fn from(e: UpstreamError) -> Self {
+        worker::Error::RustError(e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct GeminiError {
+    error: GeminiErrorDetails,
+}
+
+#[derive(Deserialize)]
+struct GeminiErrorDetails {
+    code: u16,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeminiResponse {
+    /// Empty when the prompt itself was blocked.
+    #[serde(default)]
+    pub candidates: Vec<GeminiCandidate>,
+    #[serde(rename = "promptFeedback")]
+    pub prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeminiCandidate {
+    pub content: Option<GeminiContent>,
+    #[serde(rename = "finishReason")]
+    pub finish_reason: Option<String>,
+    #[serde(rename = "safetyRatings", default)]
+    pub safety_ratings: Vec<SafetyRating>,
+}
+
+/// Finish reasons that mean a filter stopped the candidate, rather than the
+/// model running out of tokens or having nothing to say.
+const BLOCKING_FINISH_REASONS: [&str; 8] = [
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+    "IMAGE_SAFETY",
+    "IMAGE_PROHIBITED_CONTENT",
+    "IMAGE_RECITATION",
+];
+
+impl GeminiCandidate {
+    /// The finish reason, if a filter blocked this candidate.
+    pub fn block_reason(&self) -> Option<&str> {
+        self.finish_reason
+            .as_deref()
+            .filter(|reason| BLOCKING_FINISH_REASONS.contains(reason))
+    }
+}
+
+/// Why Gemini refused to answer the prompt at all.
+#[derive(Debug, Deserialize)]
+pub struct PromptFeedback {
+    #[serde(rename = "blockReason")]
+    pub block_reason: Option<String>,
+    #[serde(rename = "safetyRatings", default)]
+    pub safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SafetyRating {
+    pub category: String,
+    pub probability: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub blocked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeminiContent {
+    pub parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum GeminiPart {
+    Text {
+        text: String,
+    },
+    Image {
+        #[serde(rename = "inlineData")]
+        inline_data: InlineData,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InlineData {
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub data: String,
+}
+
+#[derive(Deserialize)]
+pub struct VideoStatusResponse {
+    pub done: Option<bool>,
+    pub response: Option<VideoGenerationResponse>,
+    pub name: Option<String>,
+    /// Set instead of `response` when Veo gave up on the operation.
+    pub error: Option<OperationError>,
+}
+
+#[derive(Deserialize)]
+pub struct OperationError {
+    pub code: Option<i32>,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VideoGenerationResponse {
+    #[serde(rename = "generateVideoResponse")]
+    pub generate_video_response: GenerateVideoResponse,
+}
+
+#[derive(Deserialize)]
+pub struct GenerateVideoResponse {
+    #[serde(rename = "generatedSamples")]
+    pub generated_samples: Vec<VideoSample>,
+}
+
+#[derive(Deserialize)]
+pub struct VideoSample {
+    pub video: VideoFile,
+}
+
+#[derive(Deserialize)]
+pub struct VideoFile {
+    pub uri: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChatRole {
+    User,
+    Model,
+}
+
+/// One message of a multi-turn exchange with an image model: its images,
+/// in order, then its text.
+pub struct ChatTurn<'a> {
+    pub role: ChatRole,
+    pub images: &'a [InlineData],
+    pub text: Option<&'a str>,
+}
+
+impl ChatTurn<'_> {
+    fn content(&self) -> Value {
+        let mut parts: Vec<Value> = self
+            .images
+            .iter()
+            .map(|image| {
+                json!({
+                    "inline_data": {
+                        "mime_type": image.mime_type,
+                        "data": image.data
+                    }
+                })
+            })
+            .collect();
+        if let Some(text) = self.text {
+            parts.push(json!({ "text": text }));
+        }
+        let role = match self.role {
+            ChatRole::User => "user",
+            ChatRole::Model => "model",
+        };
+        json!({ "role": role, "parts": parts })
+    }
+}
+
+/// Optional `generationConfig` settings shared by image generation and
+/// editing calls. Unset fields are left to the model's defaults.
+#[derive(Default)]
+pub struct ImageOptions<'a> {
+    pub candidate_count: u32,
+    pub aspect_ratio: Option<&'a str>,
+    pub seed: Option<i64>,
+    pub temperature: Option<f64>,
+    pub response_modalities: Option<&'a [String]>,
+}
+
+impl ImageOptions<'_> {
+    fn generation_config(&self) -> Option<Value> {
+        let mut config = json!({});
+        if self.candidate_count > 1 {
+            config["candidateCount"] = json!(self.candidate_count);
+        }
+        if let Some(temperature) = self.temperature {
+            config["temperature"] = json!(temperature);
+        }
+        if let Some(seed) = self.seed {
+            config["seed"] = json!(seed);
+        }
+        if let Some(modalities) = self.response_modalities {
+            config["responseModalities"] = json!(modalities);
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            config["imageConfig"] = json!({ "aspectRatio": aspect_ratio });
+        }
+        config.as_object().is_some_and(|config| !config.is_empty()).then_some(config)
+    }
+}
+
+/// Optional Veo parameters shared by text-to-video and image-to-video calls.
+#[derive(Default)]
+pub struct VideoOptions<'a> {
+    pub model: &'a str,
+    pub negative_prompt: Option<&'a str>,
+    pub aspect_ratio: Option<&'a str>,
+    pub resolution: Option<&'a str>,
+}
+
+pub struct GeminiClient {
+    base_url: String,
+    api_key: String,
+}
+
+impl GeminiClient {
+    pub fn new(base_url: &str, api_key: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.strip_prefix("gp_").unwrap_or(api_key).to_string(),
+        }
+    }
+
+    /// Builds a client from the `GEMINI_API_KEY` secret and the optional
+    /// `GEMINI_API_BASE_URL` var, which lets staging and tests point the
+    /// worker at a stand-in server.
+    pub fn from_env(env: &Env) -> std::result::Result<Self, UpstreamError> {
+        let api_key = env
+            .secret("GEMINI_API_KEY")
+            .map_err(|_| UpstreamError::NotConfigured)?
+            .to_string();
+        let base_url = env
+            .var("GEMINI_API_BASE_URL")
+            .map(|v| v.to_string())
+            .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+
+        Ok(Self::new(&base_url, &api_key))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    async fn send(&self, method: Method, url: &str, body: Option<&Value>) -> std::result::Result<Response, UpstreamError> {
+        let headers = Headers::new();
+        headers.set("x-goog-api-key", &self.api_key)?;
+
+        let mut init = RequestInit::new();
+        init.with_method(method);
+        if let Some(body) = body {
+            headers.set("Content-Type", "application/json")?;
+            init.with_body(Some(body.to_string().into()));
+        }
+        init.with_headers(headers);
+
+        let request = Request::new_with_init(url, &init)?;
+        Ok(Fetch::Request(request).send().await?)
+    }
+
+    async fn send_for_text(&self, method: Method, url: &str, body: Option<&Value>) -> std::result::Result<String, UpstreamError> {
+        let mut response = self.send(method, url, body).await?;
+        let text = response.text().await?;
+
+        let status = response.status_code();
+        if !(200..300).contains(&status) {
+            return Err(UpstreamError::from_response(status, &text));
+        }
+
+        Ok(text)
+    }
+
+    async fn generate_content(&self, model: &str, body: &Value) -> std::result::Result<GeminiResponse, UpstreamError> {
+        let url = self.url(&format!("models/{}:generateContent", model));
+        let text = self.send_for_text(Method::Post, &url, Some(body)).await?;
+
+        serde_json::from_str(&text)
+            .map_err(|e| UpstreamError::Parse(format!("Gemini response: {}", e)))
+    }
+
+    async fn predict_long_running(&self, instance: Value, options: &VideoOptions<'_>) -> std::result::Result<String, UpstreamError> {
+        let mut instance = instance;
+        if let Some(neg_prompt) = options.negative_prompt {
+            instance["negativePrompt"] = json!(neg_prompt);
+        }
+
+        let mut parameters = json!({});
+        if let Some(ar) = options.aspect_ratio {
+            parameters["aspectRatio"] = json!(ar);
+        }
+        if let Some(res) = options.resolution {
+            parameters["resolution"] = json!(res);
+        }
+
+        let body = json!({
+            "instances": [instance],
+            "parameters": parameters
+        });
+
+        let url = self.url(&format!("models/{}:predictLongRunning", options.model));
+        let text = self.send_for_text(Method::Post, &url, Some(&body)).await?;
+
+        let operation_response: Value = serde_json::from_str(&text)
+            .map_err(|e| UpstreamError::Parse(format!("Veo response: {}", e)))?;
+
+        operation_response["name"]
+            .as_str()
+            .map(|name| name.to_string())
+            .ok_or(UpstreamError::MissingData("No operation name in response"))
+    }
+
+    pub async fn generate_image(&self, model: &str, prompt: &str, options: &ImageOptions<'_>) -> std::result::Result<GeminiResponse, UpstreamError> {
+        let mut body = json!({
+            "contents": [{
+                "parts": [{
+                    "text": prompt
+                }]
+            }]
+        });
+        if let Some(config) = options.generation_config() {
+            body["generationConfig"] = config;
+        }
+
+        self.generate_content(model, &body).await
+    }
+
+    /// Edits or combines `images`; they are sent in order, ahead of the
+    /// prompt.
+    pub async fn edit_image(&self, model: &str, images: &[InlineData], prompt: &str, options: &ImageOptions<'_>) -> std::result::Result<GeminiResponse, UpstreamError> {
+        let turn = ChatTurn {
+            role: ChatRole::User,
+            images,
+            text: Some(prompt),
+        };
+        self.converse(model, &[turn], options).await
+    }
+
+    /// Sends a whole conversation, oldest turn first; the last turn is the
+    /// new user message.
+    pub async fn converse(&self, model: &str, turns: &[ChatTurn<'_>], options: &ImageOptions<'_>) -> std::result::Result<GeminiResponse, UpstreamError> {
+        let contents: Vec<Value> = turns.iter().map(ChatTurn::content).collect();
+        let mut body = json!({ "contents": contents });
+        if let Some(config) = options.generation_config() {
+            body["generationConfig"] = config;
+        }
+
+        self.generate_content(model, &body).await
+    }
+
+    pub async fn generate_video(&self, prompt: &str, options: &VideoOptions<'_>) -> std::result::Result<String, UpstreamError> {
+        self.predict_long_running(json!({ "prompt": prompt }), options).await
+    }
+
+    pub async fn edit_video(&self, image_data: &str, mime_type: &str, prompt: &str, options: &VideoOptions<'_>) -> std::result::Result<String, UpstreamError> {
+        let instance = json!({
+            "prompt": prompt,
+            "image": {
+                "bytesBase64Encoded": image_data,
+                "mimeType": mime_type
+            }
+        });
+
+        self.predict_long_running(instance, options).await
+    }
+
+    pub async fn poll_video_operation(&self, operation_name: &str) -> std::result::Result<VideoStatusResponse, UpstreamError> {
+        let url = self.url(operation_name);
+        let text = self.send_for_text(Method::Get, &url, None).await?;
+
+        serde_json::from_str(&text)
+            .map_err(|e| UpstreamError::Parse(format!("operation status: {}", e)))
+    }
+
+    /// Downloads a generated file. `uri` is absolute because Veo returns
+    /// fully-qualified download links rather than paths under the base URL.
+    pub async fn download(&self, uri: &str) -> std::result::Result<Vec<u8>, UpstreamError> {
+        let mut response = self.send(Method::Get, uri, None).await?;
+
+        let status = response.status_code();
+        if !(200..300).contains(&status) {
+            let text = response.text().await.unwrap_or_default();
+            return Err(UpstreamError::from_response(status, &text));
+        }
+
+        Ok(response.bytes().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_response_maps_google_error_codes() {
+        let body = r#"{"error":{"code":429,"message":"quota","status":"RESOURCE_EXHAUSTED"}}"#;
+        assert!(matches!(UpstreamError::from_response(429, body), UpstreamError::RateLimited));
+
+        let body = r#"{"error":{"code":400,"message":"bad prompt","status":"INVALID_ARGUMENT"}}"#;
+        match UpstreamError::from_response(400, body) {
+            UpstreamError::InvalidRequest(message) => assert_eq!(message, "bad prompt"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_response_falls_back_to_raw_body() {
+        match UpstreamError::from_response(502, "Bad Gateway") {
+            UpstreamError::Http { status, body } => {
+                assert_eq!(status, 502);
+                assert_eq!(body, "Bad Gateway");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_video_status_carries_operation_error() {
+        let body = r#"{"name":"operations/abc","done":true,"error":{"code":3,"message":"prompt rejected"}}"#;
+        let status: VideoStatusResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(status.done, Some(true));
+        assert!(status.response.is_none());
+        let error = status.error.unwrap();
+        assert_eq!(error.code, Some(3));
+        assert_eq!(error.message.as_deref(), Some("prompt rejected"));
+    }
+
+    #[test]
+    fn test_image_options_build_generation_config() {
+        assert_eq!(ImageOptions { candidate_count: 1, ..Default::default() }.generation_config(), None);
+
+        let modalities = vec!["IMAGE".to_string()];
+        let options = ImageOptions {
+            candidate_count: 2,
+            aspect_ratio: Some("16:9"),
+            seed: Some(42),
+            temperature: Some(0.5),
+            response_modalities: Some(&modalities),
+        };
+        assert_eq!(
+            options.generation_config().unwrap(),
+            json!({
+                "candidateCount": 2,
+                "temperature": 0.5,
+                "seed": 42,
+                "responseModalities": ["IMAGE"],
+                "imageConfig": {"aspectRatio": "16:9"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_turn_content() {
+        let images = [InlineData {
+            mime_type: "image/png".to_string(),
+            data: "AAAA".to_string(),
+        }];
+        let model_turn = ChatTurn {
+            role: ChatRole::Model,
+            images: &images,
+            text: None,
+        };
+        assert_eq!(
+            model_turn.content(),
+            json!({ "role": "model", "parts": [{"inline_data": {"mime_type": "image/png", "data": "AAAA"}}] })
+        );
+
+        let user_turn = ChatTurn {
+            role: ChatRole::User,
+            images: &[],
+            text: Some("make it blue"),
+        };
+        assert_eq!(user_turn.content(), json!({ "role": "user", "parts": [{"text": "make it blue"}] }));
+    }
+
+    #[test]
+    fn test_client_url_joins_base_and_path() {
+        let client = GeminiClient::new("http://localhost:9000/v1beta/", "gp_secret");
+        assert_eq!(
+            client.url("/models/x:generateContent"),
+            "http://localhost:9000/v1beta/models/x:generateContent"
+        );
+        assert_eq!(client.api_key, "secret");
+    }
+}
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::authorize;
+use crate::error::{ApiError, ApiResult};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GenerationKind {
+    Generate,
+    Edit,
+    GenerateVideo,
+    EditVideo,
+}
+
+impl GenerationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GenerationKind::Generate => "generate",
+            GenerationKind::Edit => "edit",
+            GenerationKind::GenerateVideo => "generate_video",
+            GenerationKind::EditVideo => "edit_video",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GenerationStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl GenerationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GenerationStatus::Pending => "pending",
+            GenerationStatus::Succeeded => "succeeded",
+            GenerationStatus::Failed => "failed",
+        }
+    }
+}
+
+/// One row of the `generations` ledger.
+pub struct Generation<'a> {
+    pub user_id: i32,
+    pub kind: GenerationKind,
+    pub model: &'a str,
+    pub prompt: &'a str,
+    pub parameters: Value,
+    pub status: GenerationStatus,
+    pub error_code: Option<&'a str>,
+    pub operation_name: Option<&'a str>,
+    pub latency_ms: u64,
+    pub output_bytes: Option<u64>,
+}
+
+/// Size of the decoded payload of a base64 string, without decoding it.
+pub fn base64_decoded_len(data: &str) -> u64 {
+    let padding = data.bytes().rev().take_while(|&b| b == b'=').count();
+    ((data.len() / 4) * 3).saturating_sub(padding) as u64
+}
+
+/// Inserts a ledger row and returns its id. Failures are logged rather than
+/// surfaced so that bookkeeping never breaks a generation the user paid for.
+pub async fn record(env: &Env, generation: &Generation<'_>) -> Option<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    match insert(env, &id, generation).await {
+        Ok(()) => Some(id),
+        Err(e) => {
+            console_log!("Failed to record generation: {}", e);
+            None
+        }
+    }
+}
+
+async fn insert(env: &Env, id: &str, generation: &Generation<'_>) -> Result<()> {
+    let db = env.d1("DB")?;
+    let parameters = generation.parameters.to_string();
+    let statement = db.prepare(
+        "INSERT INTO generations (id, user_id, kind, model, prompt, parameters, status, error_code, operation_name, latency_ms, output_bytes, created_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(id),
+        D1Type::Integer(generation.user_id),
+        D1Type::Text(generation.kind.as_str()),
+        D1Type::Text(generation.model),
+        D1Type::Text(generation.prompt),
+        D1Type::Text(&parameters),
+        D1Type::Text(generation.status.as_str()),
+        generation.error_code.map(D1Type::Text).unwrap_or(D1Type::Null),
+        generation.operation_name.map(D1Type::Text).unwrap_or(D1Type::Null),
+        D1Type::Real(generation.latency_ms as f64),
+        generation.output_bytes.map(|b| D1Type::Real(b as f64)).unwrap_or(D1Type::Null),
+    ])?;
+
+    query.run().await?;
+    Ok(())
+}
+
+/// Marks the pending ledger row for a video operation as finished. Latency
+/// is measured from when the operation was started.
+pub async fn complete_video(env: &Env, user_id: i32, operation_name: &str, status: GenerationStatus, error_code: Option<&str>, output_bytes: Option<u64>) {
+    let result = async {
+        let db = env.d1("DB")?;
+        let statement = db.prepare(
+            "UPDATE generations SET status = ?, error_code = ?, output_bytes = ?, \
+             latency_ms = CAST((julianday('now') - julianday(created_at)) * 86400000 AS INTEGER), updated_at = datetime('now') \
+             WHERE operation_name = ? AND user_id = ? AND status = 'pending'",
+        );
+        let query = statement.bind_refs(&[
+            D1Type::Text(status.as_str()),
+            error_code.map(D1Type::Text).unwrap_or(D1Type::Null),
+            output_bytes.map(|b| D1Type::Real(b as f64)).unwrap_or(D1Type::Null),
+            D1Type::Text(operation_name),
+            D1Type::Integer(user_id),
+        ])?;
+        query.run().await?;
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to update generation for {}: {}", operation_name, e);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct UsageRow {
+    day: String,
+    kind: String,
+    requests: u64,
+    succeeded: u64,
+    failed: u64,
+    output_bytes: u64,
+    latency_ms: u64,
+}
+
+/// Maps a finished call onto the status and error code stored in the ledger.
+pub fn outcome<T>(result: &ApiResult<T>) -> (GenerationStatus, Option<&'static str>) {
+    match result {
+        Ok(_) => (GenerationStatus::Succeeded, None),
+        Err(e) => (GenerationStatus::Failed, Some(e.code)),
+    }
+}
+
+/// `GET /usage?days=N` — per-day, per-kind totals for the caller.
+pub async fn handle_usage(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let days = requested_days(&req)?;
+    Ok(Response::from_json(&report(&ctx.env, user.id, days).await?)?)
+}
+
+/// The `days` query parameter shared by `/usage` and the admin usage view.
+pub fn requested_days(req: &Request) -> ApiResult<u32> {
+    let days = req
+        .url()?
+        .query_pairs()
+        .find(|(key, _)| key == "days")
+        .map(|(_, value)| value.parse::<u32>())
+        .transpose()
+        .map_err(|_| ApiError::bad_request("days must be a positive integer"))?
+        .unwrap_or(30);
+
+    if days == 0 || days > 365 {
+        return Err(ApiError::bad_request("days must be between 1 and 365"));
+    }
+    Ok(days)
+}
+
+/// The `/usage` response body for `user_id`.
+pub async fn report(env: &Env, user_id: i32, days: u32) -> Result<Value> {
+    let rows = daily(env, user_id, days).await?;
+    Ok(json!({
+        "success": true,
+        "days": days,
+        "usage": rows,
+        "totals": totals(&rows),
+    }))
+}
+
+/// Per-kind totals over the last `days` days, as shown by `GET /me`.
+pub async fn summary(env: &Env, user_id: i32, days: u32) -> Result<Value> {
+    Ok(totals(&daily(env, user_id, days).await?))
+}
+
+async fn daily(env: &Env, user_id: i32, days: u32) -> Result<Vec<UsageRow>> {
+    let db = env.d1("DB")?;
+    let since = format!("-{} days", days);
+    let statement = db.prepare(
+        "SELECT date(created_at) AS day, kind, COUNT(*) AS requests, \
+         SUM(CASE WHEN status = 'succeeded' THEN 1 ELSE 0 END) AS succeeded, \
+         SUM(CASE WHEN status = 'failed' THEN 1 ELSE 0 END) AS failed, \
+         COALESCE(SUM(output_bytes), 0) AS output_bytes, \
+         COALESCE(SUM(latency_ms), 0) AS latency_ms \
+         FROM generations WHERE user_id = ? AND created_at >= datetime('now', ?) \
+         GROUP BY day, kind ORDER BY day DESC, kind",
+    );
+    let query = statement.bind_refs(&[D1Type::Integer(user_id), D1Type::Text(&since)])?;
+    query.all().await?.results()
+}
+
+fn totals(rows: &[UsageRow]) -> Value {
+    let mut totals = serde_json::Map::new();
+    for row in rows {
+        let entry = totals.entry(row.kind.clone()).or_insert_with(|| {
+            json!({ "requests": 0, "succeeded": 0, "failed": 0, "output_bytes": 0 })
+        });
+        for (field, value) in [
+            ("requests", row.requests),
+            ("succeeded", row.succeeded),
+            ("failed", row.failed),
+            ("output_bytes", row.output_bytes),
+        ] {
+            entry[field] = json!(entry[field].as_u64().unwrap_or(0) + value);
+        }
+    }
+    Value::Object(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_decoded_len() {
+        assert_eq!(base64_decoded_len("aGVsbG8="), 5);
+        assert_eq!(base64_decoded_len("aGVsbG8h"), 6);
+        assert_eq!(base64_decoded_len(""), 0);
+    }
+
+    #[test]
+    fn test_totals_sum_across_days() {
+        let row = |day: &str, kind: &str, requests| UsageRow {
+            day: day.to_string(),
+            kind: kind.to_string(),
+            requests,
+            succeeded: requests,
+            failed: 0,
+            output_bytes: 10,
+            latency_ms: 0,
+        };
+        let totals = totals(&[row("2024-01-02", "generate", 2), row("2024-01-01", "generate", 3), row("2024-01-01", "edit", 1)]);
+
+        assert_eq!(totals["generate"]["requests"], 5);
+        assert_eq!(totals["generate"]["output_bytes"], 20);
+        assert_eq!(totals["edit"]["requests"], 1);
+    }
+}
@@ -0,0 +1,253 @@
+use serde::Serialize;
+use serde_json::json;
+
+use crate::error::ApiError;
+
+pub const MIN_PASSWORD_CHARS: usize = 8;
+
+/// bcrypt ignores everything past 72 bytes, so longer passwords would be
+/// silently weakened rather than rejected.
+pub const MAX_PASSWORD_BYTES: usize = 72;
+
+const MAX_EMAIL_LEN: usize = 254;
+const MAX_LOCAL_PART_LEN: usize = 64;
+
+/// One rule a field broke. `code` is stable and meant for clients; the
+/// message is for people.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &'static str, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Collapses a list of field errors into one `validation_failed` error,
+/// with each violation under `details.fields`.
+pub fn into_api_error(errors: Vec<FieldError>) -> ApiError {
+    let message = errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ");
+    ApiError::new(400, "validation_failed", message).with_details(json!({ "fields": errors }))
+}
+
+/// Emails are compared case-insensitively everywhere, so they are stored
+/// trimmed and lowercased.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// A deliberately loose syntax check: one `@`, a non-empty local part, and
+/// a dotted domain without empty labels. Deliverability is not checked.
+pub fn check_email(email: &str) -> Option<FieldError> {
+    if email.is_empty() {
+        return Some(FieldError::new("email", "email_required", "Email is required"));
+    }
+    if email.len() > MAX_EMAIL_LEN {
+        return Some(FieldError::new("email", "email_too_long", format!("Email must be at most {} characters", MAX_EMAIL_LEN)));
+    }
+
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && local.len() <= MAX_LOCAL_PART_LEN
+                && !domain.contains('@')
+                && domain.contains('.')
+                && domain.split('.').all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+                && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+        }
+        None => false,
+    };
+    (!valid).then(|| FieldError::new("email", "email_invalid", "Email address is not valid"))
+}
+
+/// Length limits plus a basic strength rule: at least one letter and one
+/// digit, and not containing the email address.
+pub fn check_password(password: &str, email: Option<&str>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    if password.chars().count() < MIN_PASSWORD_CHARS {
+        errors.push(FieldError::new(
+            "password",
+            "password_too_short",
+            format!("Password must be at least {} characters", MIN_PASSWORD_CHARS),
+        ));
+    }
+    if password.len() > MAX_PASSWORD_BYTES {
+        errors.push(FieldError::new(
+            "password",
+            "password_too_long",
+            format!("Password must be at most {} bytes", MAX_PASSWORD_BYTES),
+        ));
+    }
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+    if !password.is_empty() && (!has_letter || !has_digit) {
+        errors.push(FieldError::new(
+            "password",
+            "password_too_weak",
+            "Password must contain at least one letter and one digit",
+        ));
+    }
+    if email.is_some_and(|email| !email.is_empty() && password.to_lowercase().contains(email)) {
+        errors.push(FieldError::new(
+            "password",
+            "password_contains_email",
+            "Password must not contain your email address",
+        ));
+    }
+
+    errors
+}
+
+/// Aspect ratios Gemini's image models accept.
+pub const IMAGE_ASPECT_RATIOS: [&str; 10] = ["1:1", "2:3", "3:2", "3:4", "4:3", "4:5", "5:4", "9:16", "16:9", "21:9"];
+
+pub const MAX_TEMPERATURE: f64 = 2.0;
+
+/// Checks the generation settings of `/generate` and `/edit`. Response
+/// modalities are expected uppercased already, and must include `IMAGE`.
+pub fn check_image_options(
+    aspect_ratio: Option<&str>,
+    seed: Option<i64>,
+    temperature: Option<f64>,
+    response_modalities: Option<&[String]>,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    if aspect_ratio.is_some_and(|ratio| !IMAGE_ASPECT_RATIOS.contains(&ratio)) {
+        errors.push(FieldError::new(
+            "aspect_ratio",
+            "aspect_ratio_invalid",
+            format!("aspect_ratio must be one of {}", IMAGE_ASPECT_RATIOS.join(", ")),
+        ));
+    }
+    if seed.is_some_and(|seed| !(0..=i32::MAX as i64).contains(&seed)) {
+        errors.push(FieldError::new(
+            "seed",
+            "seed_out_of_range",
+            format!("seed must be between 0 and {}", i32::MAX),
+        ));
+    }
+    if temperature.is_some_and(|temperature| !(0.0..=MAX_TEMPERATURE).contains(&temperature)) {
+        errors.push(FieldError::new(
+            "temperature",
+            "temperature_out_of_range",
+            format!("temperature must be between 0 and {}", MAX_TEMPERATURE),
+        ));
+    }
+    if let Some(modalities) = response_modalities {
+        if modalities.iter().any(|modality| modality != "TEXT" && modality != "IMAGE") {
+            errors.push(FieldError::new(
+                "response_modalities",
+                "response_modality_invalid",
+                "response_modalities may only contain TEXT and IMAGE",
+            ));
+        } else if !modalities.iter().any(|modality| modality == "IMAGE") {
+            errors.push(FieldError::new(
+                "response_modalities",
+                "response_modalities_without_image",
+                "response_modalities must include IMAGE",
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Checks a registration and returns the normalized email.
+pub fn validate_registration(email: &str, password: &str) -> Result<String, ApiError> {
+    let email = normalize_email(email);
+    let mut errors: Vec<FieldError> = check_email(&email).into_iter().collect();
+    errors.extend(check_password(password, Some(&email)));
+
+    if errors.is_empty() {
+        Ok(email)
+    } else {
+        Err(into_api_error(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(errors: &[FieldError]) -> Vec<&'static str> {
+        errors.iter().map(|error| error.code).collect()
+    }
+
+    #[test]
+    fn test_email_syntax_and_normalization() {
+        assert_eq!(normalize_email("  User@Example.COM "), "user@example.com");
+        assert_eq!(check_email("user@example.com"), None);
+        assert_eq!(check_email("a.b+tag@mail.example.co.uk"), None);
+
+        for bad in ["user", "user@", "@example.com", "user@example", "user@@example.com", "us er@example.com", "user@example..com", "user@-example.com"] {
+            assert_eq!(check_email(bad).map(|e| e.code), Some("email_invalid"), "{}", bad);
+        }
+        assert_eq!(check_email("").map(|e| e.code), Some("email_required"));
+        let long = format!("{}@example.com", "a".repeat(250));
+        assert_eq!(check_email(&long).map(|e| e.code), Some("email_too_long"));
+    }
+
+    #[test]
+    fn test_password_policy() {
+        assert!(check_password("correct horse 42", Some("user@example.com")).is_empty());
+        assert_eq!(codes(&check_password("", None)), vec!["password_too_short"]);
+        assert_eq!(codes(&check_password("abc1", None)), vec!["password_too_short"]);
+        assert_eq!(codes(&check_password("abcdefghij", None)), vec!["password_too_weak"]);
+        assert_eq!(codes(&check_password("1234567890", None)), vec!["password_too_weak"]);
+        assert_eq!(codes(&check_password(&format!("a1{}", "x".repeat(71)), None)), vec!["password_too_long"]);
+        assert_eq!(
+            codes(&check_password("User@Example.com1", Some("user@example.com"))),
+            vec!["password_contains_email"]
+        );
+    }
+
+    #[test]
+    fn test_bcrypt_limit_counts_bytes() {
+        // 26 characters but 72 bytes: at the limit. One more byte is over.
+        let at_limit = format!("123{}", "€".repeat(23));
+        assert_eq!(at_limit.len(), MAX_PASSWORD_BYTES);
+        assert!(!codes(&check_password(&at_limit, None)).contains(&"password_too_long"));
+        let over = format!("1234{}", "€".repeat(23));
+        assert!(codes(&check_password(&over, None)).contains(&"password_too_long"));
+    }
+
+    #[test]
+    fn test_registration_reports_every_field() {
+        let error = validate_registration("nope", "short").unwrap_err();
+        assert_eq!(error.code, "validation_failed");
+        let fields = &error.details.unwrap()["fields"];
+        let codes: Vec<&str> = fields.as_array().unwrap().iter().map(|f| f["code"].as_str().unwrap()).collect();
+        assert_eq!(codes, vec!["email_invalid", "password_too_short", "password_too_weak"]);
+
+        assert_eq!(validate_registration(" Me@Example.com ", "hunter22!").unwrap(), "me@example.com");
+    }
+
+    #[test]
+    fn test_image_options() {
+        assert!(check_image_options(None, None, None, None).is_empty());
+        let both = vec!["TEXT".to_string(), "IMAGE".to_string()];
+        assert!(check_image_options(Some("16:9"), Some(0), Some(2.0), Some(&both)).is_empty());
+
+        let errors = check_image_options(Some("2:1"), Some(-1), Some(2.5), Some(&["TEXT".to_string()]));
+        assert_eq!(
+            codes(&errors),
+            vec!["aspect_ratio_invalid", "seed_out_of_range", "temperature_out_of_range", "response_modalities_without_image"]
+        );
+        let errors = check_image_options(None, Some(i32::MAX as i64 + 1), None, Some(&["AUDIO".to_string()]));
+        assert_eq!(codes(&errors), vec!["seed_out_of_range", "response_modality_invalid"]);
+    }
+}
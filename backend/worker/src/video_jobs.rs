@@ -0,0 +1,184 @@
+use serde::Deserialize;
+use serde_json::Value;
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::assets::{asset_url, WEBHOOK_URL_TTL_SECONDS};
+use crate::error::{ApiError, ApiResult};
+use crate::usage::{GenerationKind, GenerationStatus};
+
+/// A Veo operation started on behalf of a user. Clients only ever see `id`;
+/// the upstream operation name stays server-side so it cannot be used to
+/// reach another user's video.
+#[derive(Debug, Deserialize)]
+pub struct VideoJob {
+    pub id: String,
+    pub user_id: i32,
+    pub kind: String,
+    pub operation_name: String,
+    pub prompt: String,
+    pub parameters: String,
+    pub status: String,
+    pub error_code: Option<String>,
+    pub generation_id: Option<String>,
+    pub asset_id: Option<String>,
+    pub callback_url: Option<String>,
+    pub origin: Option<String>,
+    pub created_at: String,
+}
+
+/// Status of a job whose finished video one caller has claimed and is
+/// downloading. Only `video_jobs` uses it; the ledger stays `pending`.
+const PROCESSING: &str = "processing";
+
+/// A claim older than this is taken to have died with its worker, and the
+/// job can be claimed again.
+const CLAIM_MINUTES: u32 = 5;
+
+impl VideoJob {
+    /// Whether the job has no result yet: Veo is still working, or a
+    /// finished video is being collected.
+    pub fn is_pending(&self) -> bool {
+        self.status == GenerationStatus::Pending.as_str() || self.status == PROCESSING
+    }
+
+    /// Signed URL of a stored asset on the origin that started the job.
+    /// Lets the scheduled handler, which has no request, build the same
+    /// links `/video_status` would.
+    pub fn asset_url(&self, env: &Env, asset_id: &str) -> Option<String> {
+        let origin = Url::parse(self.origin.as_deref()?).ok()?;
+        Some(asset_url(env, &origin, asset_id, WEBHOOK_URL_TTL_SECONDS))
+    }
+}
+
+/// An operation Veo has accepted, about to be recorded as a job.
+pub struct NewVideoJob<'a> {
+    pub user_id: i32,
+    pub kind: GenerationKind,
+    pub operation_name: &'a str,
+    pub prompt: &'a str,
+    pub parameters: &'a Value,
+    pub generation_id: Option<&'a str>,
+    pub callback_url: Option<&'a str>,
+    pub origin: &'a str,
+}
+
+/// Records a newly started operation and returns the job id handed to the
+/// client.
+pub async fn create(env: &Env, job: &NewVideoJob<'_>) -> ApiResult<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let parameters = job.parameters.to_string();
+
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "INSERT INTO video_jobs (id, user_id, kind, operation_name, prompt, parameters, status, generation_id, callback_url, origin, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, 'pending', ?, ?, ?, datetime('now'), datetime('now'))",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(&id),
+        D1Type::Integer(job.user_id),
+        D1Type::Text(job.kind.as_str()),
+        D1Type::Text(job.operation_name),
+        D1Type::Text(job.prompt),
+        D1Type::Text(&parameters),
+        job.generation_id.map(D1Type::Text).unwrap_or(D1Type::Null),
+        job.callback_url.map(D1Type::Text).unwrap_or(D1Type::Null),
+        D1Type::Text(job.origin),
+    ])?;
+    query.run().await?;
+
+    Ok(id)
+}
+
+const COLUMNS: &str = "id, user_id, kind, operation_name, prompt, parameters, status, error_code, generation_id, asset_id, callback_url, origin, created_at";
+
+/// Looks a job up for its owner. Jobs belonging to someone else are
+/// indistinguishable from missing ones.
+pub async fn find(env: &Env, user_id: i32, id: &str) -> ApiResult<VideoJob> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare(format!("SELECT {} FROM video_jobs WHERE id = ? AND user_id = ?", COLUMNS));
+    let query = statement.bind_refs(&[D1Type::Text(id), D1Type::Integer(user_id)])?;
+    query
+        .first(None)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Video job not found"))
+}
+
+/// Claims a job whose video Veo has finished, so only one of a cron run
+/// and a user poll downloads and stores it. Claims left behind by a worker
+/// that died can be taken over after `CLAIM_MINUTES`. Returns whether this
+/// caller got the claim.
+pub async fn claim(env: &Env, job: &VideoJob) -> Result<bool> {
+    let stale = format!("-{} minutes", CLAIM_MINUTES);
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "UPDATE video_jobs SET status = ?, updated_at = datetime('now') \
+         WHERE id = ? AND (status = 'pending' OR (status = ? AND updated_at < datetime('now', ?)))",
+    );
+    let query = statement.bind_refs(&[D1Type::Text(PROCESSING), D1Type::Text(&job.id), D1Type::Text(PROCESSING), D1Type::Text(&stale)])?;
+
+    let result = query.run().await?;
+    Ok(result.meta()?.and_then(|meta| meta.changes).unwrap_or(0) > 0)
+}
+
+/// Moves an unfinished job to its final state. The guard keeps concurrent
+/// pollers from overwriting each other's result; a stored video is only
+/// accepted from the caller holding the claim.
+pub async fn finish(env: &Env, job: &VideoJob, status: GenerationStatus, error_code: Option<&str>, asset_id: Option<&str>) -> Result<bool> {
+    let guard = match asset_id {
+        Some(_) => "status = 'processing'",
+        None => "status IN ('pending', 'processing')",
+    };
+    let db = env.d1("DB")?;
+    let statement = db.prepare(format!(
+        "UPDATE video_jobs SET status = ?, error_code = ?, asset_id = ?, updated_at = datetime('now') \
+         WHERE id = ? AND {}",
+        guard
+    ));
+    let query = statement.bind_refs(&[
+        D1Type::Text(status.as_str()),
+        error_code.map(D1Type::Text).unwrap_or(D1Type::Null),
+        asset_id.map(D1Type::Text).unwrap_or(D1Type::Null),
+        D1Type::Text(&job.id),
+    ])?;
+
+    let result = query.run().await?;
+    Ok(result.meta()?.and_then(|meta| meta.changes).unwrap_or(0) > 0)
+}
+
+/// Pending jobs across all users, least recently checked first, for the
+/// scheduled handler to advance. Includes abandoned claims.
+pub async fn due(env: &Env, limit: i32) -> Result<Vec<VideoJob>> {
+    let stale = format!("-{} minutes", CLAIM_MINUTES);
+    let db = env.d1("DB")?;
+    let statement = db.prepare(format!(
+        "SELECT {} FROM video_jobs \
+         WHERE status = 'pending' OR (status = ? AND updated_at < datetime('now', ?)) ORDER BY updated_at LIMIT ?",
+        COLUMNS
+    ));
+    let query = statement.bind_refs(&[D1Type::Text(PROCESSING), D1Type::Text(&stale), D1Type::Integer(limit)])?;
+    query.all().await?.results()
+}
+
+/// Unfinished jobs started more than `max_age_hours` ago. Veo discards
+/// operations after a couple of days, so these will never complete.
+pub async fn stale(env: &Env, max_age_hours: u32, limit: i32) -> Result<Vec<VideoJob>> {
+    let cutoff = format!("-{} hours", max_age_hours);
+    let db = env.d1("DB")?;
+    let statement = db.prepare(format!(
+        "SELECT {} FROM video_jobs WHERE status IN ('pending', 'processing') AND created_at < datetime('now', ?) ORDER BY created_at LIMIT ?",
+        COLUMNS
+    ));
+    let query = statement.bind_refs(&[D1Type::Text(&cutoff), D1Type::Integer(limit)])?;
+    query.all().await?.results()
+}
+
+/// Marks a still-pending job as checked so the next scheduled run moves on
+/// to others first.
+pub async fn touch(env: &Env, job: &VideoJob) -> Result<()> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("UPDATE video_jobs SET updated_at = datetime('now') WHERE id = ? AND status = 'pending'");
+    let query = statement.bind_refs(&[D1Type::Text(&job.id)])?;
+    query.run().await?;
+    Ok(())
+}
@@ -0,0 +1,301 @@
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use worker::d1::D1Type;
+use worker::*;
+
+use crate::assets::Asset;
+use crate::authorize;
+use crate::error::{ApiError, ApiResult};
+use crate::usage::GenerationStatus;
+use crate::video_jobs::VideoJob;
+
+pub const SIGNATURE_HEADER: &str = "X-GeminiPocket-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-GeminiPocket-Timestamp";
+pub const EVENT_HEADER: &str = "X-GeminiPocket-Event";
+
+/// Seconds to wait before each retry. A delivery is marked failed once the
+/// schedule is exhausted, roughly eight hours after the first attempt.
+const RETRY_DELAYS: [u64; 6] = [30, 120, 600, 1800, 7200, 21600];
+
+/// How many due deliveries one scheduled run works through.
+const RETRY_BATCH: i32 = 50;
+
+/// Accepts `https` URLs, plus plain `http` to localhost for development.
+pub fn validate_callback_url(callback_url: &str) -> ApiResult<()> {
+    let url = Url::parse(callback_url).map_err(|_| ApiError::bad_request("callback_url must be an absolute URL"))?;
+    let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if local => Ok(()),
+        _ => Err(ApiError::bad_request("callback_url must use https")),
+    }
+}
+
+fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `"{timestamp}.{body}"`.
+/// Binding the timestamp into the MAC lets receivers reject replays.
+pub fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Delay before the next attempt, given how many attempts have been made.
+fn retry_delay(attempts: u32) -> Option<u64> {
+    RETRY_DELAYS.get(attempts.saturating_sub(1) as usize).copied()
+}
+
+async fn secret_for(env: &Env, user_id: i32) -> Result<Option<String>> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("SELECT webhook_secret FROM users WHERE id = ?");
+    let query = statement.bind_refs(&[D1Type::Integer(user_id)])?;
+    query.first::<Option<String>>(Some("webhook_secret")).await.map(Option::flatten)
+}
+
+async fn set_secret(env: &Env, user_id: i32, secret: &str) -> Result<()> {
+    let db = env.d1("DB")?;
+    let statement = db.prepare("UPDATE users SET webhook_secret = ? WHERE id = ?");
+    let query = statement.bind_refs(&[D1Type::Text(secret), D1Type::Integer(user_id)])?;
+    query.run().await?;
+    Ok(())
+}
+
+/// Returns the user's signing secret, creating one on first use so that a
+/// job with a `callback_url` always has something to sign with.
+pub async fn ensure_secret(env: &Env, user_id: i32) -> ApiResult<String> {
+    if let Some(secret) = secret_for(env, user_id).await? {
+        return Ok(secret);
+    }
+    let secret = generate_secret();
+    set_secret(env, user_id, &secret).await?;
+    Ok(secret)
+}
+
+/// Checks a `callback_url` before a video job is started and makes sure
+/// the caller has a secret to sign its callback with.
+pub async fn prepare_callback(env: &Env, user_id: i32, callback_url: Option<&str>) -> ApiResult<()> {
+    if let Some(callback_url) = callback_url {
+        validate_callback_url(callback_url)?;
+        ensure_secret(env, user_id).await?;
+    }
+    Ok(())
+}
+
+/// `GET /webhook_secret` — the caller's signing secret.
+pub async fn handle_get_secret(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let secret = ensure_secret(&ctx.env, user.id).await?;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "webhook_secret": secret,
+    }))?)
+}
+
+/// `POST /webhook_secret` — replaces the caller's signing secret. Deliveries
+/// still queued for retry are signed with the new one.
+pub async fn handle_rotate_secret(req: Request, ctx: RouteContext<Context>) -> ApiResult<Response> {
+    let user = authorize(&req, &ctx.env).await?;
+    let secret = generate_secret();
+    set_secret(&ctx.env, user.id, &secret).await?;
+
+    Ok(Response::from_json(&json!({
+        "success": true,
+        "webhook_secret": secret,
+    }))?)
+}
+
+#[derive(Deserialize)]
+struct Delivery {
+    id: String,
+    user_id: i32,
+    url: String,
+    event: String,
+    payload: String,
+    attempts: u32,
+}
+
+/// Queues the `video_job.succeeded` / `video_job.failed` callback for a job
+/// that has just finished. It is due at once, and the scheduled handler
+/// delivers it with the retries, so a slow receiver never holds up a
+/// `/video_status` poll or the batch of jobs being advanced. Failures are
+/// logged.
+pub async fn notify_job_finished(env: &Env, job: &VideoJob, status: GenerationStatus, error_code: Option<&str>, asset: Option<&Asset>) {
+    let Some(callback_url) = &job.callback_url else {
+        return;
+    };
+
+    let event = match status {
+        GenerationStatus::Succeeded => "video_job.succeeded",
+        _ => "video_job.failed",
+    };
+    let payload = json!({
+        "event": event,
+        "job_id": job.id,
+        "status": status.as_str(),
+        "error_code": error_code,
+        "asset_id": asset.map(|asset| asset.id.as_str()),
+        "video_url": asset.and_then(|asset| job.asset_url(env, &asset.id)),
+        "mime_type": asset.map(|asset| asset.content_type.as_str()),
+    });
+
+    let result = async {
+        let db = env.d1("DB")?;
+        let statement = db.prepare(
+            "INSERT INTO webhook_deliveries (id, job_id, user_id, url, event, payload, status, attempts, next_attempt_at, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, 'pending', 0, datetime('now'), datetime('now'), datetime('now'))",
+        );
+        let query = statement.bind_refs(&[
+            D1Type::Text(&uuid::Uuid::new_v4().to_string()),
+            D1Type::Text(&job.id),
+            D1Type::Integer(job.user_id),
+            D1Type::Text(callback_url),
+            D1Type::Text(event),
+            D1Type::Text(&payload.to_string()),
+        ])?;
+        query.run().await?;
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to queue webhook for job {}: {}", job.id, e);
+    }
+}
+
+async fn send(delivery: &Delivery, secret: &str) -> std::result::Result<u16, String> {
+    let timestamp = Date::now().as_millis() / 1000;
+
+    let headers = Headers::new();
+    let set = |name: &str, value: &str| headers.set(name, value).map_err(|e| e.to_string());
+    set("Content-Type", "application/json")?;
+    set("User-Agent", "GeminiPocket-Webhooks/1.0")?;
+    set(EVENT_HEADER, &delivery.event)?;
+    set(TIMESTAMP_HEADER, &timestamp.to_string())?;
+    set(SIGNATURE_HEADER, &sign(secret, timestamp, &delivery.payload))?;
+
+    let mut init = RequestInit::new();
+    init.with_method(Method::Post)
+        .with_headers(headers)
+        .with_body(Some(delivery.payload.clone().into()));
+
+    let request = Request::new_with_init(&delivery.url, &init).map_err(|e| e.to_string())?;
+    let response = Fetch::Request(request).send().await.map_err(|e| e.to_string())?;
+    Ok(response.status_code())
+}
+
+/// Makes one delivery attempt and records the outcome, scheduling the next
+/// retry on failure.
+async fn attempt(env: &Env, delivery: &Delivery) -> Result<()> {
+    let outcome = match secret_for(env, delivery.user_id).await? {
+        Some(secret) => send(delivery, &secret).await,
+        None => Err("no webhook secret".to_string()),
+    };
+
+    let attempts = delivery.attempts + 1;
+    let (status_code, error) = match &outcome {
+        Ok(code) if (200..300).contains(code) => (Some(*code), None),
+        Ok(code) => (Some(*code), Some(format!("HTTP {}", code))),
+        Err(e) => (None, Some(e.clone())),
+    };
+    let (status, delay) = match (&error, retry_delay(attempts)) {
+        (None, _) => ("delivered", 0),
+        (Some(_), Some(delay)) => ("pending", delay),
+        (Some(_), None) => ("failed", 0),
+    };
+    if let Some(error) = &error {
+        console_log!("Webhook {} attempt {} failed: {}", delivery.id, attempts, error);
+    }
+
+    let next_attempt = format!("+{} seconds", delay);
+    let db = env.d1("DB")?;
+    let statement = db.prepare(
+        "UPDATE webhook_deliveries SET status = ?, attempts = ?, last_status_code = ?, last_error = ?, \
+         next_attempt_at = datetime('now', ?), updated_at = datetime('now') WHERE id = ?",
+    );
+    let query = statement.bind_refs(&[
+        D1Type::Text(status),
+        D1Type::Integer(attempts as i32),
+        status_code.map(|code| D1Type::Integer(code as i32)).unwrap_or(D1Type::Null),
+        error.as_deref().map(D1Type::Text).unwrap_or(D1Type::Null),
+        D1Type::Text(&next_attempt),
+        D1Type::Text(&delivery.id),
+    ])?;
+    query.run().await?;
+    Ok(())
+}
+
+/// Makes the first attempt at newly queued deliveries and retries those
+/// whose backoff has elapsed. Called from the scheduled handler after video
+/// jobs are advanced, so callbacks for jobs it finished go out the same run.
+pub async fn retry_due(env: &Env) {
+    let result = async {
+        let db = env.d1("DB")?;
+        let statement = db.prepare(
+            "SELECT id, user_id, url, event, payload, attempts FROM webhook_deliveries \
+             WHERE status = 'pending' AND next_attempt_at <= datetime('now') \
+             ORDER BY next_attempt_at LIMIT ?",
+        );
+        let query = statement.bind_refs(&[D1Type::Integer(RETRY_BATCH)])?;
+        let deliveries: Vec<Delivery> = query.all().await?.results()?;
+
+        for delivery in &deliveries {
+            if let Err(e) = attempt(env, delivery).await {
+                console_log!("Failed to retry webhook {}: {}", delivery.id, e);
+            }
+        }
+        Ok::<(), worker::Error>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        console_log!("Failed to load due webhooks: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_covers_timestamp_and_body() {
+        let body = r#"{"event":"video_job.succeeded"}"#;
+        assert_eq!(
+            sign("whsec_test", 1700000000, body),
+            "sha256=5c251bb1d32b9b7a561fcce60847fc1434a56c36f8bdd53c2fc5fb8d72d5647d"
+        );
+        assert_ne!(sign("whsec_test", 1700000001, body), sign("whsec_test", 1700000000, body));
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_then_gives_up() {
+        assert_eq!(retry_delay(1), Some(30));
+        assert_eq!(retry_delay(2), Some(120));
+        assert_eq!(retry_delay(6), Some(21600));
+        assert_eq!(retry_delay(7), None);
+    }
+
+    #[test]
+    fn test_validate_callback_url() {
+        assert!(validate_callback_url("https://example.com/hooks/video").is_ok());
+        assert!(validate_callback_url("http://localhost:8080/hook").is_ok());
+        assert!(validate_callback_url("http://example.com/hook").is_err());
+        assert!(validate_callback_url("ftp://example.com").is_err());
+        assert!(validate_callback_url("not a url").is_err());
+    }
+}
@@ -1,11 +1,15 @@
 use anyhow::Result;
 use base64::{engine::general_purpose::STANDARD, Engine};
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
-use std::path::Path;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use crate::types::{ApiInfo, ApiResponse, AuthResponse, EditRequest, EditVideoRequest, GenerateRequest, GenerateVideoRequest, HealthResponse, LoginRequest, RegisterRequest, VideoOperationResponse, VideoStatusResponse};
+use crate::types::{AccountResponse, AdminUserDetail, AdminUserList, AdminUserResponse, ApiError, ApiInfo, ApiKeyList, ApiResponse, AuthResponse, CreateKeyRequest, CreateSessionRequest, CreatedKeyResponse, DeleteAccountRequest, DisableUserRequest, ForgotPasswordRequest, MessageResponse, ResetPasswordRequest, EditRequest, EditVideoRequest, ErrorEnvelope, GenerateRequest, GenerateVideoRequest, HealthResponse, HistoryDetailResponse, HistoryPage, ImageSettings, LoginRequest, ModelsResponse, RegisterRequest, ResetQuotaResponse, RevokeKeysResponse, SessionResponse, TurnRequest, TurnResponse, UpdateAccountRequest, UpdateAccountResponse, UsageReport, VideoOperationResponse, VideoStatusResponse, WebhookSecretResponse};
+use crate::utils::detect_mime_type;
 
 pub struct GeminiClient {
     client: Client,
@@ -30,6 +34,13 @@ impl GeminiClient {
         }
     }
 
+    fn authorized(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.header("Authorization", format!("Bearer {}", api_key)),
+            None => request,
+        }
+    }
+
     pub async fn register(&self, email: &str, password: &str) -> Result<AuthResponse> {
         let response = self
             .client
@@ -41,14 +52,7 @@ impl GeminiClient {
             .send()
             .await?;
 
-        if response.status().is_success() {
-            Ok(response.json().await?)
-        } else {
-            Err(anyhow::anyhow!(
-                "Registration failed with status: {}",
-                response.status()
-            ))
-        }
+        parse_response(response).await
     }
 
     pub async fn login(&self, email: &str, password: &str) -> Result<AuthResponse> {
@@ -58,28 +62,162 @@ impl GeminiClient {
             .json(&LoginRequest {
                 email: email.to_string(),
                 password: password.to_string(),
+                key_name: Some("geminipocket-cli".to_string()),
             })
             .send()
             .await?;
 
-        if response.status().is_success() {
-            Ok(response.json().await?)
-        } else {
-            Err(anyhow::anyhow!(
-                "Login failed with status: {}",
-                response.status()
-            ))
-        }
+        parse_response(response).await
+    }
+
+    pub async fn forgot_password(&self, email: &str) -> Result<MessageResponse> {
+        let response = self
+            .client
+            .post(format!("{}/password/forgot", self.api_url))
+            .json(&ForgotPasswordRequest { email: email.to_string() })
+            .send()
+            .await?;
+
+        parse_response(response).await
+    }
+
+    pub async fn reset_password(&self, token: &str, password: &str) -> Result<MessageResponse> {
+        let response = self
+            .client
+            .post(format!("{}/password/reset", self.api_url))
+            .json(&ResetPasswordRequest {
+                token: token.to_string(),
+                password: password.to_string(),
+            })
+            .send()
+            .await?;
+
+        parse_response(response).await
+    }
+
+    pub async fn account(&self) -> Result<AccountResponse> {
+        let request = self.client.get(format!("{}/me", self.api_url));
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
     }
 
-    pub async fn generate_image(&self, prompt: &str) -> Result<ApiResponse> {
-        let spinner = create_spinner("Generating image...");
+    pub async fn update_account(&self, update: &UpdateAccountRequest) -> Result<UpdateAccountResponse> {
+        let request = self.client.patch(format!("{}/me", self.api_url)).json(update);
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn delete_account(&self, password: &str) -> Result<()> {
+        let request = self
+            .client
+            .delete(format!("{}/me", self.api_url))
+            .json(&DeleteAccountRequest { password: password.to_string() });
+
+        let response = self.authorized(request).send().await?;
+        parse_response::<serde_json::Value>(response).await?;
+        Ok(())
+    }
+
+    pub async fn admin_users(&self, query: &[(&str, String)]) -> Result<AdminUserList> {
+        let request = self
+            .client
+            .get(format!("{}/admin/users", self.api_url))
+            .query(query);
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn admin_user(&self, id: i32) -> Result<AdminUserDetail> {
+        let request = self.client.get(format!("{}/admin/users/{}", self.api_url, id));
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn admin_disable_user(&self, id: i32, reason: Option<String>) -> Result<AdminUserResponse> {
+        let request = self
+            .client
+            .post(format!("{}/admin/users/{}/disable", self.api_url, id))
+            .json(&DisableUserRequest { reason });
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn admin_enable_user(&self, id: i32) -> Result<AdminUserResponse> {
+        let request = self.client.post(format!("{}/admin/users/{}/enable", self.api_url, id));
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn admin_reset_quota(&self, id: i32) -> Result<ResetQuotaResponse> {
+        let request = self.client.post(format!("{}/admin/users/{}/reset_quota", self.api_url, id));
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    /// Revokes one key of the user, or all of them when `key_id` is `None`.
+    pub async fn admin_revoke_keys(&self, id: i32, key_id: Option<&str>) -> Result<RevokeKeysResponse> {
+        let url = match key_id {
+            Some(key_id) => format!("{}/admin/users/{}/keys/{}", self.api_url, id, key_id),
+            None => format!("{}/admin/users/{}/keys", self.api_url, id),
+        };
+
+        let response = self.authorized(self.client.delete(url)).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn admin_user_usage(&self, id: i32, days: u32) -> Result<UsageReport> {
+        let request = self
+            .client
+            .get(format!("{}/admin/users/{}/usage", self.api_url, id))
+            .query(&[("days", days)]);
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn create_key(&self, name: &str) -> Result<CreatedKeyResponse> {
+        let request = self
+            .client
+            .post(format!("{}/keys", self.api_url))
+            .json(&CreateKeyRequest { name: name.to_string() });
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn list_keys(&self) -> Result<ApiKeyList> {
+        let request = self.client.get(format!("{}/keys", self.api_url));
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn revoke_key(&self, id: &str) -> Result<()> {
+        let request = self.client.delete(format!("{}/keys/{}", self.api_url, id));
+
+        let response = self.authorized(request).send().await?;
+        parse_response::<serde_json::Value>(response).await?;
+        Ok(())
+    }
+
+    pub async fn generate_image(&self, prompt: &str, model: Option<&str>, count: Option<u32>, settings: &ImageSettings) -> Result<ApiResponse> {
+        let spinner = create_spinner(if count.unwrap_or(1) > 1 { "Generating images..." } else { "Generating image..." });
 
         let mut request = self
             .client
             .post(format!("{}/generate", self.api_url))
             .json(&GenerateRequest {
                 prompt: prompt.to_string(),
+                model: model.map(|s| s.to_string()),
+                count,
+                settings: settings.clone(),
             });
 
         if let Some(api_key) = &self.api_key {
@@ -90,41 +228,25 @@ impl GeminiClient {
 
         spinner.finish_and_clear();
 
-        if response.status().is_success() {
-            Ok(response.json().await?)
-        } else {
-            Err(anyhow::anyhow!(
-                "API request failed with status: {}",
-                response.status()
-            ))
-        }
+        parse_response(response).await
     }
 
-    pub async fn edit_image(&self, image_path: &Path, prompt: &str) -> Result<ApiResponse> {
-        let spinner = create_spinner("Reading image...");
-
-        let image_data = std::fs::read(image_path)?;
-        let image_base64 = STANDARD.encode(&image_data);
+    pub async fn edit_image(&self, image_paths: &[PathBuf], prompt: &str, model: Option<&str>, settings: &ImageSettings) -> Result<ApiResponse> {
+        let spinner = create_spinner("Reading images...");
 
-        let mime_type = match image_path.extension().and_then(|s| s.to_str()) {
-            Some("png") => "image/png",
-            Some("jpg") | Some("jpeg") => "image/jpeg",
-            Some("gif") => "image/gif",
-            Some("webp") => "image/webp",
-            _ => "image/jpeg",
-        }
-        .to_string();
+        let fields = EditRequest {
+            prompt: prompt.to_string(),
+            model: model.map(|s| s.to_string()),
+            settings: settings.clone(),
+        };
+        let form = upload_form(&fields, image_paths)?;
 
         spinner.set_message("Editing image...");
 
         let mut request = self
             .client
             .post(format!("{}/edit", self.api_url))
-            .json(&EditRequest {
-                prompt: prompt.to_string(),
-                image: image_base64,
-                mime_type,
-            });
+            .multipart(form);
 
         if let Some(api_key) = &self.api_key {
             request = request.header("Authorization", format!("Bearer {}", api_key));
@@ -134,14 +256,7 @@ impl GeminiClient {
 
         spinner.finish_and_clear();
 
-        if response.status().is_success() {
-            Ok(response.json().await?)
-        } else {
-            Err(anyhow::anyhow!(
-                "API request failed with status: {}",
-                response.status()
-            ))
-        }
+        parse_response(response).await
     }
 
     pub async fn health(&self) -> Result<HealthResponse> {
@@ -158,6 +273,43 @@ impl GeminiClient {
         }
     }
 
+    /// Starts an edit session, optionally from `image_paths`.
+    pub async fn create_session(&self, image_paths: &[PathBuf], model: Option<&str>) -> Result<SessionResponse> {
+        let fields = CreateSessionRequest {
+            model: model.map(|s| s.to_string()),
+        };
+        let request = self.client.post(format!("{}/sessions", self.api_url));
+        let request = if image_paths.is_empty() {
+            request.json(&fields)
+        } else {
+            request.multipart(upload_form(&fields, image_paths)?)
+        };
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn session_turn(&self, session_id: &str, prompt: &str, settings: &ImageSettings) -> Result<TurnResponse> {
+        let spinner = create_spinner("Editing image...");
+
+        let request = self
+            .client
+            .post(format!("{}/sessions/{}/turns", self.api_url, session_id))
+            .json(&TurnRequest {
+                prompt: prompt.to_string(),
+                settings: settings.clone(),
+            });
+
+        let response = self.authorized(request).send().await;
+        spinner.finish_and_clear();
+        parse_response(response?).await
+    }
+
+    pub async fn models(&self) -> Result<ModelsResponse> {
+        let response = self.client.get(format!("{}/models", self.api_url)).send().await?;
+        parse_response(response).await
+    }
+
     pub async fn info(&self) -> Result<ApiInfo> {
         let response = self.client.get(&self.api_url).send().await?;
 
@@ -171,17 +323,21 @@ impl GeminiClient {
     pub async fn generate_video(
         &self,
         prompt: &str,
+        model: Option<&str>,
         negative_prompt: Option<&str>,
         aspect_ratio: Option<&str>,
         resolution: Option<&str>,
+        callback_url: Option<&str>,
     ) -> Result<String> {
         let spinner = create_spinner("Starting video generation...");
 
         let request_body = GenerateVideoRequest {
             prompt: prompt.to_string(),
+            model: model.map(|s| s.to_string()),
             negative_prompt: negative_prompt.map(|s| s.to_string()),
             aspect_ratio: aspect_ratio.map(|s| s.to_string()),
             resolution: resolution.map(|s| s.to_string()),
+            callback_url: callback_url.map(|s| s.to_string()),
         };
 
         let mut request = self
@@ -196,65 +352,50 @@ impl GeminiClient {
         let response = request.send().await?;
         spinner.finish_and_clear();
 
-        if response.status().is_success() {
-            let operation_response: VideoOperationResponse = response.json().await?;
-            if operation_response.success {
-                if let Some(operation_name) = operation_response.operation_name {
-                    Ok(operation_name)
-                } else {
-                    Err(anyhow::anyhow!("No operation name in response"))
-                }
+        let operation_response: VideoOperationResponse = parse_response(response).await?;
+        if operation_response.success {
+            if let Some(job_id) = operation_response.job_id {
+                Ok(job_id)
             } else {
-                Err(anyhow::anyhow!(
-                    "Video generation failed: {}",
-                    operation_response.error.unwrap_or_else(|| "Unknown error".to_string())
-                ))
+                Err(anyhow::anyhow!("No job id in response"))
             }
         } else {
             Err(anyhow::anyhow!(
-                "API request failed with status: {}",
-                response.status()
+                "Video generation failed: {}",
+                operation_response.error.unwrap_or_else(|| "Unknown error".to_string())
             ))
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn edit_video(
         &self,
         image_path: &Path,
         prompt: &str,
+        model: Option<&str>,
         negative_prompt: Option<&str>,
         aspect_ratio: Option<&str>,
         resolution: Option<&str>,
+        callback_url: Option<&str>,
     ) -> Result<String> {
         let spinner = create_spinner("Reading image for video editing...");
 
-        let image_data = std::fs::read(image_path)?;
-        let image_base64 = STANDARD.encode(&image_data);
-
-        let mime_type = match image_path.extension().and_then(|s| s.to_str()) {
-            Some("png") => "image/png",
-            Some("jpg") | Some("jpeg") => "image/jpeg",
-            Some("gif") => "image/gif",
-            Some("webp") => "image/webp",
-            _ => "image/jpeg",
-        }
-        .to_string();
-
-        spinner.set_message("Starting video editing...");
-
-        let request_body = EditVideoRequest {
+        let fields = EditVideoRequest {
             prompt: prompt.to_string(),
-            image: image_base64,
-            mime_type,
+            model: model.map(|s| s.to_string()),
             negative_prompt: negative_prompt.map(|s| s.to_string()),
             aspect_ratio: aspect_ratio.map(|s| s.to_string()),
             resolution: resolution.map(|s| s.to_string()),
+            callback_url: callback_url.map(|s| s.to_string()),
         };
+        let form = upload_form(&fields, &[image_path.to_path_buf()])?;
+
+        spinner.set_message("Starting video editing...");
 
         let mut request = self
             .client
             .post(format!("{}/edit_video", self.api_url))
-            .json(&request_body);
+            .multipart(form);
 
         if let Some(api_key) = &self.api_key {
             request = request.header("Authorization", format!("Bearer {}", api_key));
@@ -263,32 +404,25 @@ impl GeminiClient {
         let response = request.send().await?;
         spinner.finish_and_clear();
 
-        if response.status().is_success() {
-            let operation_response: VideoOperationResponse = response.json().await?;
-            if operation_response.success {
-                if let Some(operation_name) = operation_response.operation_name {
-                    Ok(operation_name)
-                } else {
-                    Err(anyhow::anyhow!("No operation name in response"))
-                }
+        let operation_response: VideoOperationResponse = parse_response(response).await?;
+        if operation_response.success {
+            if let Some(job_id) = operation_response.job_id {
+                Ok(job_id)
             } else {
-                Err(anyhow::anyhow!(
-                    "Video editing failed: {}",
-                    operation_response.error.unwrap_or_else(|| "Unknown error".to_string())
-                ))
+                Err(anyhow::anyhow!("No job id in response"))
             }
         } else {
             Err(anyhow::anyhow!(
-                "API request failed with status: {}",
-                response.status()
+                "Video editing failed: {}",
+                operation_response.error.unwrap_or_else(|| "Unknown error".to_string())
             ))
         }
     }
 
-    pub async fn check_video_status(&self, operation_name: &str) -> Result<VideoStatusResponse> {
+    pub async fn check_video_status(&self, job_id: &str) -> Result<VideoStatusResponse> {
         let mut request = self
             .client
-            .get(format!("{}/video_status/{}", self.api_url, operation_name));
+            .get(format!("{}/video_status/{}", self.api_url, job_id));
 
         if let Some(api_key) = &self.api_key {
             request = request.header("Authorization", format!("Bearer {}", api_key));
@@ -296,29 +430,92 @@ impl GeminiClient {
 
         let response = request.send().await?;
 
-        if response.status().is_success() {
-            Ok(response.json().await?)
-        } else {
-            Err(anyhow::anyhow!(
-                "Status check failed with status: {}",
-                response.status()
-            ))
+        parse_response(response).await
+    }
+
+    /// Fetches the webhook signing secret, or replaces it when `rotate` is set.
+    pub async fn webhook_secret(&self, rotate: bool) -> Result<WebhookSecretResponse> {
+        let url = format!("{}/webhook_secret", self.api_url);
+        let request = if rotate { self.client.post(url) } else { self.client.get(url) };
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn history(&self, query: &[(&str, String)]) -> Result<HistoryPage> {
+        let request = self
+            .client
+            .get(format!("{}/history", self.api_url))
+            .query(query);
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    pub async fn history_item(&self, id: &str) -> Result<HistoryDetailResponse> {
+        let request = self.client.get(format!("{}/history/{}", self.api_url, id));
+
+        let response = self.authorized(request).send().await?;
+        parse_response(response).await
+    }
+
+    /// Downloads a stored output from `/assets/{id}`.
+    pub async fn download_asset(&self, asset_url: &str) -> Result<Vec<u8>> {
+        let spinner = create_spinner("Downloading...");
+        let response = self.authorized(self.client.get(asset_url)).send().await?;
+
+        if !response.status().is_success() {
+            spinner.finish_and_clear();
+            parse_response::<serde_json::Value>(response).await?;
+            return Err(anyhow::anyhow!("Failed to download asset"));
+        }
+
+        let bytes = response.bytes().await?;
+        spinner.finish_and_clear();
+        Ok(bytes.to_vec())
+    }
+
+    /// The image from a `/generate` or `/edit` response, decoded from the
+    /// inline base64 when present and otherwise fetched from its asset URL.
+    pub async fn image_bytes(&self, response: &ApiResponse) -> Result<Vec<u8>> {
+        match (&response.image, &response.asset_url) {
+            (Some(image), _) => Ok(STANDARD.decode(image)?),
+            (None, Some(asset_url)) => self.download_asset(asset_url).await,
+            (None, None) => Err(anyhow::anyhow!("No image in response")),
         }
     }
 
-    pub async fn poll_video_completion(&self, operation_name: &str) -> Result<String> {
+    /// The bytes of every image in the response, in order. Servers that
+    /// predate `images` only describe one.
+    pub async fn all_image_bytes(&self, response: &ApiResponse) -> Result<Vec<Vec<u8>>> {
+        if response.images.is_empty() {
+            return Ok(vec![self.image_bytes(response).await?]);
+        }
+
+        let mut all = Vec::with_capacity(response.images.len());
+        for item in &response.images {
+            let bytes = match &item.image {
+                Some(image) => STANDARD.decode(image)?,
+                None => self.download_asset(&item.asset_url).await?,
+            };
+            all.push(bytes);
+        }
+        Ok(all)
+    }
+
+    pub async fn poll_video_completion(&self, job_id: &str) -> Result<String> {
         let spinner = create_spinner("Generating video...");
 
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
 
-            match self.check_video_status(operation_name).await {
+            match self.check_video_status(job_id).await {
                 Ok(status) => {
                     if status.success {
                         if let Some(true) = status.done {
-                            if let Some(video_uri) = status.video_uri {
+                            if let Some(video_url) = status.video_url {
                                 spinner.finish_and_clear();
-                                return Ok(video_uri);
+                                return Ok(video_url);
                             } else {
                                 spinner.finish_and_clear();
                                 return Err(anyhow::anyhow!("Video generation completed but no URI provided"));
@@ -343,6 +540,94 @@ impl GeminiClient {
     }
 }
 
+/// Decodes a successful response body, or turns the worker's error envelope
+/// into an [`ApiError`] so callers can match on its code.
+async fn parse_response<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response.json().await?);
+    }
+
+    let text = response.text().await.unwrap_or_default();
+    match serde_json::from_str::<ErrorEnvelope>(&text) {
+        Ok(envelope) => Err(ApiError {
+            status: status.as_u16(),
+            code: envelope.error.code,
+            message: envelope.error.message,
+            retryable: envelope.error.retryable,
+            details: envelope.error.details,
+        }
+        .into()),
+        Err(_) => Err(anyhow::anyhow!("API request failed with status: {}", status)),
+    }
+}
+
+/// The server refuses input images over this many bytes in total.
+const MAX_UPLOAD_BYTES: u64 = 15 * 1024 * 1024;
+
+/// Refuses images over [`MAX_UPLOAD_BYTES`] in total before anything is read.
+fn check_upload_size(image_paths: &[PathBuf]) -> Result<()> {
+    let total: u64 = image_paths
+        .iter()
+        .map(|path| std::fs::metadata(path).map(|metadata| metadata.len()))
+        .sum::<std::io::Result<u64>>()?;
+    if total > MAX_UPLOAD_BYTES {
+        anyhow::bail!(
+            "Input images are {:.1} MiB; the limit is {} MiB",
+            total as f64 / (1024.0 * 1024.0),
+            MAX_UPLOAD_BYTES / (1024 * 1024)
+        );
+    }
+    Ok(())
+}
+
+/// The text parts for `fields`: one per field, one per item of a list,
+/// numbers and booleans as in JSON. Unset fields are left out.
+fn form_fields<T: Serialize>(fields: &T) -> Result<Vec<(String, String)>> {
+    let mut parts = Vec::new();
+    if let serde_json::Value::Object(fields) = serde_json::to_value(fields)? {
+        for (name, value) in fields {
+            let values = match value {
+                serde_json::Value::Array(items) => items,
+                value => vec![value],
+            };
+            for value in values {
+                let text = match value {
+                    serde_json::Value::Null => continue,
+                    serde_json::Value::String(text) => text,
+                    value => value.to_string(),
+                };
+                parts.push((name.clone(), text));
+            }
+        }
+    }
+    Ok(parts)
+}
+
+/// Builds a `multipart/form-data` upload: each field of `fields` as a text
+/// part (lists repeated), and each image as an `image` file part, in order.
+fn upload_form<T: Serialize>(fields: &T, image_paths: &[PathBuf]) -> Result<Form> {
+    check_upload_size(image_paths)?;
+
+    let mut form = Form::new();
+    for (name, text) in form_fields(fields)? {
+        form = form.text(name, text);
+    }
+
+    for path in image_paths {
+        let data = std::fs::read(path)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "image".to_string());
+        let mime_type = detect_mime_type(&data, path);
+        let part = Part::bytes(data).file_name(file_name).mime_str(mime_type)?;
+        form = form.part("image", part);
+    }
+
+    Ok(form)
+}
+
 fn create_spinner(message: &str) -> ProgressBar {
     let spinner = ProgressBar::new_spinner();
     spinner.set_style(
@@ -353,4 +638,56 @@ fn create_spinner(message: &str) -> ProgressBar {
     spinner.set_message(message.to_string());
     spinner.enable_steady_tick(Duration::from_millis(100));
     spinner
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parts(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, text)| (name.to_string(), text.to_string())).collect()
+    }
+
+    #[test]
+    fn test_form_fields_flatten_settings_and_lists() {
+        let fields = EditRequest {
+            prompt: "make it blue".to_string(),
+            model: None,
+            settings: ImageSettings {
+                aspect_ratio: Some("16:9".to_string()),
+                seed: Some(42),
+                temperature: Some(0.5),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            form_fields(&fields).unwrap(),
+            parts(&[("aspect_ratio", "16:9"), ("prompt", "make it blue"), ("seed", "42"), ("temperature", "0.5")])
+        );
+
+        let fields = json!({ "modalities": ["IMAGE", "TEXT"], "inline": true, "model": null, "empty": [] });
+        assert_eq!(
+            form_fields(&fields).unwrap(),
+            parts(&[("inline", "true"), ("modalities", "IMAGE"), ("modalities", "TEXT")])
+        );
+    }
+
+    #[test]
+    fn test_upload_size_is_checked_before_reading() {
+        let dir = std::env::temp_dir().join(format!("geminipocket-upload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let small = dir.join("small.png");
+        let large = dir.join("large.png");
+        std::fs::write(&small, b"tiny").unwrap();
+        std::fs::File::create(&large).unwrap().set_len(MAX_UPLOAD_BYTES).unwrap();
+
+        assert!(check_upload_size(std::slice::from_ref(&large)).is_ok());
+        let error = check_upload_size(&[large.clone(), small.clone()]).unwrap_err();
+        assert!(error.to_string().contains("the limit is 15 MiB"), "{}", error);
+        assert!(upload_form(&json!({ "prompt": "x" }), &[large, small]).is_err());
+        assert!(check_upload_size(&[dir.join("missing.png")]).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use clap::Subcommand;
+use colored::*;
+
+use super::auth::{print_plan, print_usage};
+use crate::api::GeminiClient;
+use crate::types::AdminUser;
+
+#[derive(Subcommand)]
+pub enum AdminAction {
+    /// List users, newest first
+    Users {
+        /// Only show users whose email contains this text
+        #[arg(long, short = 'q', value_name = "TEXT")]
+        search: Option<String>,
+
+        /// Only show disabled users
+        #[arg(long, conflicts_with = "active")]
+        disabled: bool,
+
+        /// Only show users that are not disabled
+        #[arg(long)]
+        active: bool,
+
+        /// Number of entries per page
+        #[arg(long, value_name = "N", default_value = "50")]
+        limit: u32,
+
+        /// Continue from a cursor printed by a previous page
+        #[arg(long, value_name = "CURSOR")]
+        cursor: Option<String>,
+    },
+
+    /// Show one user with their limits, keys and recent usage
+    Show {
+        /// User id from `admin users`
+        #[arg(value_name = "ID")]
+        id: i32,
+    },
+
+    /// Disable a user: their keys and sessions stop working and logins are refused
+    Disable {
+        #[arg(value_name = "ID")]
+        id: i32,
+
+        /// Why, for the record
+        #[arg(long, value_name = "TEXT")]
+        reason: Option<String>,
+    },
+
+    /// Re-enable a disabled user
+    Enable {
+        #[arg(value_name = "ID")]
+        id: i32,
+    },
+
+    /// Clear a user's rate limit counters
+    ResetQuota {
+        #[arg(value_name = "ID")]
+        id: i32,
+    },
+
+    /// Revoke a user's API keys (all of them unless --key is given)
+    RevokeKeys {
+        #[arg(value_name = "ID")]
+        id: i32,
+
+        /// Only revoke this key
+        #[arg(long, value_name = "KEY_ID")]
+        key: Option<String>,
+    },
+
+    /// Show a user's per-day usage
+    Usage {
+        #[arg(value_name = "ID")]
+        id: i32,
+
+        /// Number of days to include
+        #[arg(long, value_name = "N", default_value = "30")]
+        days: u32,
+    },
+}
+
+pub async fn handle_admin(client: &GeminiClient, action: AdminAction) -> Result<()> {
+    match action {
+        AdminAction::Users {
+            search,
+            disabled,
+            active,
+            limit,
+            cursor,
+        } => {
+            let mut query = vec![("limit", limit.to_string())];
+            if disabled || active {
+                query.push(("disabled", disabled.to_string()));
+            }
+            for (key, value) in [("q", search), ("cursor", cursor)] {
+                if let Some(value) = value {
+                    query.push((key, value));
+                }
+            }
+
+            match client.admin_users(&query).await {
+                Ok(page) => {
+                    if page.users.is_empty() {
+                        println!("{}", "No users found.".dimmed());
+                    }
+                    for user in &page.users {
+                        print_user(user);
+                    }
+                    if let Some(next) = page.next_cursor {
+                        println!("\n{} --cursor {}", "More results:".cyan(), next);
+                    }
+                }
+                Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+            }
+        }
+        AdminAction::Show { id } => match client.admin_user(id).await {
+            Ok(detail) => {
+                print_user(&detail.user);
+                if let Some(reason) = &detail.user.disabled_reason {
+                    println!("  {}: {}", "Disabled because".cyan(), reason);
+                }
+                if let Some(plan) = &detail.plan {
+                    print_plan(plan);
+                }
+                println!("{}", "Keys:".bold());
+                if detail.keys.is_empty() {
+                    println!("  {}", "None".dimmed());
+                }
+                for key in &detail.keys {
+                    let state = if key.revoked { "revoked".red() } else { "active".green() };
+                    let last_used = key.last_used_at.as_deref().unwrap_or("never");
+                    println!(
+                        "  {} {} {}… [{}] {}",
+                        key.id.yellow(),
+                        key.name.bold(),
+                        key.prefix,
+                        state,
+                        format!("last used {}", last_used).dimmed()
+                    );
+                }
+                print_usage(&detail.usage);
+            }
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        AdminAction::Disable { id, reason } => match client.admin_disable_user(id, reason).await {
+            Ok(response) => {
+                println!("{} User {} disabled", "✓".green(), response.user.email.yellow());
+                println!("{}", "Their sessions were ended. Keys stay blocked until the user is enabled again.".cyan());
+            }
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        AdminAction::Enable { id } => match client.admin_enable_user(id).await {
+            Ok(response) => println!("{} User {} enabled", "✓".green(), response.user.email.yellow()),
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        AdminAction::ResetQuota { id } => match client.admin_reset_quota(id).await {
+            Ok(response) => println!("{} Cleared {} rate limit counters for user {}", "✓".green(), response.cleared, id),
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        AdminAction::RevokeKeys { id, key } => match client.admin_revoke_keys(id, key.as_deref()).await {
+            Ok(response) => println!("{} Revoked {} key(s) of user {}", "✓".green(), response.revoked, id),
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        AdminAction::Usage { id, days } => match client.admin_user_usage(id, days).await {
+            Ok(report) => {
+                println!("{}", format!("Usage of user {}, last {} days:", id, report.days).bold());
+                if report.usage.is_empty() {
+                    println!("  {}", "No requests".dimmed());
+                }
+                for row in &report.usage {
+                    println!(
+                        "  {} {:<16} {} requests ({} succeeded, {} failed, {} bytes)",
+                        row.day.dimmed(),
+                        row.kind,
+                        row.requests,
+                        row.succeeded,
+                        row.failed,
+                        row.output_bytes
+                    );
+                }
+                for (kind, totals) in &report.totals {
+                    println!("  {} {:<16} {} requests", "total".bold(), kind, totals.requests);
+                }
+            }
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+    }
+
+    Ok(())
+}
+
+fn print_user(user: &AdminUser) {
+    let state = match &user.disabled_at {
+        Some(at) => format!("disabled {}", at).red(),
+        None => "active".green(),
+    };
+    let role = if user.is_admin { " admin".magenta() } else { "".normal() };
+    println!(
+        "{} {} [{}]{} {} {}",
+        user.id.to_string().yellow(),
+        user.email.bold(),
+        state,
+        role,
+        format!("{} active keys", user.active_keys).dimmed(),
+        user.created_at.dimmed()
+    );
+}
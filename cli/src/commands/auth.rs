@@ -3,21 +3,55 @@ use colored::*;
 use std::io::{self, Write};
 
 use crate::api::GeminiClient;
-use crate::types::Config;
+use crate::types::{ApiError, Config, Plan, UpdateAccountRequest, UsageSummary};
+use crate::validation;
 
 
-pub async fn handle_register(client: &GeminiClient, config: &mut Config) -> Result<()> {
-    print!("{}: ", "Email".bold());
-    io::stdout().flush()?;
-    let mut email = String::new();
-    io::stdin().read_line(&mut email)?;
-    let email = email.trim();
+/// Prompts until `check` accepts the answer, printing each problem found.
+fn prompt_valid(label: &str, check: impl Fn(&str) -> Vec<String>) -> Result<String> {
+    loop {
+        print!("{}: ", label.bold());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim().to_string();
 
-    print!("{}: ", "Password".bold());
+        let problems = check(&answer);
+        if problems.is_empty() {
+            return Ok(answer);
+        }
+        for problem in problems {
+            println!("  {} {}", "✗".red(), problem.yellow());
+        }
+    }
+}
+
+fn prompt(label: &str) -> Result<String> {
+    print!("{}: ", label.bold());
     io::stdout().flush()?;
-    let mut password = String::new();
-    io::stdin().read_line(&mut password)?;
-    let password = password.trim();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+pub async fn handle_register(client: &GeminiClient, config: &mut Config) -> Result<()> {
+    let email = prompt_valid("Email", |email| {
+        validation::check_email(&validation::normalize_email(email)).into_iter().collect()
+    })?;
+    let email = validation::normalize_email(&email);
+
+    println!(
+        "{}",
+        format!(
+            "Password: {}-{} bytes, with at least one letter and one digit",
+            validation::MIN_PASSWORD_CHARS,
+            validation::MAX_PASSWORD_BYTES
+        )
+        .dimmed()
+    );
+    let password = prompt_valid("Password", |password| validation::check_password(password, &email))?;
+    let email = email.as_str();
+    let password = password.as_str();
 
     println!("{}", "Registering...".cyan());
 
@@ -86,6 +120,272 @@ pub async fn handle_login(client: &GeminiClient, config: &mut Config) -> Result<
         Err(e) => {
             println!("{}", "✗ Login failed".red());
             println!("{}: {}", "Error".bold(), e.to_string().red());
+            explain_login_error(&e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds what to do next when the worker refused to check the password at
+/// all because of earlier failed attempts.
+fn explain_login_error(error: &anyhow::Error) {
+    let Some(error) = error.downcast_ref::<ApiError>() else {
+        return;
+    };
+    let retry_after = error
+        .details
+        .as_ref()
+        .and_then(|details| details["retry_after"].as_u64())
+        .unwrap_or(0);
+
+    match error.code.as_str() {
+        "account_locked" => {
+            let minutes = retry_after.div_ceil(60).max(1);
+            println!(
+                "{}",
+                format!(
+                    "Too many failed logins locked this account for about {} minute{}. Wait and try again,",
+                    minutes,
+                    if minutes == 1 { "" } else { "s" }
+                )
+                .yellow()
+            );
+            println!(
+                "{}",
+                "or run 'geminipocket-cli auth forgot-password' to reset your password, which also lifts the lock.".yellow()
+            );
+        }
+        "login_throttled" => {
+            println!(
+                "{}",
+                format!("Too many failed logins from here. Wait {} seconds before trying again.", retry_after.max(1)).yellow()
+            );
+        }
+        _ => {}
+    }
+}
+
+pub async fn handle_forgot_password(client: &GeminiClient) -> Result<()> {
+    print!("{}: ", "Email".bold());
+    io::stdout().flush()?;
+    let mut email = String::new();
+    io::stdin().read_line(&mut email)?;
+    let email = email.trim();
+
+    match client.forgot_password(email).await {
+        Ok(response) => {
+            println!("{} {}", "✓".green(), response.message);
+            println!("{}", "Then run 'geminipocket-cli auth reset-password' with the token.".cyan());
+        }
+        Err(e) => {
+            println!("{}", "✗ Failed to request a password reset".red());
+            println!("{}: {}", "Error".bold(), e.to_string().red());
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets a new password from an emailed token, then logs in with it so the
+/// new API key ends up in the config.
+pub async fn handle_reset_password(client: &GeminiClient, config: &mut Config) -> Result<()> {
+    print!("{}: ", "Email".bold());
+    io::stdout().flush()?;
+    let mut email = String::new();
+    io::stdin().read_line(&mut email)?;
+    let email = email.trim();
+
+    print!("{}: ", "Reset token".bold());
+    io::stdout().flush()?;
+    let mut token = String::new();
+    io::stdin().read_line(&mut token)?;
+    let token = token.trim();
+
+    let normalized = validation::normalize_email(email);
+    let password = prompt_valid("New password", |password| validation::check_password(password, &normalized))?;
+    let password = password.as_str();
+
+    match client.reset_password(token, password).await {
+        Ok(response) => {
+            println!("{}", "✓ Password updated".green());
+            if let Some(revoked) = response.revoked_keys.filter(|&revoked| revoked > 0) {
+                println!("{}", format!("Revoked {} API key{}; log in again on your other machines.", revoked, if revoked == 1 { "" } else { "s" }).yellow());
+            }
+        }
+        Err(e) => {
+            println!("{}", "✗ Password reset failed".red());
+            println!("{}: {}", "Error".bold(), e.to_string().red());
+            return Ok(());
+        }
+    }
+
+    match client.login(email, password).await {
+        Ok(response) => {
+            if let Some(api_key) = response.api_key {
+                config.api_key = Some(api_key);
+                config.email = Some(email.to_string());
+                config.save()?;
+                println!("{}", "Logged in; your new API key has been saved to the config.".cyan());
+            }
+        }
+        Err(e) => {
+            println!("{}: {}", "Could not log in automatically".yellow(), e);
+            println!("{}", "Use 'geminipocket-cli auth login' with your new password.".cyan());
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_webhook_secret(client: &GeminiClient, rotate: bool) -> Result<()> {
+    match client.webhook_secret(rotate).await {
+        Ok(response) => {
+            if rotate {
+                println!("{}", "✓ Webhook secret rotated".green());
+            }
+            println!("{}: {}", "Webhook Secret".bold(), response.webhook_secret.yellow());
+            println!(
+                "{}",
+                "Callbacks carry X-GeminiPocket-Signature: sha256=HMAC(secret, \"{timestamp}.{body}\")".cyan()
+            );
+        }
+        Err(e) => {
+            println!("{}", "✗ Failed to get webhook secret".red());
+            println!("{}: {}", "Error".bold(), e.to_string().red());
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows the account as the worker sees it, unlike `auth status`, which only
+/// reads the local config.
+pub async fn handle_whoami(client: &GeminiClient) -> Result<()> {
+    let account = match client.account().await {
+        Ok(account) => account,
+        Err(e) => {
+            println!("{}", "✗ Failed to load account".red());
+            println!("{}: {}", "Error".bold(), e.to_string().red());
+            return Ok(());
+        }
+    };
+
+    let user = account.user;
+    println!("{}: {}", "Email".bold(), user.email.cyan());
+    println!("{}: {}", "Member since".bold(), user.created_at);
+    print_plan(&user.plan);
+    print_usage(&account.usage);
+
+    Ok(())
+}
+
+pub fn print_plan(plan: &Plan) {
+    println!("{}: {}", "Plan".bold(), plan.name.yellow());
+    for (kind, windows) in &plan.limits {
+        let limits: Vec<String> = windows
+            .iter()
+            .map(|(window, limit)| format!("{} {}", limit, window.replace('_', " ")))
+            .collect();
+        println!("  {:<8} {}", kind, limits.join(", "));
+    }
+}
+
+pub fn print_usage(usage: &UsageSummary) {
+    println!("{}", format!("Usage, last {} days:", usage.days).bold());
+    if usage.totals.is_empty() {
+        println!("  {}", "No requests yet".dimmed());
+    }
+    for (kind, totals) in &usage.totals {
+        println!(
+            "  {:<16} {} requests ({} succeeded, {} failed)",
+            kind, totals.requests, totals.succeeded, totals.failed
+        );
+    }
+}
+
+pub async fn handle_change_email(client: &GeminiClient, config: &mut Config) -> Result<()> {
+    let email = prompt_valid("New email", |email| {
+        validation::check_email(&validation::normalize_email(email)).into_iter().collect()
+    })?;
+    let current_password = prompt("Current password")?;
+
+    let update = UpdateAccountRequest {
+        current_password,
+        email: Some(validation::normalize_email(&email)),
+        new_password: None,
+    };
+    match client.update_account(&update).await {
+        Ok(response) => {
+            config.email = Some(response.email.clone());
+            config.save()?;
+            println!("{}", "✓ Email updated".green());
+            println!("{}: {}", "Email".bold(), response.email.cyan());
+        }
+        Err(e) => {
+            println!("{}", "✗ Failed to change email".red());
+            println!("{}: {}", "Error".bold(), e.to_string().red());
+            explain_login_error(&e);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_change_password(client: &GeminiClient, config: &Config) -> Result<()> {
+    let current_password = prompt("Current password")?;
+    let email = config.email.as_deref().map(validation::normalize_email).unwrap_or_default();
+    let new_password = prompt_valid("New password", |password| validation::check_password(password, &email))?;
+
+    let update = UpdateAccountRequest {
+        current_password,
+        email: None,
+        new_password: Some(new_password),
+    };
+    match client.update_account(&update).await {
+        Ok(response) => {
+            println!("{}", "✓ Password changed".green());
+            if response.sessions_revoked {
+                println!("{}", "Web sessions were logged out. API keys, including this one, keep working.".cyan());
+            }
+        }
+        Err(e) => {
+            println!("{}", "✗ Failed to change password".red());
+            println!("{}: {}", "Error".bold(), e.to_string().red());
+            explain_login_error(&e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes the account on the worker after a typed confirmation, then
+/// forgets the local credentials.
+pub async fn handle_delete_account(client: &GeminiClient, config: &mut Config, yes: bool) -> Result<()> {
+    if !yes {
+        println!(
+            "{}",
+            "This permanently deletes your account, API keys, history and stored images and videos.".yellow()
+        );
+        if prompt("Type DELETE to confirm")? != "DELETE" {
+            println!("{}", "Cancelled.".cyan());
+            return Ok(());
+        }
+    }
+    let password = prompt("Password")?;
+
+    match client.delete_account(&password).await {
+        Ok(()) => {
+            config.api_key = None;
+            config.email = None;
+            config.save()?;
+            println!("{}", "✓ Account deleted".green());
+            println!("{}", "Credentials have been removed from config.".cyan());
+        }
+        Err(e) => {
+            println!("{}", "✗ Failed to delete account".red());
+            println!("{}: {}", "Error".bold(), e.to_string().red());
+            explain_login_error(&e);
         }
     }
 
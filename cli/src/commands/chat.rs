@@ -0,0 +1,96 @@
+use anyhow::Result;
+use colored::*;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::generate::{explain_blocked, print_model_text, tag_image};
+use crate::api::GeminiClient;
+use crate::types::{ApiError, ImageSettings};
+use crate::utils::save_image;
+
+/// Typed at the prompt to end the chat; so does end of input.
+const QUIT_COMMANDS: [&str; 3] = ["/quit", "/exit", "/q"];
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_chat(
+    client: &GeminiClient,
+    image_paths: &[PathBuf],
+    model: Option<&str>,
+    settings: &ImageSettings,
+    output_dir: Option<&Path>,
+    filename: Option<&str>,
+    save_to_current: bool,
+) -> Result<()> {
+    if let Some(missing) = image_paths.iter().find(|path| !path.exists()) {
+        eprintln!("{} Image file not found: {}", "✗".red(), missing.display());
+        return Ok(());
+    }
+
+    let session = match client.create_session(image_paths, model).await {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("{} Error: {}", "✗".red(), e);
+            return Ok(());
+        }
+    };
+    println!(
+        "{} Session started with {} (up to {} turns)",
+        "✓".green(),
+        session.model.yellow(),
+        session.max_turns
+    );
+    println!("{}", "Describe a change and press Enter. Each turn builds on the last; /quit to stop.".dimmed());
+
+    let base = filename.unwrap_or("gemini_chat");
+    loop {
+        print!("{} ", ">".cyan().bold());
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+        let prompt = line.trim();
+        if prompt.is_empty() {
+            continue;
+        }
+        if QUIT_COMMANDS.contains(&prompt) {
+            break;
+        }
+
+        let turn = match client.session_turn(&session.session_id, prompt, settings).await {
+            Ok(turn) => turn,
+            Err(e) => {
+                // A blocked turn leaves the session as it was, so the user
+                // can rephrase and carry on.
+                if !explain_blocked(&e) {
+                    eprintln!("{} Error: {}", "✗".red(), e);
+                }
+                let full = e.downcast_ref::<ApiError>().is_some_and(|error| error.code == "session_full");
+                if full {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        match client.image_bytes(&turn.result).await {
+            Ok(image_data) => {
+                let image_data = tag_image(&image_data, prompt, Some(&session.model), settings);
+                let name = format!("{}_turn{}", base, turn.turn);
+                let output_path = save_image(&image_data, output_dir, Some(&name), save_to_current)?;
+                println!(
+                    "{} Turn {} saved to: {}",
+                    "✓".green(),
+                    turn.turn,
+                    output_path.display().to_string().bold()
+                );
+            }
+            Err(e) => eprintln!("{} Could not download turn {}: {}", "✗".red(), turn.turn, e),
+        }
+        print_model_text(turn.result.text.as_deref());
+    }
+
+    println!("{}", format!("Session {} ended", session.session_id).dimmed());
+    Ok(())
+}
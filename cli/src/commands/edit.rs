@@ -2,41 +2,48 @@ use anyhow::Result;
 use colored::*;
 use std::path::{Path, PathBuf};
 
+use super::generate::{exit_if_blocked, print_model_text, tag_image};
 use crate::api::GeminiClient;
+use crate::types::ImageSettings;
 use crate::utils::save_image;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_edit(
     client: &GeminiClient,
-    image_path: &PathBuf,
+    image_paths: &[PathBuf],
     prompt: &str,
+    model: Option<&str>,
+    settings: &ImageSettings,
     output_dir: Option<&Path>,
     filename: Option<&str>,
     save_to_current: bool,
 ) -> Result<()> {
-    if !image_path.exists() {
-        eprintln!("{} Image file not found: {}", "✗".red(), image_path.display());
+    if let Some(missing) = image_paths.iter().find(|path| !path.exists()) {
+        eprintln!("{} Image file not found: {}", "✗".red(), missing.display());
         return Ok(());
     }
 
+    let names: Vec<String> = image_paths.iter().map(|path| path.display().to_string()).collect();
     println!(
         "{} {} with prompt: {}",
         "Editing".bold(),
-        image_path.display().to_string().cyan(),
+        names.join(", ").cyan(),
         prompt.italic()
     );
 
-    match client.edit_image(image_path, prompt).await {
+    match client.edit_image(image_paths, prompt, model, settings).await {
         Ok(response) => {
             if response.success {
-                if let Some(image_data) = response.image {
-                    let output_path =
-                        save_image(&image_data, output_dir, filename, save_to_current)?;
-                    println!(
-                        "{} Edited image saved to: {}",
-                        "✓".green(),
-                        output_path.display().to_string().bold()
-                    );
-                }
+                let image_data = client.image_bytes(&response).await?;
+                let image_data = tag_image(&image_data, prompt, model, settings);
+                let output_path =
+                    save_image(&image_data, output_dir, filename, save_to_current)?;
+                println!(
+                    "{} Edited image saved to: {}",
+                    "✓".green(),
+                    output_path.display().to_string().bold()
+                );
+                print_model_text(response.text.as_deref());
             } else {
                 eprintln!(
                     "{} Edit failed: {}",
@@ -45,7 +52,10 @@ pub async fn handle_edit(
                 );
             }
         }
-        Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        Err(e) => {
+            exit_if_blocked(&e);
+            eprintln!("{} Error: {}", "✗".red(), e);
+        }
     }
 
     Ok(())
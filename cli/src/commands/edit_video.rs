@@ -5,30 +5,38 @@ use std::path::Path;
 use crate::api::GeminiClient;
 use crate::utils::save_video;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_edit_video(
     client: &GeminiClient,
     image_path: &Path,
     prompt: &str,
+    model: Option<&str>,
     output_dir: Option<&Path>,
     filename: Option<&str>,
     save_to_current: bool,
     negative_prompt: Option<&str>,
     aspect_ratio: Option<&str>,
     resolution: Option<&str>,
+    callback_url: Option<&str>,
 ) -> Result<()> {
     println!("{} {}", "Editing video from image:".bold(), image_path.display().to_string().italic());
     println!("{} {}", "Edit prompt:".bold(), prompt.italic());
 
-    match client.edit_video(image_path, prompt, negative_prompt, aspect_ratio, resolution).await {
-        Ok(operation_name) => {
-            println!("{} Started video editing (operation: {})", "✓".green(), operation_name);
+    match client.edit_video(image_path, prompt, model, negative_prompt, aspect_ratio, resolution, callback_url).await {
+        Ok(job_id) => {
+            println!("{} Started video editing (job: {})", "✓".green(), job_id);
 
-            match client.poll_video_completion(&operation_name).await {
-                Ok(video_uri) => {
+            if let Some(callback_url) = callback_url {
+                println!("{} The result will be posted to {}", "✓".green(), callback_url.bold());
+                return Ok(());
+            }
+
+            match client.poll_video_completion(&job_id).await {
+                Ok(video_url) => {
                     println!("{} Video editing completed!", "✓".green());
 
-                    // Download the video
-                    let output_path = save_video(&video_uri, output_dir, filename, save_to_current).await?;
+                    let video_data = client.download_asset(&video_url).await?;
+                    let output_path = save_video(&video_data, output_dir, filename, save_to_current)?;
                     println!(
                         "{} Video saved to: {}",
                         "✓".green(),
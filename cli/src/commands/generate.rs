@@ -3,29 +3,57 @@ use colored::*;
 use std::path::Path;
 
 use crate::api::GeminiClient;
-use crate::utils::save_image;
+use crate::types::{ApiError, ImageSettings};
+use crate::utils::{save_image, with_png_text};
 
+/// Exit status when the model's safety filters blocked the request, so
+/// scripts can tell it apart from other failures.
+pub const EXIT_CONTENT_BLOCKED: i32 = 3;
+
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_generate(
     client: &GeminiClient,
     prompt: &str,
+    model: Option<&str>,
+    count: u32,
+    settings: &ImageSettings,
     output_dir: Option<&Path>,
     filename: Option<&str>,
     save_to_current: bool,
 ) -> Result<()> {
     println!("{} {}", "Generating image:".bold(), prompt.italic());
 
-    match client.generate_image(prompt).await {
+    let requested = (count > 1).then_some(count);
+    match client.generate_image(prompt, model, requested, settings).await {
         Ok(response) => {
             if response.success {
-                if let Some(image_data) = response.image {
-                    let output_path =
-                        save_image(&image_data, output_dir, filename, save_to_current)?;
+                let images = client.all_image_bytes(&response).await?;
+                if images.len() < count as usize {
+                    println!(
+                        "{} The model returned {} of {} images",
+                        "!".yellow(),
+                        images.len(),
+                        count
+                    );
+                }
+
+                let base = filename.unwrap_or("gemini_image");
+                for (index, image_data) in images.iter().enumerate() {
+                    // Numbered names keep images saved in the same second apart.
+                    let name = if images.len() > 1 {
+                        format!("{}_{}", base, index + 1)
+                    } else {
+                        base.to_string()
+                    };
+                    let image_data = tag_image(image_data, prompt, model, settings);
+                    let output_path = save_image(&image_data, output_dir, Some(&name), save_to_current)?;
                     println!(
                         "{} Image saved to: {}",
                         "✓".green(),
                         output_path.display().to_string().bold()
                     );
                 }
+                print_model_text(response.text.as_deref());
             } else {
                 eprintln!(
                     "{} Generation failed: {}",
@@ -34,8 +62,87 @@ pub async fn handle_generate(
                 );
             }
         }
-        Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        Err(e) => {
+            exit_if_blocked(&e);
+            eprintln!("{} Error: {}", "✗".red(), e);
+        }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Explains a `content_blocked` error and exits with
+/// [`EXIT_CONTENT_BLOCKED`]. Other errors are left to the caller.
+pub fn exit_if_blocked(error: &anyhow::Error) {
+    if explain_blocked(error) {
+        std::process::exit(EXIT_CONTENT_BLOCKED);
+    }
+}
+
+/// Prints why a `content_blocked` error was blocked and returns true.
+/// Returns false for any other error.
+pub fn explain_blocked(error: &anyhow::Error) -> bool {
+    let Some(error) = error.downcast_ref::<ApiError>() else {
+        return false;
+    };
+    if error.code != "content_blocked" {
+        return false;
+    }
+
+    let details = error.details.clone().unwrap_or_default();
+    let reason = details["reason"].as_str().unwrap_or("unknown reason");
+    eprintln!("{} Blocked by the model's safety filters ({})", "✗".red(), reason);
+
+    let flagged = details["safety_ratings"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|rating| {
+            rating["blocked"].as_bool().unwrap_or(false)
+                || matches!(rating["probability"].as_str(), Some("MEDIUM" | "HIGH"))
+        });
+    for rating in flagged {
+        eprintln!(
+            "  • {} ({})",
+            rating["category"].as_str().unwrap_or("unknown").yellow(),
+            rating["probability"].as_str().unwrap_or("?").to_lowercase()
+        );
+    }
+
+    let hint = if details["blocked"] == "prompt" {
+        "The prompt was rejected before anything was generated. Rephrase it without the flagged content and try again."
+    } else {
+        "The prompt was accepted but the result was filtered. Try a different wording or input image; retrying as-is will be blocked again."
+    };
+    eprintln!("{}", hint.yellow());
+    true
+}
+
+/// Embeds the prompt and settings in a PNG, so the file records how it was
+/// made.
+pub fn tag_image(image: &[u8], prompt: &str, model: Option<&str>, settings: &ImageSettings) -> Vec<u8> {
+    let mut parameters = serde_json::to_value(settings).unwrap_or_default();
+    parameters["prompt"] = prompt.into();
+    if let Some(model) = model {
+        parameters["model"] = model.into();
+    }
+    let software = format!("geminipocket-cli {}", env!("CARGO_PKG_VERSION"));
+    with_png_text(
+        image,
+        &[
+            ("Description", prompt),
+            ("Software", &software),
+            ("geminipocket", &parameters.to_string()),
+        ],
+    )
+}
+
+/// Prints what the model said alongside the image, if anything.
+pub fn print_model_text(text: Option<&str>) {
+    if let Some(text) = text {
+        println!("{}", "Model says:".cyan());
+        for line in text.lines() {
+            println!("  {}", line.italic());
+        }
+    }
+}
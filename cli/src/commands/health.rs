@@ -6,7 +6,14 @@ use crate::api::GeminiClient;
 pub async fn handle_health(client: &GeminiClient) -> Result<()> {
     match client.health().await {
         Ok(health) => {
-            println!("{} API is {}", "✓".green(), health.status.green());
+            if health.status == "healthy" {
+                println!("{} API is {}", "✓".green(), health.status.green());
+            } else {
+                println!("{} API is {}", "!".yellow(), health.status.yellow());
+            }
+            for (check, _) in health.checks.iter().filter(|(_, passed)| !**passed) {
+                println!("  {} {} failed", "✗".red(), check);
+            }
             if let Some(timestamp) = health.timestamp {
                 let dt = chrono::DateTime::from_timestamp(timestamp as i64 / 1000, 0)
                     .unwrap_or_else(chrono::Utc::now);
@@ -0,0 +1,171 @@
+use anyhow::Result;
+use clap::Subcommand;
+use colored::*;
+use std::path::Path;
+
+use crate::api::GeminiClient;
+use crate::types::HistoryItem;
+use crate::utils::{save_image, save_video};
+
+#[derive(Subcommand)]
+pub enum HistoryAction {
+    /// List past generations, newest first
+    List {
+        /// Only show one kind: generate, edit, generate_video, edit_video
+        #[arg(long, value_name = "KIND")]
+        kind: Option<String>,
+
+        /// Only show generations on or after this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        from: Option<String>,
+
+        /// Only show generations on or before this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        to: Option<String>,
+
+        /// Only show generations whose prompt contains this text
+        #[arg(long, short = 'q', value_name = "TEXT")]
+        search: Option<String>,
+
+        /// Number of entries per page
+        #[arg(long, value_name = "N", default_value = "20")]
+        limit: u32,
+
+        /// Continue from a cursor printed by a previous page
+        #[arg(long, value_name = "CURSOR")]
+        cursor: Option<String>,
+    },
+
+    /// Show the parameters and download links of one generation
+    Show {
+        /// Generation id from `history list`
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+
+    /// Download every image or video one generation produced
+    Download {
+        /// Generation id from `history list`
+        #[arg(value_name = "ID")]
+        id: String,
+
+        /// Save to current directory (overrides config)
+        #[arg(short, long)]
+        save: bool,
+    },
+}
+
+pub async fn handle_history(client: &GeminiClient, action: HistoryAction, output_dir: Option<&Path>) -> Result<()> {
+    match action {
+        HistoryAction::List {
+            kind,
+            from,
+            to,
+            search,
+            limit,
+            cursor,
+        } => {
+            let mut query = vec![("limit", limit.to_string())];
+            for (key, value) in [("kind", kind), ("from", from), ("to", to), ("q", search), ("cursor", cursor)] {
+                if let Some(value) = value {
+                    query.push((key, value));
+                }
+            }
+
+            match client.history(&query).await {
+                Ok(page) => {
+                    if page.items.is_empty() {
+                        println!("{}", "No generations found.".dimmed());
+                    }
+                    for item in &page.items {
+                        print_item(item);
+                    }
+                    if let Some(next) = page.next_cursor {
+                        println!("\n{} --cursor {}", "More results:".cyan(), next);
+                    }
+                }
+                Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+            }
+        }
+        HistoryAction::Show { id } => match client.history_item(&id).await {
+            Ok(response) => {
+                let generation = response.generation;
+                print_item(&generation.item);
+                println!("  {}: {}", "Model".cyan(), generation.item.model);
+                if let Some(latency) = generation.item.latency_ms {
+                    println!("  {}: {} ms", "Latency".cyan(), latency);
+                }
+                if let Some(bytes) = generation.item.output_bytes {
+                    println!("  {}: {} bytes", "Output size".cyan(), bytes);
+                }
+                if let Some(code) = &generation.item.error_code {
+                    println!("  {}: {}", "Error".cyan(), code.red());
+                }
+                println!(
+                    "  {}: {}",
+                    "Parameters".cyan(),
+                    serde_json::to_string_pretty(&generation.parameters)?
+                );
+                if generation.assets.len() > 1 {
+                    for (index, asset) in generation.assets.iter().enumerate() {
+                        println!("  {} {}: {}", "Download".cyan(), index + 1, asset.asset_url.bold());
+                    }
+                } else {
+                    match generation.download_url {
+                        Some(url) => println!("  {}: {}", "Download".cyan(), url.bold()),
+                        None => println!("  {}: {}", "Download".cyan(), "(not available)".dimmed()),
+                    }
+                }
+            }
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        HistoryAction::Download { id, save } => match client.history_item(&id).await {
+            Ok(response) => {
+                let assets = response.generation.assets;
+                if assets.is_empty() {
+                    println!("{}", "This generation has no stored output to download.".dimmed());
+                }
+                let short_id = id.get(..8).unwrap_or(&id);
+                for (index, asset) in assets.iter().enumerate() {
+                    let name = if assets.len() > 1 {
+                        format!("gemini_{}_{}", short_id, index + 1)
+                    } else {
+                        format!("gemini_{}", short_id)
+                    };
+                    let data = match client.download_asset(&asset.asset_url).await {
+                        Ok(data) => data,
+                        Err(e) => {
+                            eprintln!("{} Could not download {}: {}", "✗".red(), asset.asset_id, e);
+                            continue;
+                        }
+                    };
+                    let output_path = if asset.mime_type.starts_with("video/") {
+                        save_video(&data, output_dir, Some(&name), save)?
+                    } else {
+                        save_image(&data, output_dir, Some(&name), save)?
+                    };
+                    println!("{} Saved to: {}", "✓".green(), output_path.display().to_string().bold());
+                }
+            }
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+    }
+
+    Ok(())
+}
+
+fn print_item(item: &HistoryItem) {
+    let status = match item.status.as_str() {
+        "succeeded" => item.status.green(),
+        "failed" => item.status.red(),
+        _ => item.status.yellow(),
+    };
+    println!(
+        "{} {} {} [{}] {}",
+        item.created_at.dimmed(),
+        item.id.yellow(),
+        item.kind.cyan(),
+        status,
+        item.prompt.italic()
+    );
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use clap::Subcommand;
+use colored::*;
+
+use crate::api::GeminiClient;
+
+#[derive(Subcommand)]
+pub enum KeysAction {
+    /// Create a new API key; the key is only shown once
+    Create {
+        /// Name to recognise the key by, e.g. "laptop" or "ci"
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// List your API keys
+    List,
+
+    /// Revoke an API key so it can no longer be used
+    Revoke {
+        /// Key id from `keys list`
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+}
+
+pub async fn handle_keys(client: &GeminiClient, action: KeysAction) -> Result<()> {
+    match action {
+        KeysAction::Create { name } => match client.create_key(&name).await {
+            Ok(response) => {
+                println!("{}", "✓ API key created".green());
+                println!("{}: {}", "ID".bold(), response.key.id);
+                println!("{}: {}", "API Key".bold(), response.api_key.yellow());
+                println!("{}", "Store it now; it cannot be shown again.".cyan());
+            }
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        KeysAction::List => match client.list_keys().await {
+            Ok(list) => {
+                if list.keys.is_empty() {
+                    println!("{}", "No API keys found.".dimmed());
+                }
+                for key in &list.keys {
+                    let state = if key.revoked { "revoked".red() } else { "active".green() };
+                    let last_used = key.last_used_at.as_deref().unwrap_or("never");
+                    println!(
+                        "{} {} {}… [{}] {} {}",
+                        key.id.yellow(),
+                        key.name.bold(),
+                        key.prefix,
+                        state,
+                        key.created_at.dimmed(),
+                        format!("last used {}", last_used).dimmed()
+                    );
+                }
+            }
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+        KeysAction::Revoke { id } => match client.revoke_key(&id).await {
+            Ok(()) => println!("{} API key {} revoked", "✓".green(), id.yellow()),
+            Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+        },
+    }
+
+    Ok(())
+}
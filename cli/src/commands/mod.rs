@@ -1,17 +1,27 @@
+pub mod admin;
 pub mod auth;
+pub mod chat;
 pub mod config;
 pub mod edit;
 pub mod edit_video;
 pub mod generate;
 pub mod generate_video;
 pub mod health;
+pub mod history;
 pub mod info;
+pub mod keys;
+pub mod models;
 
-pub use auth::{handle_login, handle_logout, handle_register, handle_status};
+pub use admin::handle_admin;
+pub use auth::{handle_change_email, handle_change_password, handle_delete_account, handle_forgot_password, handle_login, handle_logout, handle_register, handle_reset_password, handle_status, handle_webhook_secret, handle_whoami};
+pub use chat::handle_chat;
 pub use self::config::handle_config;
 pub use edit::handle_edit;
 pub use edit_video::handle_edit_video;
 pub use generate::handle_generate;
 pub use generate_video::handle_generate_video;
 pub use health::handle_health;
-pub use info::handle_info;
\ No newline at end of file
+pub use history::handle_history;
+pub use info::handle_info;
+pub use keys::handle_keys;
+pub use models::handle_models;
\ No newline at end of file
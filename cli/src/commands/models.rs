@@ -0,0 +1,24 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::api::GeminiClient;
+
+pub async fn handle_models(client: &GeminiClient) -> Result<()> {
+    match client.models().await {
+        Ok(response) => {
+            println!("{}", "Available models".bold());
+            for (kind, choice) in &response.models {
+                println!("  {}:", kind.cyan());
+                for model in &choice.allowed {
+                    if *model == choice.default {
+                        println!("    • {} {}", model.yellow(), "(default)".dimmed());
+                    } else {
+                        println!("    • {}", model);
+                    }
+                }
+            }
+        }
+        Err(e) => eprintln!("{} Error: {}", "✗".red(), e),
+    }
+    Ok(())
+}
@@ -2,4 +2,5 @@ pub mod api;
 pub mod commands;
 pub mod config;
 pub mod types;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod validation;
\ No newline at end of file
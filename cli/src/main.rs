@@ -4,8 +4,8 @@ use std::path::PathBuf;
 
 use geminipocket::{
     api::GeminiClient,
-    commands::{self, config::ConfigAction},
-    types::Config,
+    commands::{self, admin::AdminAction, config::ConfigAction, history::HistoryAction, keys::KeysAction},
+    types::{Config, ImageSettings},
 };
 
 #[derive(Parser)]
@@ -39,6 +39,12 @@ EXAMPLES:
    Edit image into video:
      geminipocket-cli edit-video photo.png \"make it dance and spin\"
 
+   List recent generations:
+     geminipocket-cli history list --kind generate --limit 10
+
+   Create an extra API key for a CI job:
+     geminipocket-cli keys create \"ci\"
+
    Configure default output directory:
      geminipocket-cli config set output_dir ~/Videos/AI
 ")]
@@ -64,6 +70,13 @@ enum Commands {
         #[arg(value_name = "PROMPT")]
         prompt: String,
 
+        /// Number of images to generate (1-4); each is saved with a numbered name
+        #[arg(long, short = 'c', value_name = "N", default_value = "1", value_parser = clap::value_parser!(u32).range(1..=4))]
+        count: u32,
+
+        #[command(flatten)]
+        settings: ImageSettings,
+
         /// Custom filename (timestamp will be added)
         #[arg(short, long, value_name = "NAME")]
         name: Option<String>,
@@ -71,18 +84,26 @@ enum Commands {
         /// Save to current directory (overrides config)
         #[arg(short, long)]
         save: bool,
+
+        /// Model to use (see `geminipocket-cli models`); the server default if omitted
+        #[arg(long, short = 'm', value_name = "MODEL")]
+        model: Option<String>,
     },
     
     /// Transform an existing image using AI
     Edit {
-        /// Path to the image file (PNG, JPG, GIF, WebP)
-        #[arg(value_name = "IMAGE")]
-        image: PathBuf,
+        /// Image files (PNG, JPG, GIF, WebP); give several to combine them,
+        /// in the order the prompt refers to them
+        #[arg(value_name = "IMAGE", required = true, num_args = 1..)]
+        images: Vec<PathBuf>,
 
-        /// Describe how to transform the image
-        #[arg(value_name = "PROMPT")]
+        /// Describe how to transform the image(s)
+        #[arg(value_name = "PROMPT", required = true)]
         prompt: String,
 
+        #[command(flatten)]
+        settings: ImageSettings,
+
         /// Custom filename (timestamp will be added)
         #[arg(short, long, value_name = "NAME")]
         name: Option<String>,
@@ -90,6 +111,33 @@ enum Commands {
         /// Save to current directory (overrides config)
         #[arg(short, long)]
         save: bool,
+
+        /// Model to use (see `geminipocket-cli models`); the server default if omitted
+        #[arg(long, short = 'm', value_name = "MODEL")]
+        model: Option<String>,
+    },
+
+    /// Refine an image over several prompts; each turn builds on the last
+    Chat {
+        /// Images to start from (PNG, JPG, GIF, WebP); without any, the
+        /// first prompt creates one
+        #[arg(value_name = "IMAGE")]
+        images: Vec<PathBuf>,
+
+        #[command(flatten)]
+        settings: ImageSettings,
+
+        /// Custom filename (turn number and timestamp will be added)
+        #[arg(short, long, value_name = "NAME")]
+        name: Option<String>,
+
+        /// Save to current directory (overrides config)
+        #[arg(short, long)]
+        save: bool,
+
+        /// Model to use (see `geminipocket-cli models`); the server default if omitted
+        #[arg(long, short = 'm', value_name = "MODEL")]
+        model: Option<String>,
     },
 
     /// Generate a video from text description
@@ -107,6 +155,10 @@ enum Commands {
         #[arg(short, long)]
         save: bool,
 
+        /// Model to use (see `geminipocket-cli models`); the server default if omitted
+        #[arg(long, short = 'm', value_name = "MODEL")]
+        model: Option<String>,
+
         /// Negative prompt to avoid certain elements
         #[arg(long, value_name = "NEG_PROMPT")]
         negative_prompt: Option<String>,
@@ -118,6 +170,10 @@ enum Commands {
         /// Resolution (720p or 1080p)
         #[arg(long, value_name = "RES", default_value = "720p")]
         resolution: String,
+
+        /// Return immediately and have the result POSTed to this URL
+        #[arg(long, value_name = "URL")]
+        callback_url: Option<String>,
     },
 
     /// Transform an existing image into a video using AI
@@ -139,6 +195,10 @@ enum Commands {
         #[arg(short, long)]
         save: bool,
 
+        /// Model to use (see `geminipocket-cli models`); the server default if omitted
+        #[arg(long, short = 'm', value_name = "MODEL")]
+        model: Option<String>,
+
         /// Negative prompt to avoid certain elements
         #[arg(long, value_name = "NEG_PROMPT")]
         negative_prompt: Option<String>,
@@ -150,8 +210,15 @@ enum Commands {
         /// Resolution (720p or 1080p)
         #[arg(long, value_name = "RES", default_value = "720p")]
         resolution: String,
+
+        /// Return immediately and have the result POSTed to this URL
+        #[arg(long, value_name = "URL")]
+        callback_url: Option<String>,
     },
     
+    /// List the models each kind of request can use
+    Models,
+
     /// Configure settings (API URL, output directory)
     Config {
         #[command(subcommand)]
@@ -164,6 +231,24 @@ enum Commands {
     /// Show API version and available endpoints
     Info,
 
+    /// Browse past generations (list, show, download)
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+
+    /// Manage API keys (create, list, revoke)
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
+
+    /// Manage users (admin accounts only)
+    Admin {
+        #[command(subcommand)]
+        action: AdminAction,
+    },
+
     /// Authentication commands (login, register, logout, status)
     Auth {
         #[command(subcommand)]
@@ -182,8 +267,37 @@ enum AuthAction {
     /// Logout and remove stored credentials
     Logout,
 
-    /// Show authentication status
+    /// Show authentication status from the local config
     Status,
+
+    /// Show your account, plan limits and recent usage
+    Whoami,
+
+    /// Change the email address of your account
+    ChangeEmail,
+
+    /// Change your password
+    ChangePassword,
+
+    /// Permanently delete your account and everything stored for it
+    DeleteAccount {
+        /// Skip the confirmation prompt
+        #[arg(long)]
+        yes: bool,
+    },
+
+    /// Email yourself a password reset token
+    ForgotPassword,
+
+    /// Set a new password using an emailed reset token
+    ResetPassword,
+
+    /// Show the secret used to sign webhook callbacks
+    WebhookSecret {
+        /// Replace the secret with a new one
+        #[arg(long)]
+        rotate: bool,
+    },
 }
 
 #[tokio::main]
@@ -201,10 +315,13 @@ async fn main() -> Result<()> {
     };
 
     match cli.command {
-        Commands::Generate { prompt, name, save } => {
+        Commands::Generate { prompt, count, settings, name, save, model } => {
             commands::handle_generate(
                 &client,
                 &prompt,
+                model.as_deref(),
+                count,
+                &settings,
                 output_dir,
                 name.as_deref(),
                 save,
@@ -212,15 +329,37 @@ async fn main() -> Result<()> {
             .await?;
         }
         Commands::Edit {
-            image,
+            images,
             prompt,
+            settings,
             name,
             save,
+            model,
         } => {
             commands::handle_edit(
                 &client,
-                &image,
+                &images,
                 &prompt,
+                model.as_deref(),
+                &settings,
+                output_dir,
+                name.as_deref(),
+                save,
+            )
+            .await?;
+        }
+        Commands::Chat {
+            images,
+            settings,
+            name,
+            save,
+            model,
+        } => {
+            commands::handle_chat(
+                &client,
+                &images,
+                model.as_deref(),
+                &settings,
                 output_dir,
                 name.as_deref(),
                 save,
@@ -231,19 +370,23 @@ async fn main() -> Result<()> {
             prompt,
             name,
             save,
+            model,
             negative_prompt,
             aspect_ratio,
             resolution,
+            callback_url,
         } => {
             commands::handle_generate_video(
                 &client,
                 &prompt,
+                model.as_deref(),
                 output_dir,
                 name.as_deref(),
                 save,
                 negative_prompt.as_deref(),
                 Some(&aspect_ratio),
                 Some(&resolution),
+                callback_url.as_deref(),
             )
             .await?;
         }
@@ -252,23 +395,30 @@ async fn main() -> Result<()> {
             prompt,
             name,
             save,
+            model,
             negative_prompt,
             aspect_ratio,
             resolution,
+            callback_url,
         } => {
             commands::handle_edit_video(
                 &client,
                 &image,
                 &prompt,
+                model.as_deref(),
                 output_dir,
                 name.as_deref(),
                 save,
                 negative_prompt.as_deref(),
                 Some(&aspect_ratio),
                 Some(&resolution),
+                callback_url.as_deref(),
             )
             .await?;
         }
+        Commands::Models => {
+            commands::handle_models(&client).await?;
+        }
         Commands::Config { action } => {
             commands::handle_config(action)?;
         }
@@ -278,6 +428,15 @@ async fn main() -> Result<()> {
         Commands::Info => {
             commands::handle_info(&client).await?;
         }
+        Commands::History { action } => {
+            commands::handle_history(&client, action, output_dir).await?;
+        }
+        Commands::Keys { action } => {
+            commands::handle_keys(&client, action).await?;
+        }
+        Commands::Admin { action } => {
+            commands::handle_admin(&client, action).await?;
+        }
         Commands::Auth { action } => {
             match action {
                 AuthAction::Register => {
@@ -292,6 +451,27 @@ async fn main() -> Result<()> {
                 AuthAction::Status => {
                     commands::handle_status(&config)?;
                 }
+                AuthAction::Whoami => {
+                    commands::handle_whoami(&client).await?;
+                }
+                AuthAction::ChangeEmail => {
+                    commands::handle_change_email(&client, &mut config).await?;
+                }
+                AuthAction::ChangePassword => {
+                    commands::handle_change_password(&client, &config).await?;
+                }
+                AuthAction::DeleteAccount { yes } => {
+                    commands::handle_delete_account(&client, &mut config, yes).await?;
+                }
+                AuthAction::ForgotPassword => {
+                    commands::handle_forgot_password(&client).await?;
+                }
+                AuthAction::ResetPassword => {
+                    commands::handle_reset_password(&client, &mut config).await?;
+                }
+                AuthAction::WebhookSecret { rotate } => {
+                    commands::handle_webhook_secret(&client, rotate).await?;
+                }
             }
         }
     }
@@ -1,25 +1,122 @@
+use clap::Args;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::PathBuf;
 
 #[derive(Serialize, Deserialize)]
 pub struct GenerateRequest {
     pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    #[serde(flatten)]
+    pub settings: ImageSettings,
+}
+
+/// Generation settings for `generate` and `edit`. They are sent with the
+/// request and embedded in the saved PNG.
+#[derive(Args, Serialize, Deserialize, Default, Clone)]
+pub struct ImageSettings {
+    /// Aspect ratio: 1:1, 2:3, 3:2, 3:4, 4:3, 4:5, 5:4, 9:16, 16:9 or 21:9
+    #[arg(long, value_name = "RATIO")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<String>,
+
+    /// Seed for more repeatable results (0-2147483647)
+    #[arg(long, value_name = "N")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+
+    /// Sampling temperature (0.0-2.0); lower is more predictable
+    #[arg(long, value_name = "T")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+
+    /// What the model may return: image, text or both, comma-separated
+    #[arg(long = "modalities", value_name = "LIST", value_delimiter = ',')]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_modalities: Option<Vec<String>>,
 }
 
+/// The text parts of an `/edit` upload; the images go as file parts.
 #[derive(Serialize, Deserialize)]
 pub struct EditRequest {
     pub prompt: String,
-    pub image: String,
-    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub settings: ImageSettings,
 }
 
 #[derive(Deserialize)]
 pub struct ApiResponse {
     pub success: bool,
+    pub asset_id: Option<String>,
+    pub asset_url: Option<String>,
+    /// Only present when the request asked for inline base64.
     pub image: Option<String>,
     #[allow(dead_code)]
     pub mime_type: Option<String>,
     pub error: Option<String>,
+    /// Every image of the response; the fields above describe the first.
+    #[serde(default)]
+    pub images: Vec<ImageItem>,
+    /// Text the model sent along with the images.
+    pub text: Option<String>,
+}
+
+/// The text parts of a `POST /sessions` upload; starting images go as
+/// file parts.
+#[derive(Serialize, Deserialize)]
+pub struct CreateSessionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SessionResponse {
+    pub session_id: String,
+    pub model: String,
+    pub max_turns: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TurnRequest {
+    pub prompt: String,
+    #[serde(flatten)]
+    pub settings: ImageSettings,
+}
+
+/// A turn's result: an `/edit` response plus where it sits in the session.
+#[derive(Deserialize)]
+pub struct TurnResponse {
+    pub turn: u32,
+    #[serde(flatten)]
+    pub result: ApiResponse,
+}
+
+#[derive(Deserialize)]
+pub struct ImageItem {
+    #[allow(dead_code)]
+    pub asset_id: String,
+    pub asset_url: String,
+    #[allow(dead_code)]
+    pub mime_type: String,
+    pub image: Option<String>,
+}
+
+/// The models one request kind accepts; `default` is used when none is given.
+#[derive(Deserialize)]
+pub struct ModelChoice {
+    pub default: String,
+    pub allowed: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ModelsResponse {
+    pub models: BTreeMap<String, ModelChoice>,
 }
 
 #[derive(Deserialize)]
@@ -33,6 +130,8 @@ pub struct ApiInfo {
 pub struct HealthResponse {
     pub status: String,
     pub timestamp: Option<f64>,
+    #[serde(default)]
+    pub checks: BTreeMap<String, bool>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -53,43 +152,215 @@ pub struct RegisterRequest {
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_name: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct AuthResponse {
     pub success: bool,
     pub api_key: Option<String>,
+    #[allow(dead_code)]
+    pub key_id: Option<String>,
     pub error: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Serialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct MessageResponse {
+    pub message: String,
+    /// Set by `/password/reset`.
+    pub revoked_keys: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct Plan {
+    pub name: String,
+    /// Limits by request kind, e.g. `{"image": {"per_minute": 10, "per_day": 200}}`.
+    pub limits: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+#[derive(Deserialize)]
+pub struct Account {
+    pub email: String,
+    pub created_at: String,
+    pub plan: Plan,
+}
+
+#[derive(Deserialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+#[derive(Deserialize)]
+pub struct UsageSummary {
+    pub days: u32,
+    pub totals: BTreeMap<String, UsageTotals>,
+}
+
+#[derive(Deserialize)]
+pub struct AccountResponse {
+    pub user: Account,
+    pub usage: UsageSummary,
+}
+
+#[derive(Serialize)]
+pub struct UpdateAccountRequest {
+    pub current_password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_password: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateAccountResponse {
+    pub email: String,
+    pub sessions_revoked: bool,
+}
+
+#[derive(Serialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+/// A user as listed by the admin routes.
+#[derive(Deserialize)]
+pub struct AdminUser {
+    pub id: i32,
+    pub email: String,
+    pub is_admin: bool,
+    pub disabled_at: Option<String>,
+    pub disabled_reason: Option<String>,
+    pub created_at: String,
+    pub active_keys: u32,
+}
+
+#[derive(Deserialize)]
+pub struct AdminUserList {
+    pub users: Vec<AdminUser>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AdminUserDetail {
+    pub user: AdminUser,
+    pub plan: Option<Plan>,
+    pub keys: Vec<ApiKey>,
+    pub usage: UsageSummary,
+}
+
+#[derive(Deserialize)]
+pub struct AdminUserResponse {
+    pub user: AdminUser,
+}
+
+#[derive(Serialize)]
+pub struct DisableUserRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeKeysResponse {
+    pub revoked: u32,
+}
+
+#[derive(Deserialize)]
+pub struct ResetQuotaResponse {
+    pub cleared: u64,
+}
+
+#[derive(Deserialize)]
+pub struct UsageDay {
+    pub day: String,
+    pub kind: String,
+    pub requests: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    pub output_bytes: u64,
+}
+
+#[derive(Deserialize)]
+pub struct UsageReport {
+    pub days: u32,
+    pub usage: Vec<UsageDay>,
+    pub totals: BTreeMap<String, UsageTotals>,
+}
+
+#[derive(Serialize)]
+pub struct CreateKeyRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ApiKeyList {
+    pub keys: Vec<ApiKey>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatedKeyResponse {
+    pub key: ApiKey,
+    pub api_key: String,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct GenerateVideoRequest {
     pub prompt: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub negative_prompt: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aspect_ratio: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resolution: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
 }
 
+/// The text parts of an `/edit_video` upload; the image goes as a file part.
 #[derive(Serialize, Deserialize)]
 pub struct EditVideoRequest {
     pub prompt: String,
-    pub image: String,
-    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub negative_prompt: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aspect_ratio: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resolution: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct VideoOperationResponse {
     pub success: bool,
-    pub operation_name: Option<String>,
+    pub job_id: Option<String>,
     pub error: Option<String>,
 }
 
@@ -97,7 +368,8 @@ pub struct VideoOperationResponse {
 pub struct VideoStatusResponse {
     pub success: bool,
     pub done: Option<bool>,
-    pub video_uri: Option<String>,
+    pub asset_id: Option<String>,
+    pub video_url: Option<String>,
     pub error: Option<String>,
 }
 
@@ -106,4 +378,83 @@ pub struct ApiVideoResponse {
     pub success: bool,
     pub video_uri: Option<String>,
     pub error: Option<String>,
-}
\ No newline at end of file
+}
+#[derive(Deserialize)]
+pub struct WebhookSecretResponse {
+    pub webhook_secret: String,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryItem {
+    pub id: String,
+    pub kind: String,
+    pub model: String,
+    pub prompt: String,
+    pub status: String,
+    pub error_code: Option<String>,
+    pub latency_ms: Option<u64>,
+    pub output_bytes: Option<u64>,
+    pub created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryPage {
+    pub items: Vec<HistoryItem>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryDetail {
+    #[serde(flatten)]
+    pub item: HistoryItem,
+    pub parameters: serde_json::Value,
+    pub download_url: Option<String>,
+    /// Every stored output. Servers that predate it only give `download_url`.
+    #[serde(default)]
+    pub assets: Vec<HistoryAsset>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryAsset {
+    pub asset_id: String,
+    pub asset_url: String,
+    pub mime_type: String,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryDetailResponse {
+    pub generation: HistoryDetail,
+}
+
+#[derive(Deserialize)]
+pub struct ErrorEnvelope {
+    pub error: ErrorBody,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(default)]
+    pub retryable: bool,
+    #[serde(default)]
+    pub details: Option<serde_json::Value>,
+}
+
+/// Structured error returned by the worker for any non-2xx response.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: u16,
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+    pub details: Option<serde_json::Value>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for ApiError {}
@@ -1,17 +1,13 @@
 use anyhow::Result;
-use base64::{engine::general_purpose::STANDARD, Engine};
-use reqwest::Client;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 pub fn save_image(
-    base64_data: &str,
+    image_data: &[u8],
     output_dir: Option<&Path>,
     filename: Option<&str>,
     save_to_current: bool,
 ) -> Result<PathBuf> {
-    let image_data = STANDARD.decode(base64_data)?;
-
     let output_dir = if save_to_current {
         PathBuf::from(".")
     } else if let Some(dir) = output_dir {
@@ -31,14 +27,12 @@ pub fn save_image(
     Ok(output_path)
 }
 
-pub async fn save_video(
-    video_uri: &str,
+pub fn save_video(
+    video_data: &[u8],
     output_dir: Option<&Path>,
     filename: Option<&str>,
     save_to_current: bool,
 ) -> Result<PathBuf> {
-    let client = Client::new();
-
     let output_dir = if save_to_current {
         PathBuf::from(".")
     } else if let Some(dir) = output_dir {
@@ -54,19 +48,123 @@ pub async fn save_video(
     let final_filename = format!("{}_{}.mp4", filename, timestamp);
     let output_path = output_dir.join(final_filename);
 
-    let response = client.get(video_uri).send().await?;
-    let video_data = response.bytes().await?;
-
     fs::write(&output_path, video_data)?;
     Ok(output_path)
 }
 
-pub fn detect_mime_type(path: &Path) -> &'static str {
-    match path.extension().and_then(|s| s.to_str()) {
+/// The image type `data` starts with, falling back to the extension of
+/// `path`. The server checks the bytes again, so this is only the label.
+pub fn detect_mime_type(data: &[u8], path: &Path) -> &'static str {
+    if data.starts_with(PNG_SIGNATURE) {
+        return "image/png";
+    }
+    if data.starts_with(b"\xff\xd8\xff") {
+        return "image/jpeg";
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return "image/gif";
+    }
+    if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        return "image/webp";
+    }
+    match path.extension().and_then(|s| s.to_str()).map(str::to_lowercase).as_deref() {
         Some("png") => "image/png",
         Some("jpg") | Some("jpeg") => "image/jpeg",
         Some("gif") => "image/gif",
         Some("webp") => "image/webp",
-        _ => "image/jpeg",
+        _ => "application/octet-stream",
+    }
+}
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// IHDR is always the first chunk: signature, length, type, 13 bytes of
+/// data and the CRC.
+const PNG_IHDR_END: usize = 33;
+
+/// Returns a copy of `image` with an `iTXt` chunk per entry right after
+/// IHDR, so viewers and `exiftool` show how it was made. Anything that is
+/// not a PNG is returned unchanged.
+pub fn with_png_text(image: &[u8], entries: &[(&str, &str)]) -> Vec<u8> {
+    if !image.starts_with(PNG_SIGNATURE) || image.len() < PNG_IHDR_END || &image[12..16] != b"IHDR" {
+        return image.to_vec();
+    }
+
+    let mut output = Vec::with_capacity(image.len() + 256);
+    output.extend_from_slice(&image[..PNG_IHDR_END]);
+    for (keyword, text) in entries {
+        // Keyword, NUL, no compression, empty language tag and translated
+        // keyword, then the UTF-8 text.
+        let mut data = Vec::with_capacity(keyword.len() + text.len() + 5);
+        data.extend_from_slice(keyword.as_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data.extend_from_slice(text.as_bytes());
+
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let chunk_start = output.len();
+        output.extend_from_slice(b"iTXt");
+        output.extend_from_slice(&data);
+        let crc = crc32(&output[chunk_start..]);
+        output.extend_from_slice(&crc.to_be_bytes());
     }
-}
\ No newline at end of file
+    output.extend_from_slice(&image[PNG_IHDR_END..]);
+    output
+}
+
+/// The CRC-32 PNG chunks use (ISO 3309, as in zlib).
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn png() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbImage::new(2, 3)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_crc32_matches_png_chunks() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_with_png_text_adds_chunks_after_ihdr() {
+        let original = png();
+        let tagged = with_png_text(&original, &[("Prompt", "a red fox"), ("Model", "gemini")]);
+
+        let chunk = &tagged[PNG_IHDR_END..];
+        let length = u32::from_be_bytes(chunk[..4].try_into().unwrap()) as usize;
+        assert_eq!(&chunk[4..8], b"iTXt");
+        assert_eq!(&chunk[8..8 + length], b"Prompt\0\0\0\0\0a red fox");
+        let crc = u32::from_be_bytes(chunk[8 + length..12 + length].try_into().unwrap());
+        assert_eq!(crc, crc32(&chunk[4..8 + length]));
+        assert!(tagged.ends_with(&original[PNG_IHDR_END..]));
+
+        // The tagged file must still decode as the same image.
+        let decoded = image::load_from_memory(&tagged).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 3));
+    }
+
+    #[test]
+    fn test_with_png_text_leaves_other_input_alone() {
+        let jpeg = b"\xff\xd8\xff\xe0 not a png".to_vec();
+        assert_eq!(with_png_text(&jpeg, &[("Prompt", "x")]), jpeg);
+
+        let truncated = &png()[..20];
+        assert_eq!(with_png_text(truncated, &[("Prompt", "x")]), truncated);
+    }
+}
@@ -0,0 +1,107 @@
+// Client-side copy of the worker's registration rules, so `auth register`
+// can re-prompt instead of round-tripping. The worker stays authoritative.
+
+pub const MIN_PASSWORD_CHARS: usize = 8;
+
+/// bcrypt ignores everything past 72 bytes.
+pub const MAX_PASSWORD_BYTES: usize = 72;
+
+const MAX_EMAIL_LEN: usize = 254;
+const MAX_LOCAL_PART_LEN: usize = 64;
+
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+pub fn check_email(email: &str) -> Option<String> {
+    if email.is_empty() {
+        return Some("Email is required".to_string());
+    }
+    if email.len() > MAX_EMAIL_LEN {
+        return Some(format!("Email must be at most {} characters", MAX_EMAIL_LEN));
+    }
+
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && local.len() <= MAX_LOCAL_PART_LEN
+                && !domain.contains('@')
+                && domain.contains('.')
+                && domain.split('.').all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+                && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+        }
+        None => false,
+    };
+    (!valid).then(|| "Email address is not valid".to_string())
+}
+
+pub fn check_password(password: &str, email: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if password.chars().count() < MIN_PASSWORD_CHARS {
+        errors.push(format!("Password must be at least {} characters", MIN_PASSWORD_CHARS));
+    }
+    if password.len() > MAX_PASSWORD_BYTES {
+        errors.push(format!("Password must be at most {} bytes", MAX_PASSWORD_BYTES));
+    }
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+    if !password.is_empty() && (!has_letter || !has_digit) {
+        errors.push("Password must contain at least one letter and one digit".to_string());
+    }
+    if !email.is_empty() && password.to_lowercase().contains(email) {
+        errors.push("Password must not contain your email address".to_string());
+    }
+
+    errors
+}
+
+// Mirrors the worker's cases in backend/worker/src/validation.rs, so the two
+// copies cannot drift apart unnoticed.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOO_SHORT: &str = "Password must be at least 8 characters";
+    const TOO_LONG: &str = "Password must be at most 72 bytes";
+    const TOO_WEAK: &str = "Password must contain at least one letter and one digit";
+    const CONTAINS_EMAIL: &str = "Password must not contain your email address";
+
+    #[test]
+    fn test_email_syntax_and_normalization() {
+        assert_eq!(normalize_email("  User@Example.COM "), "user@example.com");
+        assert_eq!(check_email("user@example.com"), None);
+        assert_eq!(check_email("a.b+tag@mail.example.co.uk"), None);
+
+        for bad in ["user", "user@", "@example.com", "user@example", "user@@example.com", "us er@example.com", "user@example..com", "user@-example.com", "user@example-.com"] {
+            assert_eq!(check_email(bad).as_deref(), Some("Email address is not valid"), "{}", bad);
+        }
+        assert_eq!(check_email("").as_deref(), Some("Email is required"));
+        let long = format!("{}@example.com", "a".repeat(250));
+        assert_eq!(check_email(&long).as_deref(), Some("Email must be at most 254 characters"));
+    }
+
+    #[test]
+    fn test_password_policy() {
+        assert!(check_password("correct horse 42", "user@example.com").is_empty());
+        assert_eq!(check_password("", ""), vec![TOO_SHORT]);
+        assert_eq!(check_password("abc1", ""), vec![TOO_SHORT]);
+        assert_eq!(check_password("abcdefghij", ""), vec![TOO_WEAK]);
+        assert_eq!(check_password("1234567890", ""), vec![TOO_WEAK]);
+        assert_eq!(check_password(&format!("a1{}", "x".repeat(71)), ""), vec![TOO_LONG]);
+        assert_eq!(check_password("User@Example.com1", "user@example.com"), vec![CONTAINS_EMAIL]);
+    }
+
+    #[test]
+    fn test_bcrypt_limit_counts_bytes() {
+        // 26 characters but 72 bytes: at the limit. One more byte is over.
+        let at_limit = format!("123{}", "€".repeat(23));
+        assert_eq!(at_limit.len(), MAX_PASSWORD_BYTES);
+        assert!(!check_password(&at_limit, "").iter().any(|error| error == TOO_LONG));
+        let over = format!("1234{}", "€".repeat(23));
+        assert!(check_password(&over, "").iter().any(|error| error == TOO_LONG));
+
+        // Length is counted in characters, so short multibyte input is too short.
+        assert_eq!(check_password("éééééé1", ""), vec![TOO_SHORT]);
+    }
+}
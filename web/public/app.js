@@ -1,5 +1,9 @@
-let currentApiKey = localStorage.getItem('apiKey');
+// The browser only ever holds a short-lived session token and a rotating
+// refresh token; the permanent API key stays with the CLI.
+let sessionToken = localStorage.getItem('sessionToken');
+let refreshToken = localStorage.getItem('refreshToken');
 let currentUser = localStorage.getItem('userEmail');
+localStorage.removeItem('apiKey');
 
 function showSection(sectionId, buttonElement) {
     document.querySelectorAll('.section').forEach(section => {
@@ -145,21 +149,18 @@ async function handleLogin(event) {
             headers: {
                 'Content-Type': 'application/json',
             },
-            body: JSON.stringify({ email, password }),
+            body: JSON.stringify({ email, password, session: true }),
         });
 
         const data = await response.json();
 
         if (data.success) {
-            currentApiKey = data.api_key;
-            currentUser = email;
-            localStorage.setItem('apiKey', currentApiKey);
-            localStorage.setItem('userEmail', currentUser);
+            saveSession(data, email);
 
             updateAuthUI();
             showStatus('login-status', 'Login successful!', 'success');
         } else {
-            showStatus('login-status', data.error || 'Login failed', 'error');
+            showStatus('login-status', errorMessage(data, 'Login failed'), 'error');
         }
     } catch (error) {
         showStatus('login-status', 'Network error. Please try again.', 'error');
@@ -188,32 +189,102 @@ async function handleRegister(event) {
             headers: {
                 'Content-Type': 'application/json',
             },
-            body: JSON.stringify({ email, password }),
+            body: JSON.stringify({ email, password, session: true }),
         });
 
         const data = await response.json();
 
         if (data.success) {
-            currentApiKey = data.api_key;
-            currentUser = email;
-            localStorage.setItem('apiKey', currentApiKey);
-            localStorage.setItem('userEmail', currentUser);
+            // Registration starts a session directly, so no API key is
+            // minted for the browser.
+            saveSession(data, email);
 
             updateAuthUI();
             showStatus('register-status', 'Registration successful!', 'success');
         } else {
-            showStatus('register-status', data.error || 'Registration failed', 'error');
+            showStatus('register-status', errorMessage(data, 'Registration failed'), 'error');
         }
     } catch (error) {
         showStatus('register-status', 'Network error. Please try again.', 'error');
     }
 }
 
-function handleLogout() {
-    currentApiKey = null;
+function saveSession(data, email) {
+    sessionToken = data.session_token;
+    refreshToken = data.refresh_token;
+    localStorage.setItem('sessionToken', sessionToken);
+    localStorage.setItem('refreshToken', refreshToken);
+    if (email) {
+        currentUser = email;
+        localStorage.setItem('userEmail', currentUser);
+    }
+}
+
+function clearSession() {
+    sessionToken = null;
+    refreshToken = null;
     currentUser = null;
-    localStorage.removeItem('apiKey');
+    localStorage.removeItem('sessionToken');
+    localStorage.removeItem('refreshToken');
     localStorage.removeItem('userEmail');
+}
+
+// Trades the refresh token for a new session token. Returns false when the
+// session is over and the user has to log in again.
+async function refreshSession() {
+    if (!refreshToken) {
+        return false;
+    }
+    try {
+        const response = await fetch('/token/refresh', {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({ refresh_token: refreshToken }),
+        });
+        const data = await response.json();
+        if (data.success) {
+            saveSession(data);
+            return true;
+        }
+    } catch (error) {
+        return false;
+    }
+    clearSession();
+    updateAuthUI();
+    return false;
+}
+
+// fetch() with the session token attached, refreshing it once if the
+// server says it has expired.
+async function authFetch(url, options = {}) {
+    const withToken = () => ({
+        ...options,
+        headers: { ...(options.headers || {}), 'Authorization': `Bearer ${sessionToken}` },
+    });
+
+    let response = await fetch(url, withToken());
+    if (response.status === 401 && await refreshSession()) {
+        response = await fetch(url, withToken());
+    }
+    return response;
+}
+
+async function handleLogout() {
+    if (sessionToken) {
+        try {
+            await fetch('/logout', {
+                method: 'POST',
+                headers: {
+                    'Authorization': `Bearer ${sessionToken}`,
+                },
+            });
+        } catch (error) {
+            // The local session is cleared either way.
+        }
+    }
+    clearSession();
     updateAuthUI();
 }
 
@@ -222,7 +293,7 @@ function updateAuthUI() {
     const logoutBtn = document.getElementById('logout-btn');
     const nav = document.getElementById('main-nav');
 
-    if (currentUser && currentApiKey) {
+    if (currentUser && sessionToken) {
         userInfo.innerHTML = `<strong>Logged in as:</strong> ${currentUser}`;
         logoutBtn.style.display = 'inline-block';
         nav.style.display = 'flex';
@@ -247,18 +318,17 @@ async function generateImage() {
     btn.disabled = true;
     loading.style.display = 'inline-block';
 
-    if (!currentApiKey) {
+    if (!sessionToken) {
         showStatus('generate-status', 'Please login first', 'error');
         showSection('auth');
         return;
     }
 
     try {
-        const response = await fetch('/generate', {
+        const response = await authFetch('/generate', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
-                'Authorization': `Bearer ${currentApiKey}`,
             },
             body: JSON.stringify({ prompt }),
         });
@@ -266,13 +336,13 @@ async function generateImage() {
         const data = await response.json();
 
         if (data.success) {
-            document.getElementById('generated-image').src = `data:image/png;base64,${data.image}`;
+            document.getElementById('generated-image').src = await assetSrc(data.asset_url);
             document.getElementById('generated-image').style.display = 'block';
             document.getElementById('download-generate-btn').style.display = 'inline-block';
             document.getElementById('generate-result').style.display = 'block';
             showStatus('generate-status', 'Image generated successfully!', 'success');
         } else {
-            showStatus('generate-status', data.error || 'Failed to generate image', 'error');
+            showStatus('generate-status', errorMessage(data, 'Failed to generate image'), 'error');
         }
     } catch (error) {
         showStatus('generate-status', 'Network error. Please try again.', 'error');
@@ -301,7 +371,7 @@ async function editImage() {
     btn.disabled = true;
     loading.style.display = 'inline-block';
 
-    if (!currentApiKey) {
+    if (!sessionToken) {
         showStatus('edit-status', 'Please login first', 'error');
         showSection('auth');
         return;
@@ -311,11 +381,10 @@ async function editImage() {
         const file = fileInput.files[0];
         const base64 = await fileToBase64(file);
 
-        const response = await fetch('/edit', {
+        const response = await authFetch('/edit', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
-                'Authorization': `Bearer ${currentApiKey}`,
             },
             body: JSON.stringify({
                 prompt,
@@ -327,13 +396,13 @@ async function editImage() {
         const data = await response.json();
 
         if (data.success) {
-            document.getElementById('edited-image').src = `data:image/png;base64,${data.image}`;
+            document.getElementById('edited-image').src = await assetSrc(data.asset_url);
             document.getElementById('edit-result').style.display = 'block';
             document.getElementById('comparison-container').style.display = 'flex';
             document.getElementById('download-edit-btn').style.display = 'inline-block';
             showStatus('edit-status', 'Image edited successfully!', 'success');
         } else {
-            showStatus('edit-status', data.error || 'Failed to edit image', 'error');
+            showStatus('edit-status', errorMessage(data, 'Failed to edit image'), 'error');
         }
     } catch (error) {
         showStatus('edit-status', 'Network error. Please try again.', 'error');
@@ -359,18 +428,17 @@ async function generateVideo() {
     btn.disabled = true;
     loading.style.display = 'inline-block';
 
-    if (!currentApiKey) {
+    if (!sessionToken) {
         showStatus('generate-video-status', 'Please login first', 'error');
         showSection('auth');
         return;
     }
 
     try {
-        const response = await fetch('/generate_video?test_mode=false', {
+        const response = await authFetch('/generate_video?test_mode=false', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
-                'Authorization': `Bearer ${currentApiKey}`,
             },
             body: JSON.stringify({
                 prompt,
@@ -383,9 +451,9 @@ async function generateVideo() {
         const data = await response.json();
 
         if (data.success) {
-            pollVideoStatus(data.operation_name, 'generate-video');
+            pollVideoStatus(data.job_id, 'generate-video');
         } else {
-            showStatus('generate-video-status', data.error || 'Failed to start video generation', 'error');
+            showStatus('generate-video-status', errorMessage(data, 'Failed to start video generation'), 'error');
         }
     } catch (error) {
         showStatus('generate-video-status', 'Network error. Please try again.', 'error');
@@ -411,18 +479,17 @@ async function generateTestVideo() {
     btn.disabled = true;
     loading.style.display = 'inline-block';
 
-    if (!currentApiKey) {
+    if (!sessionToken) {
         showStatus('test-generate-video-status', 'Please login first', 'error');
         showSection('auth');
         return;
     }
 
     try {
-        const response = await fetch('/generate_video?test_mode=true', {
+        const response = await authFetch('/generate_video?test_mode=true', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
-                'Authorization': `Bearer ${currentApiKey}`,
             },
             body: JSON.stringify({
                 prompt,
@@ -435,9 +502,9 @@ async function generateTestVideo() {
         const data = await response.json();
 
         if (data.success) {
-            pollTestVideoStatus(data.operation_name, 'test-generate-video');
+            pollTestVideoStatus(data.job_id, 'test-generate-video');
         } else {
-            showStatus('test-generate-video-status', data.error || 'Failed to start test video generation', 'error');
+            showStatus('test-generate-video-status', errorMessage(data, 'Failed to start test video generation'), 'error');
         }
     } catch (error) {
         showStatus('test-generate-video-status', 'Network error. Please try again.', 'error');
@@ -469,7 +536,7 @@ async function editVideo() {
     btn.disabled = true;
     loading.style.display = 'inline-block';
 
-    if (!currentApiKey) {
+    if (!sessionToken) {
         showStatus('edit-video-status', 'Please login first', 'error');
         showSection('auth');
         return;
@@ -479,11 +546,10 @@ async function editVideo() {
         const file = fileInput.files[0];
         const base64 = await fileToBase64(file);
 
-        const response = await fetch('/edit_video', {
+        const response = await authFetch('/edit_video', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
-                'Authorization': `Bearer ${currentApiKey}`,
             },
             body: JSON.stringify({
                 prompt,
@@ -498,9 +564,9 @@ async function editVideo() {
         const data = await response.json();
 
         if (data.success) {
-            pollVideoStatus(data.operation_name, 'edit-video');
+            pollVideoStatus(data.job_id, 'edit-video');
         } else {
-            showStatus('edit-video-status', data.error || 'Failed to start video editing', 'error');
+            showStatus('edit-video-status', errorMessage(data, 'Failed to start video editing'), 'error');
         }
     } catch (error) {
         showStatus('edit-video-status', 'Network error. Please try again.', 'error');
@@ -510,7 +576,7 @@ async function editVideo() {
     }
 }
 
-async function pollVideoStatus(operationName, section) {
+async function pollVideoStatus(jobId, section) {
     const resultContainer = document.getElementById(`${section}-result`);
     const videoElement = document.getElementById(section === 'generate-video' ? 'generated-video' : 'edited-video');
     const downloadBtn = document.getElementById(section === 'generate-video' ? 'download-generate-video-btn' : 'download-edit-video-btn');
@@ -519,18 +585,14 @@ async function pollVideoStatus(operationName, section) {
 
     const pollInterval = setInterval(async () => {
         try {
-            const response = await fetch(`/video_status/${operationName}?test_mode=false`, {
-                headers: {
-                    'Authorization': `Bearer ${currentApiKey}`,
-                },
-            });
+            const response = await authFetch(`/video_status/${jobId}?test_mode=false`);
             const data = await response.json();
 
             if (data.success && data.done) {
                 clearInterval(pollInterval);
 
-                if (data.video) {
-                    videoElement.src = data.video;
+                if (data.video_url) {
+                    videoElement.src = await assetSrc(data.video_url);
                     videoElement.style.display = 'block';
                     resultContainer.style.display = 'block';
                     downloadBtn.style.display = 'inline-block';
@@ -545,7 +607,7 @@ async function pollVideoStatus(operationName, section) {
                 }
             } else if (!data.success) {
                 clearInterval(pollInterval);
-                showStatus(`${section}-status`, data.error || 'Video generation failed', 'error');
+                showStatus(`${section}-status`, errorMessage(data, 'Video generation failed'), 'error');
             }
         } catch (error) {
             clearInterval(pollInterval);
@@ -554,7 +616,7 @@ async function pollVideoStatus(operationName, section) {
     }, 10000);
 }
 
-async function pollTestVideoStatus(operationName, section) {
+async function pollTestVideoStatus(jobId, section) {
     const resultContainer = document.getElementById(`${section}-result`);
     const videoElement = document.getElementById('test-generated-video');
     const downloadBtn = document.getElementById('test-download-generate-video-btn');
@@ -563,18 +625,14 @@ async function pollTestVideoStatus(operationName, section) {
 
     const pollInterval = setInterval(async () => {
         try {
-            const response = await fetch(`/video_status/${operationName}?test_mode=true`, {
-                headers: {
-                    'Authorization': `Bearer ${currentApiKey}`,
-                },
-            });
+            const response = await authFetch(`/video_status/${jobId}?test_mode=true`);
             const data = await response.json();
 
             if (data.success && data.done) {
                 clearInterval(pollInterval);
 
-                if (data.video) {
-                    videoElement.src = data.video;
+                if (data.video_url) {
+                    videoElement.src = await assetSrc(data.video_url);
                     videoElement.style.display = 'block';
                     resultContainer.style.display = 'block';
                     downloadBtn.style.display = 'inline-block';
@@ -585,7 +643,7 @@ async function pollTestVideoStatus(operationName, section) {
                 }
             } else if (!data.success) {
                 clearInterval(pollInterval);
-                showStatus(`${section}-status`, data.error || 'Test video generation failed', 'error');
+                showStatus(`${section}-status`, errorMessage(data, 'Test video generation failed'), 'error');
             }
         } catch (error) {
             clearInterval(pollInterval);
@@ -594,6 +652,27 @@ async function pollTestVideoStatus(operationName, section) {
     }, 4000); // Poll at realistic interval for test mode
 }
 
+// Signed asset URLs can go straight into <img>/<video>. Unsigned ones (a
+// worker without SESSION_SECRET) need the session token, so they are fetched
+// here and shown through an object URL instead.
+async function assetSrc(url) {
+    if (new URL(url, window.location.href).searchParams.has('sig')) {
+        return url;
+    }
+    const response = await authFetch(url);
+    if (!response.ok) {
+        throw new Error(`Failed to load asset (${response.status})`);
+    }
+    return URL.createObjectURL(await response.blob());
+}
+
+function errorMessage(data, fallback) {
+    if (data && data.error) {
+        return data.error.message || data.error;
+    }
+    return fallback;
+}
+
 function showStatus(elementId, message, type) {
     const element = document.getElementById(elementId);
     element.textContent = message;